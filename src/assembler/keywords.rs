@@ -17,6 +17,7 @@ pub(super) const KEYWORDS: &[(&str, Keyword)] = &[
     ("ADD", BuiltinMnemonic(CpuInstr(ADD))),
     ("AF", Operand(Af)),
     ("AND", BuiltinMnemonic(CpuInstr(AND))),
+    ("ASSERT", BuiltinMnemonic(Directive(Assert))),
     ("B", Operand(B)),
     ("BC", Operand(Bc)),
     ("BIT", BuiltinMnemonic(CpuInstr(BIT))),
@@ -35,8 +36,12 @@ pub(super) const KEYWORDS: &[(&str, Keyword)] = &[
     ("E", Operand(E)),
     ("EI", BuiltinMnemonic(CpuInstr(EI))),
     ("ENDC", BuiltinMnemonic(Directive(Endc))),
+    ("ENDL", BuiltinMnemonic(Directive(Endl))),
     ("ENDM", BuiltinMnemonic(Directive(Endm))),
+    ("ENDU", BuiltinMnemonic(Directive(Endu))),
     ("EQU", BuiltinMnemonic(Directive(Equ))),
+    ("EQUS", BuiltinMnemonic(Directive(Equs))),
+    ("FAIL", BuiltinMnemonic(Directive(Fail))),
     ("H", Operand(H)),
     ("HALT", BuiltinMnemonic(CpuInstr(HALT))),
     ("HL", Operand(Hl)),
@@ -45,19 +50,26 @@ pub(super) const KEYWORDS: &[(&str, Keyword)] = &[
     ("IF", BuiltinMnemonic(Directive(If))),
     ("INC", BuiltinMnemonic(CpuInstr(INC))),
     ("INCLUDE", BuiltinMnemonic(Directive(Include))),
+    ("JMP", BuiltinMnemonic(CpuInstr(JMP))),
     ("JP", BuiltinMnemonic(CpuInstr(JP))),
     ("JR", BuiltinMnemonic(CpuInstr(JR))),
     ("L", Operand(L)),
     ("LD", BuiltinMnemonic(CpuInstr(LD))),
     ("LDHL", BuiltinMnemonic(CpuInstr(LDHL))),
+    ("LOAD", BuiltinMnemonic(Directive(Load))),
     ("MACRO", BuiltinMnemonic(Directive(Macro))),
     ("NC", Operand(Nc)),
+    ("NEXTU", BuiltinMnemonic(Directive(Nextu))),
     ("NOP", BuiltinMnemonic(CpuInstr(NOP))),
     ("NZ", Operand(Nz)),
     ("OR", BuiltinMnemonic(CpuInstr(OR))),
     ("ORG", BuiltinMnemonic(Directive(Org))),
     ("POP", BuiltinMnemonic(CpuInstr(POP))),
+    ("POPS", BuiltinMnemonic(Directive(Pops))),
+    ("PRINT", BuiltinMnemonic(Directive(Print))),
+    ("PRINTLN", BuiltinMnemonic(Directive(Println))),
     ("PUSH", BuiltinMnemonic(CpuInstr(PUSH))),
+    ("PUSHS", BuiltinMnemonic(Directive(Pushs))),
     ("RES", BuiltinMnemonic(CpuInstr(RES))),
     ("RET", BuiltinMnemonic(CpuInstr(RET))),
     ("RETI", BuiltinMnemonic(CpuInstr(RETI))),
@@ -80,6 +92,9 @@ pub(super) const KEYWORDS: &[(&str, Keyword)] = &[
     ("STOP", BuiltinMnemonic(CpuInstr(STOP))),
     ("SUB", BuiltinMnemonic(CpuInstr(SUB))),
     ("SWAP", BuiltinMnemonic(CpuInstr(SWAP))),
+    ("UNION", BuiltinMnemonic(Directive(Union))),
+    ("WARN", BuiltinMnemonic(Directive(Warn))),
+    ("WEAK", BuiltinMnemonic(Directive(Weak))),
     ("XOR", BuiltinMnemonic(CpuInstr(XOR))),
     ("Z", Operand(Z)),
 ];
@@ -92,17 +107,31 @@ pub(crate) enum BuiltinMnemonic {
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Directive {
+    Assert,
     Db,
     Ds,
     Dw,
     Endc,
+    Endl,
     Endm,
+    Endu,
     Equ,
+    Equs,
+    Fail,
     If,
     Include,
+    Load,
     Macro,
+    Nextu,
     Org,
+    Pops,
+    Print,
+    Println,
+    Pushs,
     Section,
+    Union,
+    Warn,
+    Weak,
 }
 
 impl BuiltinMnemonic {
@@ -110,7 +139,7 @@ impl BuiltinMnemonic {
         match self {
             BuiltinMnemonic::Directive(directive) => matches!(
                 directive,
-                Directive::Equ | Directive::Macro | Directive::Section
+                Directive::Equ | Directive::Equs | Directive::Macro | Directive::Section
             ),
             _ => false,
         }
@@ -203,6 +232,7 @@ pub(super) const DI: Mnemonic = Mnemonic::Nullary(0xf3);
 pub(super) const EI: Mnemonic = Mnemonic::Nullary(0xfb);
 pub(super) const HALT: Mnemonic = Mnemonic::Nullary(0x76);
 pub(super) const INC: Mnemonic = Mnemonic::IncDec(IncDec::Inc);
+pub(super) const JMP: Mnemonic = Mnemonic::Branch(BranchKind::Explicit(ExplicitBranch::Jmp));
 pub(super) const JP: Mnemonic = Mnemonic::Branch(BranchKind::Explicit(ExplicitBranch::Jp));
 pub(super) const JR: Mnemonic = Mnemonic::Branch(BranchKind::Explicit(ExplicitBranch::Jr));
 pub(super) const LD: Mnemonic = Mnemonic::Ld;
@@ -242,6 +272,7 @@ pub enum BranchKind {
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ExplicitBranch {
     Call,
+    Jmp,
     Jp,
     Jr,
 }
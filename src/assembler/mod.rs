@@ -3,7 +3,7 @@ use self::session::{ReentrancyActions, Session};
 use crate::codebase::{FileSystem, StdFileSystem};
 use crate::diagnostics::{Clause, Diagnostic, Tag};
 use crate::object::Object;
-use crate::{Config, DiagnosticsConfig, InputConfig};
+use crate::{Config, DiagnosticsConfig, InputConfig, PrintConfig};
 
 mod keywords;
 mod semantics;
@@ -40,7 +40,12 @@ impl<'r, 'a> Assembler<'r, 'a> {
             DiagnosticsConfig::Ignore => diagnostics_holder.get_or_insert(|_| {}),
             DiagnosticsConfig::Output(diagnostics) => *diagnostics,
         };
-        try_assemble(name, input, diagnostics)
+        let mut print_holder = None;
+        let print: &mut dyn FnMut(&str) = match &mut self.config.print {
+            PrintConfig::Stdout => print_holder.get_or_insert(|text: &str| print!("{}", text)),
+            PrintConfig::Custom(print) => *print,
+        };
+        try_assemble(name, input, diagnostics, print)
     }
 }
 
@@ -48,10 +53,14 @@ fn try_assemble<'a>(
     name: &str,
     input: &'a mut dyn FileSystem,
     output: &'a mut dyn FnMut(Diagnostic),
+    print: &'a mut dyn FnMut(&str),
 ) -> Option<Object> {
-    let mut session = Session::new(input, output);
+    let mut session = Session::new(input, output, print);
     match session.analyze_file(name.into(), None) {
-        Ok(()) => Some(Object(session.try_into_object_data())),
+        Ok(()) => {
+            session.finish();
+            Some(Object(session.try_into_object_data()))
+        }
         Err(error) => {
             output(Diagnostic {
                 clauses: vec![Clause {
@@ -113,6 +122,7 @@ mod tests {
         let mut config = Config {
             input: InputConfig::Custom(fs),
             diagnostics: DiagnosticsConfig::Output(&mut output),
+            print: PrintConfig::default(),
         };
         let mut assembler = Assembler::new(&mut config);
         assembler.assemble(path);
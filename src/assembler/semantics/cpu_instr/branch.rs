@@ -50,8 +50,12 @@ where
                             .0
                             .push(ExprOp::Atom(Atom::Location).with_span(span.clone()));
                         target.0.push(ExprOp::Binary(BinOp::Minus).with_span(span));
+                        self.session.emit_fragment(Fragment::Relative(target))
+                    }
+                    Branch::Explicit(ExplicitBranch::Jmp, target) => {
+                        let condition = condition.map(|(condition, _)| encode_condition(condition));
                         self.session
-                            .emit_fragment(Fragment::Immediate(target, Width::Byte))
+                            .emit_fragment(Fragment::Jump(condition, target))
                     }
                     Branch::Explicit(ExplicitBranch::Call, target) => {
                         self.session.emit_fragment(Fragment::Byte(match condition {
@@ -169,7 +173,7 @@ where
         }
         (BranchKind::Explicit(_), Some(BranchTarget::DerefHl(span))) => {
             Err(Message::RequiresConstantTarget {
-                mnemonic: diagnostics.strip_span(&kind.1),
+                mnemonic: diagnostics.strip_span(kind.1),
             }
             .at(span))
         }
@@ -252,14 +256,11 @@ mod tests {
         let nn = Name::Symbol(SymbolId(7));
         analyze(JR, vec![nn.into()]).expect_fragments(vec![
             Fragment::Byte(0x18),
-            Fragment::Immediate(
-                Expr(vec![
-                    ExprOp::Atom(Atom::Name(nn)).with_span(TokenId::Operand(0, 0).into()),
-                    ExprOp::Atom(Atom::Location).with_span(TokenId::Operand(0, 0).into()),
-                    ExprOp::Binary(BinOp::Minus).with_span(TokenId::Operand(0, 0).into()),
-                ]),
-                Width::Byte,
-            ),
+            Fragment::Relative(Expr(vec![
+                ExprOp::Atom(Atom::Name(nn)).with_span(TokenId::Operand(0, 0).into()),
+                ExprOp::Atom(Atom::Location).with_span(TokenId::Operand(0, 0).into()),
+                ExprOp::Binary(BinOp::Minus).with_span(TokenId::Operand(0, 0).into()),
+            ])),
         ])
     }
 
@@ -268,14 +269,11 @@ mod tests {
         let nn = Name::Symbol(SymbolId(7));
         analyze(JR, vec![Condition::Nz.into(), nn.into()]).expect_fragments(vec![
             Fragment::Byte(0x20),
-            Fragment::Immediate(
-                Expr(vec![
-                    ExprOp::Atom(Atom::Name(nn)).with_span(TokenId::Operand(1, 0).into()),
-                    ExprOp::Atom(Atom::Location).with_span(TokenId::Operand(1, 0).into()),
-                    ExprOp::Binary(BinOp::Minus).with_span(TokenId::Operand(1, 0).into()),
-                ]),
-                Width::Byte,
-            ),
+            Fragment::Relative(Expr(vec![
+                ExprOp::Atom(Atom::Name(nn)).with_span(TokenId::Operand(1, 0).into()),
+                ExprOp::Atom(Atom::Location).with_span(TokenId::Operand(1, 0).into()),
+                ExprOp::Binary(BinOp::Minus).with_span(TokenId::Operand(1, 0).into()),
+            ])),
         ])
     }
 
@@ -284,14 +282,11 @@ mod tests {
         let nn = Name::Symbol(SymbolId(7));
         analyze(JR, vec![Condition::Z.into(), nn.into()]).expect_fragments(vec![
             Fragment::Byte(0x28),
-            Fragment::Immediate(
-                Expr(vec![
-                    ExprOp::Atom(Atom::Name(nn)).with_span(TokenId::Operand(1, 0).into()),
-                    ExprOp::Atom(Atom::Location).with_span(TokenId::Operand(1, 0).into()),
-                    ExprOp::Binary(BinOp::Minus).with_span(TokenId::Operand(1, 0).into()),
-                ]),
-                Width::Byte,
-            ),
+            Fragment::Relative(Expr(vec![
+                ExprOp::Atom(Atom::Name(nn)).with_span(TokenId::Operand(1, 0).into()),
+                ExprOp::Atom(Atom::Location).with_span(TokenId::Operand(1, 0).into()),
+                ExprOp::Binary(BinOp::Minus).with_span(TokenId::Operand(1, 0).into()),
+            ])),
         ])
     }
 
@@ -300,14 +295,11 @@ mod tests {
         let nn = Name::Symbol(SymbolId(7));
         analyze(JR, vec![Condition::Nc.into(), nn.into()]).expect_fragments(vec![
             Fragment::Byte(0x30),
-            Fragment::Immediate(
-                Expr(vec![
-                    ExprOp::Atom(Atom::Name(nn)).with_span(TokenId::Operand(1, 0).into()),
-                    ExprOp::Atom(Atom::Location).with_span(TokenId::Operand(1, 0).into()),
-                    ExprOp::Binary(BinOp::Minus).with_span(TokenId::Operand(1, 0).into()),
-                ]),
-                Width::Byte,
-            ),
+            Fragment::Relative(Expr(vec![
+                ExprOp::Atom(Atom::Name(nn)).with_span(TokenId::Operand(1, 0).into()),
+                ExprOp::Atom(Atom::Location).with_span(TokenId::Operand(1, 0).into()),
+                ExprOp::Binary(BinOp::Minus).with_span(TokenId::Operand(1, 0).into()),
+            ])),
         ])
     }
 
@@ -316,17 +308,23 @@ mod tests {
         let nn = Name::Symbol(SymbolId(7));
         analyze(JR, vec![Condition::C.into(), nn.into()]).expect_fragments(vec![
             Fragment::Byte(0x38),
-            Fragment::Immediate(
-                Expr(vec![
-                    ExprOp::Atom(Atom::Name(nn)).with_span(TokenId::Operand(1, 0).into()),
-                    ExprOp::Atom(Atom::Location).with_span(TokenId::Operand(1, 0).into()),
-                    ExprOp::Binary(BinOp::Minus).with_span(TokenId::Operand(1, 0).into()),
-                ]),
-                Width::Byte,
-            ),
+            Fragment::Relative(Expr(vec![
+                ExprOp::Atom(Atom::Name(nn)).with_span(TokenId::Operand(1, 0).into()),
+                ExprOp::Atom(Atom::Location).with_span(TokenId::Operand(1, 0).into()),
+                ExprOp::Binary(BinOp::Minus).with_span(TokenId::Operand(1, 0).into()),
+            ])),
         ])
     }
 
+    #[test]
+    fn jmp_nc_e() {
+        let nn = Name::Symbol(SymbolId(7));
+        analyze(JMP, vec![Condition::Nc.into(), nn.into()]).expect_fragments(vec![Fragment::Jump(
+            Some(0x10),
+            name(nn, TokenId::Operand(1, 0)),
+        )])
+    }
+
     #[test]
     fn jp_deref_hl() {
         analyze(JP, vec![M::DerefHl.into()]).expect_fragments(vec![Fragment::Byte(0xe9)])
@@ -60,7 +60,7 @@ where
         Arg::Deref(BareArg::OperandKeyword(symbol, inner_span), outer_span) => {
             analyze_deref_operand_keyword((symbol, &inner_span), outer_span, diagnostics)
         }
-        Arg::String(_, span) => {
+        Arg::String(span) => {
             diagnostics.emit_diag(Message::StringInInstruction.at(span));
             Err(())
         }
@@ -259,7 +259,7 @@ pub mod tests {
     #[test]
     fn analyze_string_in_instruction() {
         let span = 0;
-        let parsed_expr = Arg::String("some_string".into(), span.into());
+        let parsed_expr = Arg::String(span.into());
         assert_eq!(
             analyze_operand(parsed_expr, Context::Other),
             Err(vec![Event::EmitDiag {
@@ -1,7 +1,8 @@
 use crate::assembler::keywords::Directive;
 use crate::assembler::semantics::*;
 use crate::diagnostics::*;
-use crate::object::{Fragment, Width};
+use crate::eval::Const;
+use crate::object::{Constraints, Fragment, SectionKind, Width};
 use crate::span::Source;
 
 pub(super) fn analyze_directive<'a, S: Analysis>(
@@ -35,20 +36,72 @@ where
 {
     fn analyze(self, directive: Directive) -> TokenStreamSemantics<'a, S> {
         match directive {
+            Directive::Assert => self.analyze_assert(),
             Directive::Equ => self.analyze_equ(),
+            Directive::Equs => self.analyze_equs(),
             Directive::Macro => self.analyze_macro(),
+            Directive::Nextu => self.analyze_nullary(Backend::next_union),
             Directive::Section => self.analyze_section(),
+            Directive::Union => self.analyze_nullary(Backend::begin_union),
             Directive::Db => self.analyze_data(Width::Byte),
             Directive::Ds => self.analyze_ds(),
             Directive::Dw => self.analyze_data(Width::Word),
             Directive::Endc => self.analyze_endc(),
+            Directive::Endl => self.analyze_nullary(Backend::end_load),
+            Directive::Endu => self.analyze_nullary(Backend::end_union),
             Directive::Endm => unimplemented!(),
+            Directive::Fail => self.analyze_user_diag(|message| Message::UserError { message }),
             Directive::If => self.analyze_if(),
             Directive::Include => self.analyze_include(),
+            Directive::Load => self.analyze_load(),
             Directive::Org => self.analyze_org(),
+            Directive::Pops => self.analyze_nullary(Backend::pop_section),
+            Directive::Print => self.analyze_print(""),
+            Directive::Println => self.analyze_print("\n"),
+            Directive::Pushs => self.analyze_nullary(Backend::push_section),
+            Directive::Warn => self.analyze_user_diag(|message| Message::UserWarning { message }),
+            Directive::Weak => self.analyze_weak(),
         }
     }
 
+    fn analyze_assert(mut self) -> TokenStreamSemantics<'a, S> {
+        let mut args = self.args.into_iter();
+        let condition = match args.next() {
+            Some(arg) => self.session.expect_const(arg),
+            None => {
+                self.session.session.emit_diag(
+                    Message::OperandCount {
+                        actual: 0,
+                        expected: 1,
+                    }
+                    .at(self.span),
+                );
+                return self.session;
+            }
+        };
+        let session = &mut self.session;
+        let message = args.next().map(|arg| session.expect_string(arg));
+        let extra = args.count();
+        if extra > 0 {
+            self.session.session.emit_diag(
+                Message::OperandCount {
+                    actual: 2 + extra,
+                    expected: 2,
+                }
+                .at(self.span),
+            );
+            return self.session;
+        }
+        if let (Ok(condition), Ok(message)) = (condition, message.transpose()) {
+            self.session.session.emit_assertion(
+                condition,
+                message.map(|(message, _)| message),
+                self.span,
+            )
+        }
+        self.session
+    }
+
     fn analyze_data(mut self, width: Width) -> TokenStreamSemantics<'a, S> {
         for arg in self.args {
             let expr = match self.session.expect_const(arg) {
@@ -75,22 +128,200 @@ where
     }
 
     fn analyze_equ(mut self) -> TokenStreamSemantics<'a, S> {
-        let (symbol, _) = self.label.take().unwrap();
+        let (symbol, (params, _)) = self.label.take().unwrap();
         if let Some(arg) = single_arg(self.span, self.args, self.session.session) {
             if let Ok(expr) = self.session.expect_const(arg) {
-                self.session
-                    .session
-                    .define_symbol(symbol, SymbolDef::Closure(expr))
+                let def = if params.is_empty() {
+                    SymbolDef::Closure(expr)
+                } else {
+                    SymbolDef::Function {
+                        params: params.len(),
+                        body: expr,
+                    }
+                };
+                self.session.session.define_symbol(symbol, def)
             }
         }
         self.session
     }
 
+    fn analyze_equs(mut self) -> TokenStreamSemantics<'a, S> {
+        let (symbol, _) = self.label.take().unwrap();
+        if let Some((string, _)) = single_string_arg(self.span, self.args, &mut self.session) {
+            self.session
+                .session
+                .define_symbol(symbol, SymbolDef::String(string))
+        }
+        self.session
+    }
+
     fn analyze_section(mut self) -> TokenStreamSemantics<'a, S> {
         let ident = self.label.take().unwrap().0;
         self.session
             .session
             .define_symbol(ident, SymbolDef::Section);
+        if self.args.is_empty() {
+            return self.session;
+        }
+        if let Ok(constraints) = self.analyze_constraints() {
+            self.session.session.constrain_section(constraints)
+        }
+        self.session
+    }
+
+    fn analyze_constraints(&mut self) -> Result<Constraints<S::Span>, ()> {
+        let mut constraints = Constraints::default();
+        let mut bank = None;
+        for arg in std::mem::take(&mut self.args) {
+            self.analyze_section_arg(arg, &mut constraints, &mut bank)?
+        }
+        if let Some((value, span)) = bank {
+            match constraints.kind.and_then(SectionKind::banks) {
+                Some(banks) if banks.contains(&value) => constraints.bank = Some(value as u16),
+                Some(banks) => {
+                    let (min, max) = banks.into_inner();
+                    self.session
+                        .session
+                        .emit_diag(Message::OutOfRange { value, min, max }.at(span));
+                    return Err(());
+                }
+                None => {
+                    self.session
+                        .session
+                        .emit_diag(Message::BankRequiresBankedKind.at(span));
+                    return Err(());
+                }
+            }
+        }
+        Ok(constraints)
+    }
+
+    fn analyze_section_arg(
+        &mut self,
+        arg: ParsedArg<S::Span>,
+        constraints: &mut Constraints<S::Span>,
+        bank: &mut Option<(i32, S::Span)>,
+    ) -> Result<(), ()> {
+        let expr = match arg {
+            ParsedArg::Bare(expr) => expr,
+            ParsedArg::Parenthesized(_, span) | ParsedArg::String(_, span) => {
+                return self.invalid_section_arg(span)
+            }
+            ParsedArg::Error => return Err(()),
+        };
+        let span = expr.span();
+        let (name, param) = match split_section_arg(expr) {
+            Some(result) => result,
+            None => return self.invalid_section_arg(span),
+        };
+        let is_duplicate = match (name.to_ascii_uppercase().as_str(), param) {
+            ("ALIGN", Some(param)) => {
+                let (value, value_span) = self.eval_section_arg(param)?;
+                if !(0..=16).contains(&value) {
+                    self.session.session.emit_diag(
+                        Message::OutOfRange {
+                            value,
+                            min: 0,
+                            max: 16,
+                        }
+                        .at(value_span),
+                    );
+                    return Err(());
+                }
+                constraints.align.replace(value as u8).is_some()
+            }
+            ("BANK", Some(param)) => bank.replace(self.eval_section_arg(param)?).is_some(),
+            ("KEEP", None) => std::mem::replace(&mut constraints.keep, true),
+            (keyword, param) => match section_kind(keyword) {
+                Some(kind) => {
+                    if let Some(param) = param {
+                        let addr = self.session.expect_const(ParsedArg::Bare(param))?;
+                        constraints.addr = Some(addr)
+                    }
+                    constraints.kind.replace(kind).is_some()
+                }
+                None => return self.invalid_section_arg(span),
+            },
+        };
+        if is_duplicate {
+            let arg = self.session.session.strip_span(&span);
+            self.session
+                .session
+                .emit_diag(Message::DuplicateSectionArg { arg }.at(span));
+            return Err(());
+        }
+        Ok(())
+    }
+
+    fn eval_section_arg(&mut self, expr: Expr<StringRef, S::Span>) -> Result<(i32, S::Span), ()> {
+        let expr = self.session.expect_const(ParsedArg::Bare(expr))?;
+        let span = expr.span();
+        match self.session.session.eval_const(expr) {
+            Some(value) => Ok((value, span)),
+            None => {
+                self.session
+                    .session
+                    .emit_diag(Message::MustBeConst.at(span));
+                Err(())
+            }
+        }
+    }
+
+    fn invalid_section_arg<T>(&mut self, span: S::Span) -> Result<T, ()> {
+        let arg = self.session.session.strip_span(&span);
+        self.session
+            .session
+            .emit_diag(Message::InvalidSectionArg { arg }.at(span));
+        Err(())
+    }
+
+    fn analyze_nullary(self, f: fn(&mut S, S::Span)) -> TokenStreamSemantics<'a, S> {
+        if !self.args.is_empty() {
+            self.session.session.emit_diag(
+                Message::OperandCount {
+                    actual: self.args.len(),
+                    expected: 0,
+                }
+                .at(self.span),
+            );
+            return self.session;
+        }
+        f(self.session.session, self.span);
+        self.session
+    }
+
+    // The load block gets a RAM section of its own, constrained like a `SECTION`.
+    fn analyze_load(mut self) -> TokenStreamSemantics<'a, S> {
+        let span = match self.args.first() {
+            Some(ParsedArg::Bare(expr)) => expr.span(),
+            Some(ParsedArg::Parenthesized(_, span)) | Some(ParsedArg::String(_, span)) => {
+                span.clone()
+            }
+            Some(ParsedArg::Error) => return self.session,
+            None => {
+                self.session.session.emit_diag(
+                    Message::OperandCount {
+                        actual: 0,
+                        expected: 1,
+                    }
+                    .at(self.span),
+                );
+                return self.session;
+            }
+        };
+        let constraints = match self.analyze_constraints() {
+            Ok(constraints) => constraints,
+            Err(()) => return self.session,
+        };
+        match constraints.kind {
+            Some(kind) if kind.is_ram() => self.session.session.begin_load(constraints, self.span),
+            _ => {
+                let arg = self.session.session.strip_span(&span);
+                self.session
+                    .session
+                    .emit_diag(Message::InvalidLoadTarget { arg }.at(span))
+            }
+        }
         self.session
     }
 
@@ -99,26 +330,31 @@ where
     }
 
     fn analyze_if(mut self) -> TokenStreamSemantics<'a, S> {
-        match single_arg(self.span, self.args, self.session.session) {
-            Some(arg) => {
-                let value = self.session.expect_const(arg);
-                match self.session.session.is_non_zero(value.unwrap()) {
-                    Some(true) => (),
-                    Some(false) => {
-                        self.session.state.mode = LineRule::TokenLine(TokenLineState {
-                            context: TokenContext::FalseIf,
-                        })
-                    }
-                    None => unimplemented!(),
-                }
+        let value = match single_arg(self.span, self.args, self.session.session) {
+            Some(arg) => match self.session.expect_const(arg) {
+                Ok(value) => value,
+                Err(()) => return self.session,
+            },
+            None => return self.session,
+        };
+        let span = value.span();
+        match self.session.session.is_non_zero(value) {
+            Some(true) => (),
+            Some(false) => {
+                self.session.state.mode = LineRule::TokenLine(TokenLineState {
+                    context: TokenContext::FalseIf,
+                })
             }
-            None => unimplemented!(),
+            None => self
+                .session
+                .session
+                .emit_diag(Message::MustBeConst.at(span)),
         }
         self.session
     }
 
-    fn analyze_include(self) -> TokenStreamSemantics<'a, S> {
-        let (path, span) = match reduce_include(self.span, self.args, self.session.session) {
+    fn analyze_include(mut self) -> TokenStreamSemantics<'a, S> {
+        let (path, span) = match single_string_arg(self.span, self.args, &mut self.session) {
             Some(result) => result,
             None => return self.session,
         };
@@ -150,6 +386,49 @@ where
         )
     }
 
+    fn analyze_print(mut self, terminator: &str) -> TokenStreamSemantics<'a, S> {
+        let mut text = String::new();
+        for arg in self.args {
+            match arg {
+                ParsedArg::String(string, _) => text.push_str(&string),
+                arg => {
+                    let expr = match self.session.expect_const(arg) {
+                        Ok(expr) => expr,
+                        Err(()) => return self.session,
+                    };
+                    let span = expr.span();
+                    match self.session.session.eval_value(expr) {
+                        Const::String(string) => text.push_str(&string),
+                        Const::Num(n) => match n.exact() {
+                            Some(n) => text.push_str(&n.to_string()),
+                            None => {
+                                self.session
+                                    .session
+                                    .emit_diag(Message::MustBeConst.at(span));
+                                return self.session;
+                            }
+                        },
+                    }
+                }
+            }
+        }
+        text.push_str(terminator);
+        self.session.session.print(&text);
+        self.session
+    }
+
+    fn analyze_user_diag(
+        mut self,
+        mk_message: impl FnOnce(Box<str>) -> Message<<S as StripSpan<S::Span>>::Stripped>,
+    ) -> TokenStreamSemantics<'a, S> {
+        let span = self.span.clone();
+        if let Some((message, _)) = single_string_arg(self.span, self.args, &mut self.session) {
+            let message = mk_message(message.to_string().into_boxed_str());
+            self.session.session.emit_diag(message.at(span))
+        }
+        self.session
+    }
+
     fn analyze_org(mut self) -> TokenStreamSemantics<'a, S> {
         if let Some(arg) = single_arg(self.span, self.args, self.session.session) {
             let result = self.session.expect_const(arg);
@@ -159,46 +438,95 @@ where
         }
         self.session
     }
+
+    fn analyze_weak(mut self) -> TokenStreamSemantics<'a, S> {
+        if self.args.is_empty() {
+            self.session.session.emit_diag(
+                Message::OperandCount {
+                    actual: 0,
+                    expected: 1,
+                }
+                .at(self.span),
+            );
+            return self.session;
+        }
+        for arg in std::mem::take(&mut self.args) {
+            let symbol = match arg {
+                ParsedArg::Bare(expr) => {
+                    let span = expr.span();
+                    match split_section_arg(expr) {
+                        Some((ident, None)) => Ok((ident, span)),
+                        _ => Err(span),
+                    }
+                }
+                ParsedArg::Parenthesized(_, span) | ParsedArg::String(_, span) => Err(span),
+                ParsedArg::Error => return self.session,
+            };
+            match symbol {
+                Ok(symbol) => self.session.session.declare_weak(symbol),
+                Err(span) => self
+                    .session
+                    .session
+                    .emit_diag(Message::ExpectedSymbolName.at(span)),
+            }
+        }
+        self.session
+    }
 }
 
-fn reduce_include<D: Diagnostics<S>, S: Clone>(
-    span: S,
-    args: Vec<ParsedArg<S>>,
-    diagnostics: &mut D,
-) -> Option<(StringRef, S)> {
-    let arg = match single_arg(span, args, diagnostics) {
-        Some(arg) => arg,
-        None => return None,
-    };
-    let result = match arg {
-        ParsedArg::String(path, span) => Ok((path, span)),
-        ParsedArg::Bare(expr) => Err(Some(expr.span())),
-        ParsedArg::Parenthesized(_, span) => Err(Some(span)),
-        ParsedArg::Error => Err(None),
-    };
-    match result {
-        Ok(result) => Some(result),
-        Err(Some(span)) => {
-            diagnostics.emit_diag(Message::ExpectedString.at(span));
-            None
+fn split_section_arg<S>(
+    mut expr: Expr<StringRef, S>,
+) -> Option<(StringRef, Option<Expr<StringRef, S>>)> {
+    match &expr.0.last()?.item {
+        ExprOp::Atom(Atom::Name(name)) if expr.0.len() == 1 => Some((name.clone(), None)),
+        ExprOp::FnCall(1) => {
+            expr.0.pop();
+            match expr.0.remove(0).item {
+                ExprOp::Atom(Atom::Name(name)) => Some((name, Some(expr))),
+                _ => None,
+            }
         }
-        Err(None) => None,
+        _ => None,
     }
 }
 
+fn section_kind(keyword: &str) -> Option<SectionKind> {
+    match keyword {
+        "HRAM" => Some(SectionKind::Hram),
+        "OAM" => Some(SectionKind::Oam),
+        "ROM0" => Some(SectionKind::Rom0),
+        "ROMX" => Some(SectionKind::Romx),
+        "SRAM" => Some(SectionKind::Sram),
+        "VRAM" => Some(SectionKind::Vram),
+        "WRAM0" => Some(SectionKind::Wram0),
+        "WRAMX" => Some(SectionKind::Wramx),
+        _ => None,
+    }
+}
+
+fn single_string_arg<S: Analysis>(
+    span: S::Span,
+    args: Vec<ParsedArg<S::Span>>,
+    session: &mut TokenStreamSemantics<S>,
+) -> Option<(StringRef, S::Span)> {
+    let arg = single_arg(span, args, session.session)?;
+    session.expect_string(arg).ok()
+}
+
 fn single_arg<T, D: Diagnostics<S>, S>(
     span: S,
     args: impl IntoIterator<Item = T>,
     diagnostics: &mut D,
 ) -> Option<T> {
     let mut args = args.into_iter();
-    if let Some(arg) = args.next() {
-        assert!(args.next().is_none());
-        Some(arg)
+    let arg = args.next();
+    let actual = arg.iter().count() + args.count();
+    if actual == 1 {
+        arg
     } else {
         diagnostics.emit_diag(
             Message::OperandCount {
-                actual: 0,
+                actual,
                 expected: 1,
             }
             .at(span),
@@ -319,6 +647,58 @@ mod tests {
         )
     }
 
+    #[test]
+    fn diagnose_string_in_db() {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        {
+            let actions = session.semantic_actions();
+            let mut actions = actions
+                .will_parse_line()
+                .into_instr_line()
+                .will_parse_instr("DB".into(), ())
+                .into_builtin_instr()
+                .will_parse_arg();
+            actions.act_on_atom(ExprAtom::Literal(Literal::String("1.0".into())), ());
+            actions.did_parse_arg().did_parse_instr().did_parse_line(());
+        }
+        assert_eq!(
+            session.log(),
+            [Event::EmitDiag {
+                diag: Message::ExpectedFound {
+                    expected: ValueKind::Num,
+                    found: ValueKind::String,
+                }
+                .at(())
+                .into()
+            }]
+        )
+    }
+
+    #[test]
+    fn emit_parenthesized_word() {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        {
+            let actions = session.semantic_actions();
+            let mut actions = actions
+                .will_parse_line()
+                .into_instr_line()
+                .will_parse_instr("DW".into(), ())
+                .into_builtin_instr()
+                .will_parse_arg();
+            actions.act_on_atom(mk_literal(0x1234), ());
+            actions.act_on_operator(Operator::Unary(UnaryOperator::Parentheses), ());
+            actions.did_parse_arg().did_parse_instr().did_parse_line(());
+        }
+        assert_eq!(
+            session.log(),
+            [Event::EmitFragment {
+                fragment: mk_word(0x1234)
+            }]
+        )
+    }
+
     #[test]
     fn reserve_3_bytes() {
         let mut fixture = TestFixture::new();
@@ -407,6 +787,327 @@ mod tests {
         )
     }
 
+    #[test]
+    fn assert_with_message() {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        {
+            let actions = session.semantic_actions();
+            let mut actions = actions
+                .will_parse_line()
+                .into_instr_line()
+                .will_parse_instr("ASSERT".into(), ())
+                .into_builtin_instr()
+                .will_parse_arg();
+            actions.act_on_atom(mk_literal(1), ());
+            let mut actions = actions.did_parse_arg().will_parse_arg();
+            actions.act_on_atom(ExprAtom::Literal(Literal::String("message".into())), ());
+            actions.did_parse_arg().did_parse_instr().did_parse_line(());
+        }
+        assert_eq!(
+            session.log(),
+            [Event::EmitAssertion {
+                condition: 1.into(),
+                message: Some("message".into()),
+                span: ()
+            }]
+        )
+    }
+
+    #[test]
+    fn assert_without_args() {
+        test_unary_directive_without_args("ASSERT")
+    }
+
+    #[test]
+    fn fail_with_message() {
+        test_user_diag(
+            "FAIL",
+            Message::UserError {
+                message: "message".into(),
+            },
+        )
+    }
+
+    #[test]
+    fn warn_with_message() {
+        test_user_diag(
+            "WARN",
+            Message::UserWarning {
+                message: "message".into(),
+            },
+        )
+    }
+
+    #[test]
+    fn warn_with_two_messages() {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        {
+            let actions = session.semantic_actions();
+            let mut actions = actions
+                .will_parse_line()
+                .into_instr_line()
+                .will_parse_instr("WARN".into(), ())
+                .into_builtin_instr()
+                .will_parse_arg();
+            actions.act_on_atom(ExprAtom::Literal(Literal::String("a".into())), ());
+            let mut actions = actions.did_parse_arg().will_parse_arg();
+            actions.act_on_atom(ExprAtom::Literal(Literal::String("b".into())), ());
+            actions.did_parse_arg().did_parse_instr().did_parse_line(());
+        }
+        assert_eq!(
+            session.log(),
+            [Event::EmitDiag {
+                diag: Message::OperandCount {
+                    actual: 2,
+                    expected: 1
+                }
+                .at(())
+                .into()
+            }]
+        )
+    }
+
+    fn test_user_diag(directive: &str, message: Message<()>) {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        {
+            let actions = session.semantic_actions();
+            let mut actions = actions
+                .will_parse_line()
+                .into_instr_line()
+                .will_parse_instr(directive.into(), ())
+                .into_builtin_instr()
+                .will_parse_arg();
+            actions.act_on_atom(ExprAtom::Literal(Literal::String("message".into())), ());
+            actions.did_parse_arg().did_parse_instr().did_parse_line(());
+        }
+        assert_eq!(
+            session.log(),
+            [Event::EmitDiag {
+                diag: message.at(()).into()
+            }]
+        )
+    }
+
+    #[test]
+    fn load_into_hram() {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        analyze_directive(
+            (Directive::Load, ()),
+            None,
+            vec![mk_section_arg("HRAM", Some(0xff80))],
+            session.semantic_actions(),
+        );
+        assert_eq!(
+            session.log(),
+            [Event::BeginLoad {
+                constraints: Constraints {
+                    addr: Some(0xff80.into()),
+                    kind: Some(SectionKind::Hram),
+                    ..Default::default()
+                },
+                span: ()
+            }]
+        )
+    }
+
+    #[test]
+    fn load_into_floating_ram_section() {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        analyze_directive(
+            (Directive::Load, ()),
+            None,
+            vec![mk_section_arg("wram0", None)],
+            session.semantic_actions(),
+        );
+        assert_eq!(
+            session.log(),
+            [Event::BeginLoad {
+                constraints: Constraints {
+                    kind: Some(SectionKind::Wram0),
+                    ..Default::default()
+                },
+                span: ()
+            }]
+        )
+    }
+
+    #[test]
+    fn diagnose_load_into_rom() {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        analyze_directive(
+            (Directive::Load, ()),
+            None,
+            vec![mk_section_arg("ROMX", None)],
+            session.semantic_actions(),
+        );
+        assert_eq!(
+            session.log(),
+            [Event::EmitDiag {
+                diag: Message::InvalidLoadTarget { arg: () }.at(()).into()
+            }]
+        )
+    }
+
+    #[test]
+    fn end_load_without_load_block() {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        analyze_directive(
+            (Directive::Endl, ()),
+            None,
+            vec![],
+            session.semantic_actions(),
+        );
+        assert_eq!(
+            session.log(),
+            [
+                Event::EndLoad { span: () },
+                Event::EmitDiag {
+                    diag: Message::UnmatchedEndl.at(()).into()
+                }
+            ]
+        )
+    }
+
+    #[test]
+    fn union_directives() {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        {
+            let mut actions = session.semantic_actions();
+            for directive in &["UNION", "NEXTU", "ENDU"] {
+                actions = actions
+                    .will_parse_line()
+                    .into_instr_line()
+                    .will_parse_instr((*directive).into(), ())
+                    .into_builtin_instr()
+                    .did_parse_instr()
+                    .did_parse_line(());
+            }
+        }
+        assert_eq!(
+            session.log(),
+            [
+                Event::BeginUnion { span: () },
+                Event::NextUnion { span: () },
+                Event::EndUnion { span: () }
+            ]
+        )
+    }
+
+    #[test]
+    fn push_and_pop_section() {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        {
+            let actions = session.semantic_actions();
+            actions
+                .will_parse_line()
+                .into_instr_line()
+                .will_parse_instr("PUSHS".into(), ())
+                .into_builtin_instr()
+                .did_parse_instr()
+                .did_parse_line(())
+                .will_parse_line()
+                .into_instr_line()
+                .will_parse_instr("POPS".into(), ())
+                .into_builtin_instr()
+                .did_parse_instr()
+                .did_parse_line(());
+        }
+        assert_eq!(
+            session.log(),
+            [
+                Event::PushSection { span: () },
+                Event::PopSection { span: () }
+            ]
+        )
+    }
+
+    #[test]
+    fn println_string_and_number() {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        {
+            let actions = session.semantic_actions();
+            let mut actions = actions
+                .will_parse_line()
+                .into_instr_line()
+                .will_parse_instr("PRINTLN".into(), ())
+                .into_builtin_instr()
+                .will_parse_arg();
+            actions.act_on_atom(ExprAtom::Literal(Literal::String("x = ".into())), ());
+            let mut actions = actions.did_parse_arg().will_parse_arg();
+            actions.act_on_atom(mk_literal(42), ());
+            actions.did_parse_arg().did_parse_instr().did_parse_line(());
+        }
+        assert_eq!(
+            session.log(),
+            [Event::Print {
+                text: "x = 42\n".into()
+            }]
+        )
+    }
+
+    #[test]
+    fn print_non_constant_expr() {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        {
+            let actions = session.semantic_actions();
+            let mut actions = actions
+                .will_parse_line()
+                .into_instr_line()
+                .will_parse_instr("PRINT".into(), ())
+                .into_builtin_instr()
+                .will_parse_arg();
+            actions.act_on_atom(ExprAtom::LocationCounter, ());
+            actions.did_parse_arg().did_parse_instr().did_parse_line(());
+        }
+        assert_eq!(
+            session.log(),
+            [Event::EmitDiag {
+                diag: Message::MustBeConst.at(()).into()
+            }]
+        )
+    }
+
+    #[test]
+    fn declare_weak_symbols() {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        {
+            let actions = session.semantic_actions();
+            let mut actions = actions
+                .will_parse_line()
+                .into_instr_line()
+                .will_parse_instr("WEAK".into(), ())
+                .into_builtin_instr()
+                .will_parse_arg();
+            actions.act_on_atom(ExprAtom::Ident("handler".into()), ());
+            let mut actions = actions.did_parse_arg().will_parse_arg();
+            actions.act_on_atom(mk_literal(42), ());
+            actions.did_parse_arg().did_parse_instr().did_parse_line(());
+        }
+        assert_eq!(
+            session.log(),
+            [
+                Event::DeclareWeak {
+                    symbol: ("handler".into(), ())
+                },
+                Event::EmitDiag {
+                    diag: Message::ExpectedSymbolName.at(()).into()
+                }
+            ]
+        )
+    }
+
     #[test]
     fn data_with_malformed_expr() {
         let mut fixture = TestFixture::new();
@@ -527,6 +1228,33 @@ mod tests {
         )
     }
 
+    #[test]
+    fn define_string_symbol() {
+        let symbol = "VERSION";
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        {
+            let actions = session.semantic_actions();
+            let mut actions = actions
+                .will_parse_line()
+                .into_instr_line()
+                .will_parse_label((symbol.into(), ()))
+                .did_parse_label()
+                .will_parse_instr("EQUS".into(), ())
+                .into_builtin_instr()
+                .will_parse_arg();
+            actions.act_on_atom(ExprAtom::Literal(Literal::String("1.2".into())), ());
+            actions.did_parse_arg().did_parse_instr().did_parse_line(());
+        }
+        assert_eq!(
+            session.log(),
+            [Event::DefineSymbol {
+                symbol: (symbol.into(), ()),
+                def: SymbolDef::String("1.2".into())
+            }]
+        )
+    }
+
     #[test]
     fn define_fn_with_param() {
         let name = "my_fn";
@@ -556,7 +1284,10 @@ mod tests {
             session.log(),
             [Event::DefineSymbol {
                 symbol: (name.into(), ()),
-                def: SymbolDef::Closure(Atom::from(ParamId(0)).into())
+                def: SymbolDef::Function {
+                    params: 1,
+                    body: Atom::from(ParamId(0)).into()
+                }
             },]
         )
     }
@@ -588,6 +1319,150 @@ mod tests {
         )
     }
 
+    #[test]
+    fn constrain_romx_section_to_bank() {
+        let args = vec![
+            mk_section_arg("ROMX", None),
+            mk_section_arg("BANK", Some(3)),
+            mk_section_arg("ALIGN", Some(8)),
+        ];
+        assert_eq!(
+            analyze_section_args(args)[1..],
+            [Event::ConstrainSection {
+                constraints: Constraints {
+                    align: Some(8),
+                    bank: Some(3),
+                    kind: Some(SectionKind::Romx),
+                    ..Default::default()
+                }
+            }]
+        )
+    }
+
+    #[test]
+    fn constrain_section_to_kind_and_addr() {
+        let args = vec![mk_section_arg("rom0", Some(0x150))];
+        assert_eq!(
+            analyze_section_args(args)[1..],
+            [Event::ConstrainSection {
+                constraints: Constraints {
+                    addr: Some(Expr::from_atom(Atom::Const(0x150), ())),
+                    kind: Some(SectionKind::Rom0),
+                    ..Default::default()
+                }
+            }]
+        )
+    }
+
+    #[test]
+    fn mark_section_as_kept() {
+        let args = vec![mk_section_arg("ROMX", None), mk_section_arg("KEEP", None)];
+        assert_eq!(
+            analyze_section_args(args)[1..],
+            [Event::ConstrainSection {
+                constraints: Constraints {
+                    kind: Some(SectionKind::Romx),
+                    keep: true,
+                    ..Default::default()
+                }
+            }]
+        )
+    }
+
+    #[test]
+    fn diagnose_bank_without_banked_kind() {
+        let args = vec![
+            mk_section_arg("ROM0", None),
+            mk_section_arg("BANK", Some(1)),
+        ];
+        assert_eq!(
+            analyze_section_args(args)[1..],
+            [Event::EmitDiag {
+                diag: Message::BankRequiresBankedKind.at(()).into()
+            }]
+        )
+    }
+
+    #[test]
+    fn diagnose_bank_out_of_range() {
+        let args = vec![
+            mk_section_arg("WRAMX", None),
+            mk_section_arg("BANK", Some(8)),
+        ];
+        assert_eq!(
+            analyze_section_args(args)[1..],
+            [Event::EmitDiag {
+                diag: Message::OutOfRange {
+                    value: 8,
+                    min: 1,
+                    max: 7
+                }
+                .at(())
+                .into()
+            }]
+        )
+    }
+
+    #[test]
+    fn diagnose_align_out_of_range() {
+        let args = vec![mk_section_arg("ALIGN", Some(17))];
+        assert_eq!(
+            analyze_section_args(args)[1..],
+            [Event::EmitDiag {
+                diag: Message::OutOfRange {
+                    value: 17,
+                    min: 0,
+                    max: 16
+                }
+                .at(())
+                .into()
+            }]
+        )
+    }
+
+    #[test]
+    fn diagnose_duplicate_section_kind() {
+        let args = vec![mk_section_arg("ROM0", None), mk_section_arg("ROMX", None)];
+        assert_eq!(
+            analyze_section_args(args)[1..],
+            [Event::EmitDiag {
+                diag: Message::DuplicateSectionArg { arg: () }.at(()).into()
+            }]
+        )
+    }
+
+    #[test]
+    fn diagnose_invalid_section_arg() {
+        let args = vec![mk_section_arg("FLASH", None)];
+        assert_eq!(
+            analyze_section_args(args)[1..],
+            [Event::EmitDiag {
+                diag: Message::InvalidSectionArg { arg: () }.at(()).into()
+            }]
+        )
+    }
+
+    fn mk_section_arg(name: &str, param: Option<i32>) -> ParsedArg<()> {
+        let mut expr = Expr(vec![ExprOp::Atom(Atom::Name(name.into())).with_span(())]);
+        if let Some(param) = param {
+            expr.0.push(ExprOp::Atom(Atom::Const(param)).with_span(()));
+            expr.0.push(ExprOp::FnCall(1).with_span(()))
+        }
+        ParsedArg::Bare(expr)
+    }
+
+    fn analyze_section_args(args: BuiltinInstrArgs<()>) -> Vec<Event<()>> {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        analyze_directive(
+            (Directive::Section, ()),
+            Some((("my_section".into(), ()), (vec![], vec![]))),
+            args,
+            session.semantic_actions(),
+        );
+        session.log().to_vec()
+    }
+
     #[test]
     fn taken_if_remains_in_instr_mode() {
         let mut fixture = TestFixture::new();
@@ -626,6 +1501,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn diagnose_if_without_constant_condition() {
+        let mut fixture = TestFixture::new();
+        let mut session = fixture.session();
+        let label = Expr::from_atom(Atom::Name("label".into()), ());
+        let session = analyze_directive(
+            (Directive::If, ()),
+            None,
+            vec![ParsedArg::Bare(label)],
+            session.semantic_actions(),
+        );
+        assert_eq!(
+            session.state,
+            TokenStreamState {
+                mode: LineRule::InstrLine(InstrLineState { label: None })
+            }
+        );
+        assert_eq!(
+            session.session.log().last(),
+            Some(&Event::EmitDiag {
+                diag: Message::MustBeConst.at(()).into()
+            })
+        )
+    }
+
     fn test_unary_directive_without_args(directive: &str) {
         let mut fixture = TestFixture::new();
         let mut session = fixture.session();
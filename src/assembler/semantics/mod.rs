@@ -2,12 +2,13 @@ use super::keywords::{BuiltinMnemonic, Directive, Mnemonic, OperandKeyword};
 use super::session::*;
 use super::string_ref::StringRef;
 use super::syntax::actions::*;
-use super::syntax::{LexError, Literal, SemanticToken, Sigil, Token};
+use super::syntax::{LexError, LexItem, Literal, SemanticToken, Sigil, Token};
 
-use crate::diagnostics::{CompactDiag, Diagnostics, Message};
+use crate::diagnostics::{CompactDiag, Diagnostics, Message, ValueKind};
+use crate::eval::Const;
 use crate::expr::{Atom, Expr, ExprOp, ParamId};
 use crate::object::Name;
-use crate::span::{SpanSource, Spanned, StripSpan, WithSpan};
+use crate::span::{Source, SpanSource, Spanned, StripSpan, WithSpan};
 
 macro_rules! set_state {
     ($session:expr, $state:expr) => {
@@ -45,7 +46,7 @@ where
     }
 }
 
-impl<'a, 'b, S: Analysis, T> Semantics<'a, S, T> {
+impl<'a, S: Analysis, T> Semantics<'a, S, T> {
     fn map_state<F: FnOnce(T) -> U, U>(self, f: F) -> Semantics<'a, S, U> {
         Semantics {
             session: self.session,
@@ -159,7 +160,7 @@ enum ParsedArg<S> {
 enum Arg<S> {
     Bare(BareArg<S>),
     Deref(BareArg<S>, S),
-    String(StringRef, S),
+    String(S),
     Error,
 }
 
@@ -169,10 +170,12 @@ enum BareArg<S> {
     OperandKeyword(OperandKeyword, S),
 }
 
+#[cfg(test)]
 trait NameVisibility {
     fn name_visibility(&self, name: &str) -> Visibility;
 }
 
+#[cfg(test)]
 impl<S> NameVisibility for S {
     fn name_visibility(&self, name: &str) -> Visibility {
         if name.starts_with('_') {
@@ -212,7 +215,7 @@ where
     fn next_token(
         &mut self,
     ) -> Option<LexerOutput<Self::Ident, Self::Literal, Self::Error, Self::Span>> {
-        self.session.next_token()
+        next_token_diagnosing_lex_errors(&mut *self.session)
     }
 
     fn merge_spans(&mut self, left: &Self::Span, right: &Self::Span) -> Self::Span {
@@ -305,7 +308,7 @@ impl<'a, S: Analysis> TokenLineContext for TokenLineSemantics<'a, S> {
         if let Some(MnemonicEntry::Builtin(mnemonic)) = self.session.mnemonic_lookup(ident.clone())
         {
             if let TokenLineRule::LineEnd(()) =
-                self.state.context.act_on_mnemonic(&mnemonic, span.clone())
+                self.state.context.act_on_mnemonic(mnemonic, span.clone())
             {
                 return TokenLineRule::LineEnd(TokenContextFinalizationSemantics { parent: self });
             }
@@ -370,6 +373,17 @@ impl<'a, S: Analysis> LineFinalizer for TokenLineSemantics<'a, S> {
     }
 }
 
+fn next_token_diagnosing_lex_errors<S>(session: &mut S) -> Option<LexItem<S::Span>>
+where
+    S: NextToken + Diagnostics<<S as SpanSource>::Span>,
+{
+    let token = session.next_token();
+    if let Some((Err(error), span)) = &token {
+        session.emit_diag(Message::from(*error).at(span.clone()))
+    }
+    token
+}
+
 pub(super) struct TokenContextFinalizationSemantics<'a, S: Analysis> {
     parent: TokenLineSemantics<'a, S>,
 }
@@ -384,7 +398,7 @@ impl<'a, S: Analysis> ParsingContext for TokenContextFinalizationSemantics<'a, S
     fn next_token(
         &mut self,
     ) -> Option<LexerOutput<Self::Ident, Self::Literal, Self::Error, Self::Span>> {
-        self.parent.session.next_token()
+        next_token_diagnosing_lex_errors(&mut *self.parent.session)
     }
 
     fn merge_spans(&mut self, left: &Self::Span, right: &Self::Span) -> Self::Span {
@@ -656,18 +670,47 @@ where
 
 impl<'a, S: Analysis, T> Semantics<'a, S, T> {
     fn expect_const(&mut self, arg: ParsedArg<S::Span>) -> Result<Expr<Name, S::Span>, ()> {
+        // Outside of instructions parentheses only group.
         match self.session.resolve_names(arg)? {
-            Arg::Bare(BareArg::Const(value)) => Ok(value),
-            Arg::Bare(BareArg::OperandKeyword(_, span)) => {
+            Arg::Bare(BareArg::Const(value)) | Arg::Deref(BareArg::Const(value), _) => Ok(value),
+            Arg::Bare(BareArg::OperandKeyword(_, span))
+            | Arg::Deref(BareArg::OperandKeyword(_, span), _) => {
                 let keyword = self.session.strip_span(&span);
                 self.session
                     .emit_diag(Message::KeywordInExpr { keyword }.at(span));
                 Err(())
             }
+            Arg::String(span) => {
+                self.session.emit_diag(
+                    Message::ExpectedFound {
+                        expected: ValueKind::Num,
+                        found: ValueKind::String,
+                    }
+                    .at(span),
+                );
+                Err(())
+            }
             Arg::Error => Err(()),
-            _ => unimplemented!(),
         }
     }
+
+    fn expect_string(&mut self, arg: ParsedArg<S::Span>) -> Result<(StringRef, S::Span), ()> {
+        let span = match arg {
+            ParsedArg::String(string, span) => return Ok((string, span)),
+            ParsedArg::Bare(expr) => {
+                let expr = self.expect_const(ParsedArg::Bare(expr))?;
+                let span = expr.span();
+                match self.session.eval_value(expr) {
+                    Const::String(string) => return Ok(((*string).into(), span)),
+                    Const::Num(_) => span,
+                }
+            }
+            ParsedArg::Parenthesized(_, span) => span,
+            ParsedArg::Error => return Err(()),
+        };
+        self.session.emit_diag(Message::ExpectedString.at(span));
+        Err(())
+    }
 }
 
 impl From<Directive> for BuiltinMnemonic {
@@ -710,16 +753,32 @@ trait ClassifyExpr<I, S> {
     fn classify_expr(&mut self, expr: Expr<I, S>) -> Result<BareArg<S>, ()>;
 }
 
+trait QueryName<S> {
+    fn query_name(&mut self, name: &StringRef, span: &S) -> Result<NameEntry, ()>;
+}
+
+impl<T: IdentTable + AnonLabels<S>, S: Clone> QueryName<S> for T {
+    fn query_name(&mut self, name: &StringRef, span: &S) -> Result<NameEntry, ()> {
+        match AnonLabelRef::parse(name) {
+            Some(reference) => self
+                .query_anon_label(reference, span.clone())
+                .map(NameEntry::Symbol)
+                .ok_or(()),
+            None => Ok(self.query_term(name)),
+        }
+    }
+}
+
 impl<T, S> Resolve<S> for T
 where
-    T: IdentTable + Diagnostics<S>,
+    T: IdentTable + AnonLabels<S> + Diagnostics<S>,
     S: Clone,
 {
     fn resolve_names(&mut self, arg: ParsedArg<S>) -> Result<Arg<S>, ()> {
         Ok(match arg {
             ParsedArg::Bare(expr) => Arg::Bare(self.classify_expr(expr)?),
             ParsedArg::Parenthesized(expr, span) => Arg::Deref(self.classify_expr(expr)?, span),
-            ParsedArg::String(string, span) => Arg::String(string, span),
+            ParsedArg::String(_, span) => Arg::String(span),
             ParsedArg::Error => Arg::Error,
         })
     }
@@ -727,14 +786,14 @@ where
 
 impl<T, S> ClassifyExpr<StringRef, S> for T
 where
-    T: IdentTable + Diagnostics<S>,
+    T: IdentTable + AnonLabels<S> + Diagnostics<S>,
     S: Clone,
 {
     fn classify_expr(&mut self, mut expr: Expr<StringRef, S>) -> Result<BareArg<S>, ()> {
         if expr.0.len() == 1 {
             let node = expr.0.pop().unwrap();
             match node.item {
-                ExprOp::Atom(Atom::Name(name)) => match self.query_term(&name) {
+                ExprOp::Atom(Atom::Name(name)) => match self.query_name(&name, &node.span)? {
                     NameEntry::OperandKeyword(operand) => {
                         Ok(BareArg::OperandKeyword(operand, node.span))
                     }
@@ -759,6 +818,10 @@ where
                         ExprOp::Atom(Atom::Param(id)).with_span(node.span)
                     ])))
                 }
+                ExprOp::Atom(Atom::String(string)) => Ok(BareArg::Const(Expr(vec![ExprOp::Atom(
+                    Atom::String(string),
+                )
+                .with_span(node.span)]))),
                 _ => panic!("first node in expression must be an atom"),
             }
         } else {
@@ -766,15 +829,16 @@ where
             let mut error = false;
             for node in expr.0 {
                 match node.item {
-                    ExprOp::Atom(Atom::Name(name)) => match self.query_term(&name) {
-                        NameEntry::OperandKeyword(_) => {
+                    ExprOp::Atom(Atom::Name(name)) => match self.query_name(&name, &node.span) {
+                        Ok(NameEntry::OperandKeyword(_)) => {
                             let keyword = self.strip_span(&node.span);
                             self.emit_diag(Message::KeywordInExpr { keyword }.at(node.span));
                             error = true
                         }
-                        NameEntry::Symbol(id) => {
+                        Ok(NameEntry::Symbol(id)) => {
                             nodes.push(ExprOp::Atom(Atom::Name(id)).with_span(node.span))
                         }
+                        Err(()) => error = true,
                     },
                     ExprOp::Atom(Atom::Const(n)) => {
                         nodes.push(ExprOp::Atom(Atom::Const(n)).with_span(node.span))
@@ -785,8 +849,12 @@ where
                     ExprOp::Atom(Atom::Param(id)) => {
                         nodes.push(ExprOp::Atom(Atom::Param(id)).with_span(node.span))
                     }
+                    ExprOp::Atom(Atom::String(string)) => {
+                        nodes.push(ExprOp::Atom(Atom::String(string)).with_span(node.span))
+                    }
                     ExprOp::Binary(op) => nodes.push(ExprOp::Binary(op).with_span(node.span)),
                     ExprOp::FnCall(arity) => nodes.push(ExprOp::FnCall(arity).with_span(node.span)),
+                    ExprOp::Unary(op) => nodes.push(ExprOp::Unary(op).with_span(node.span)),
                 }
             }
             if !error {
@@ -819,7 +887,13 @@ impl<'a, S: Analysis> ArgContext for ArgSemantics<'a, S> {
                 self.act_on_expr_node(ExprOp::Atom(Atom::Const(n)), span)
             }
             ExprAtom::Literal(Literal::String(string)) => {
-                self.state.arg = Some(ParsedArg::String(string, span))
+                let string = self.session.interpolate(string, &span);
+                match self.state.arg {
+                    None => self.state.arg = Some(ParsedArg::String(string, span)),
+                    Some(_) => {
+                        self.act_on_expr_node(ExprOp::Atom(Atom::String((*string).into())), span)
+                    }
+                }
             }
             ExprAtom::LocationCounter => self.act_on_expr_node(ExprOp::Atom(Atom::Location), span),
             ExprAtom::Error => self.state.arg = Some(ParsedArg::Error),
@@ -830,6 +904,9 @@ impl<'a, S: Analysis> ArgContext for ArgSemantics<'a, S> {
         match op {
             Operator::Binary(op) => self.act_on_expr_node(ExprOp::Binary(op), span),
             Operator::FnCall(arity) => self.act_on_expr_node(ExprOp::FnCall(arity), span),
+            Operator::Unary(UnaryOperator::Op(op)) => {
+                self.act_on_expr_node(ExprOp::Unary(op), span)
+            }
             Operator::Unary(UnaryOperator::Parentheses) => match &self.state.arg {
                 Some(ParsedArg::Bare(arg)) => {
                     self.state.arg = Some(ParsedArg::Parenthesized((*arg).clone(), span))
@@ -849,14 +926,17 @@ impl<'a, S: Analysis> ArgSemantics<'a, S> {
                 Some(ParsedArg::Bare(expr))
             }
             Some(ParsedArg::Error) => Some(ParsedArg::Error),
-            Some(ParsedArg::String(_, _)) => todo!(),
+            Some(ParsedArg::String(string, string_span)) => Some(ParsedArg::Bare(Expr(vec![
+                ExprOp::Atom(Atom::String((*string).into())).with_span(string_span),
+                node.with_span(span),
+            ]))),
         }
     }
 
     fn act_on_ident(&mut self, ident: StringRef, span: S::Span) {
         let no_params = vec![];
         let params = match &self.state.parent.label {
-            Some((_, (params, _))) => &params,
+            Some((_, (params, _))) => params,
             _ => &no_params,
         };
         let param = params.iter().position(|param| *param == ident).map(ParamId);
@@ -1592,7 +1672,10 @@ mod tests {
         }
         let expected = [Event::DefineSymbol {
             symbol: ("label".into(), "label".into()),
-            def: SymbolDef::Closure(Expr::from_atom(Atom::Param(ParamId(0)), "param2".into())),
+            def: SymbolDef::Function {
+                params: 1,
+                body: Expr::from_atom(Atom::Param(ParamId(0)), "param2".into()),
+            },
         }];
         assert_eq!(session.log(), expected)
     }
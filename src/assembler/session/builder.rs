@@ -1,13 +1,21 @@
 use super::*;
 
-use crate::diagnostics::{Diagnostics, DiagnosticsContext};
-use crate::expr::Expr;
+use crate::diagnostics::{Diagnostics, DiagnosticsContext, IgnoreDiagnostics, Message};
+use crate::expr::{Atom, BinOp, Expr, ExprOp};
+use crate::object::var::Var;
 use crate::object::*;
+use crate::span::WithSpan;
+
+use std::collections::HashSet;
 
 pub(crate) struct ObjectBuilder<S> {
     content: Content<StringRef, S>,
+    load: Option<LoadBlock<S>>,
     state: Option<BuilderState<S>>,
+    stack: Vec<SavedSection<S>>,
+    unions: Vec<S>,
     vars: VarTable,
+    weak: HashSet<SymbolId>,
 }
 
 enum BuilderState<S> {
@@ -16,12 +24,29 @@ enum BuilderState<S> {
     SectionPrelude(usize),
 }
 
+struct SavedSection<S> {
+    state: BuilderState<S>,
+    local_names: HashMap<StringRef, NameEntry>,
+    load: Option<LoadBlock<S>>,
+    unions: Vec<S>,
+    span: S,
+}
+
+struct LoadBlock<S> {
+    end: VarId,
+    span: S,
+}
+
 impl<S> ObjectBuilder<S> {
     pub fn new() -> Self {
         ObjectBuilder {
             content: Content::new(),
+            load: None,
             state: Some(BuilderState::AnonSectionPrelude { addr: None }),
+            stack: Vec::new(),
+            unions: Vec::new(),
             vars: VarTable::new(),
+            weak: HashSet::new(),
         }
     }
 }
@@ -31,6 +56,69 @@ impl<S> ObjectBuilder<S> {
         self.current_section().fragments.push(fragment)
     }
 
+    // Records the current location without ending the section prelude. Before any section there
+    // is no location to record.
+    fn mark_location(&mut self, location: VarId) {
+        match self.state.as_ref().unwrap() {
+            BuilderState::AnonSectionPrelude { .. } => (),
+            BuilderState::SectionPrelude(index) | BuilderState::Section(index) => {
+                self.content.sections[*index]
+                    .fragments
+                    .push(Fragment::Reloc(location))
+            }
+        }
+    }
+
+    // Adds an unnamed RAM section that reserves room for a load block ending at `end`, and returns
+    // the symbol of the section.
+    fn add_load_section(&mut self, constraints: Constraints<S>, end: VarId, span: S) -> SymbolId
+    where
+        S: Clone,
+    {
+        let SectionId(index) = self.add_section(None);
+        let section = SymbolId(self.content.symbols.len());
+        self.content.symbols.push(Symbol::Local {
+            def: SymbolDefRecord {
+                def_ident_span: span.clone(),
+                meaning: SymbolMeaning::Section(SectionId(index)),
+            },
+        });
+        let end_label = SymbolId(self.content.symbols.len());
+        self.content.symbols.push(Symbol::Local {
+            def: SymbolDefRecord {
+                def_ident_span: span.clone(),
+                meaning: SymbolMeaning::Closure(Closure {
+                    expr: Expr(vec![ExprOp::Atom(Atom::Location).with_span(span.clone())]),
+                    location: end,
+                    params: 0,
+                }),
+            },
+        });
+        let size = Expr(vec![
+            ExprOp::Atom(Atom::Name(Name::Symbol(end_label))).with_span(span.clone()),
+            ExprOp::Atom(Atom::Name(Name::Symbol(section))).with_span(span.clone()),
+            ExprOp::Binary(BinOp::Minus).with_span(span),
+        ]);
+        let section_data = &mut self.content.sections[index];
+        section_data.constraints = constraints;
+        section_data.fragments.push(Fragment::Reserved(size));
+        section
+    }
+
+    fn end_load(&mut self, end: VarId) {
+        self.push(Fragment::Reloc(end));
+        self.push(Fragment::EndLoad)
+    }
+
+    fn current_kind(&self) -> Option<SectionKind> {
+        match self.state.as_ref().unwrap() {
+            BuilderState::AnonSectionPrelude { .. } => None,
+            BuilderState::SectionPrelude(index) | BuilderState::Section(index) => {
+                self.content.sections[*index].constraints.kind
+            }
+        }
+    }
+
     fn current_section(&mut self) -> &mut Section<S> {
         match self.state.take().unwrap() {
             BuilderState::AnonSectionPrelude { addr } => {
@@ -51,7 +139,7 @@ impl<S> ObjectBuilder<S> {
     fn add_section(&mut self, symbol: Option<(SymbolId, S)>) -> SectionId {
         let section = SectionId(self.content.sections.len());
         self.content.sections.push(Section {
-            constraints: Constraints { addr: None },
+            constraints: Constraints::default(),
             addr: self.vars.alloc(),
             size: self.vars.alloc(),
             fragments: Vec::new(),
@@ -68,13 +156,17 @@ impl<S> ObjectBuilder<S> {
         section
     }
 
-    fn define_symbol(&mut self, SymbolId(id): SymbolId, def: SymbolDefRecord<S>) {
+    fn define_symbol(&mut self, symbol: SymbolId, def: SymbolDefRecord<S>) {
+        let SymbolId(id) = symbol;
         match &mut self.content.symbols[id] {
             Symbol::Unknown { ident } => {
-                self.content.symbols[id] = if ident.starts_with('_') {
+                self.content.symbols[id] = if ident.starts_with('_') || is_anon_label(ident) {
                     Symbol::Local { def }
+                } else if self.weak.contains(&symbol) {
+                    let ident = std::mem::take(ident);
+                    Symbol::Weak { ident, def }
                 } else {
-                    let ident = std::mem::replace(ident, StringRef::default());
+                    let ident = std::mem::take(ident);
                     Symbol::Exported { ident, def }
                 }
             }
@@ -82,6 +174,41 @@ impl<S> ObjectBuilder<S> {
         }
     }
 
+    fn declare_weak(&mut self, symbol: SymbolId) {
+        let entry = &mut self.content.symbols[symbol.0];
+        *entry = match std::mem::replace(
+            entry,
+            Symbol::Unknown {
+                ident: StringRef::default(),
+            },
+        ) {
+            Symbol::Exported { ident, def } => Symbol::Weak { ident, def },
+            other => other,
+        };
+        self.weak.insert(symbol);
+    }
+
+    // Defines a symbol as the location of an unplaced var, so that its value stays unknown without
+    // the symbol being reported as unresolved again at link time.
+    pub(super) fn define_unknown(&mut self, symbol: SymbolId, span: S)
+    where
+        S: Clone,
+    {
+        let location = self.vars.alloc();
+        let expr = Expr(vec![ExprOp::Atom(Atom::Location).with_span(span.clone())]);
+        self.define_symbol(
+            symbol,
+            SymbolDefRecord {
+                def_ident_span: span,
+                meaning: SymbolMeaning::Closure(Closure {
+                    expr,
+                    location,
+                    params: 0,
+                }),
+            },
+        )
+    }
+
     pub fn alloc_symbol(&mut self, ident: StringRef) -> Name {
         let name = Name::Symbol(SymbolId(self.content.symbols.len()));
         self.content.symbols.push(Symbol::Unknown { ident });
@@ -90,6 +217,7 @@ impl<S> ObjectBuilder<S> {
 
     pub fn into_content(self) -> Content<Box<str>, S> {
         Content {
+            assertions: self.content.assertions,
             sections: self.content.sections,
             symbols: self
                 .content
@@ -100,13 +228,17 @@ impl<S> ObjectBuilder<S> {
                         ident: ident.to_string().into_boxed_str(),
                         def,
                     },
+                    Symbol::Weak { ident, def } => Symbol::Weak {
+                        ident: ident.to_string().into_boxed_str(),
+                        def,
+                    },
                     Symbol::Local { def } => Symbol::Local { def },
                     Symbol::Unknown { ident } => Symbol::Unknown {
                         ident: ident.to_string().into_boxed_str(),
                     },
                 })
                 .collect(),
-            vars: self.vars.0.len(),
+            vars: self.vars.len(),
         }
     }
 }
@@ -117,6 +249,75 @@ where
     Self: Diagnostics<R::Span>,
     for<'r> DiagnosticsContext<'r, 'a, R, OutputForwarder<'a>>: Diagnostics<R::Span>,
 {
+    fn begin_load(&mut self, constraints: Constraints<R::Span>, span: R::Span) {
+        #[cfg(test)]
+        self.log_event(Event::BeginLoad {
+            constraints: constraints.clone(),
+            span: span.clone(),
+        });
+
+        if self.builder.load.is_some() {
+            self.emit_diag(Message::NestedLoad.at(span))
+        } else {
+            self.builder.current_section();
+            let end = self.builder.vars.alloc();
+            let section = self
+                .builder
+                .add_load_section(constraints, end, span.clone());
+            let addr = Expr(vec![
+                ExprOp::Atom(Atom::Name(Name::Symbol(section))).with_span(span.clone())
+            ]);
+            self.builder.push(Fragment::Load(addr));
+            self.builder.load = Some(LoadBlock { end, span })
+        }
+    }
+
+    fn begin_union(&mut self, span: R::Span) {
+        #[cfg(test)]
+        self.log_event(Event::BeginUnion { span: span.clone() });
+
+        match self.builder.current_kind() {
+            Some(kind) if !kind.is_ram() => self.emit_diag(Message::UnionOutsideRam.at(span)),
+            _ => {
+                self.builder.push(Fragment::Union);
+                self.builder.unions.push(span)
+            }
+        }
+    }
+
+    fn constrain_section(&mut self, constraints: Constraints<R::Span>) {
+        #[cfg(test)]
+        self.log_event(Event::ConstrainSection {
+            constraints: constraints.clone(),
+        });
+
+        if let Some(BuilderState::SectionPrelude(index)) = self.builder.state {
+            let section = &mut self.builder.content.sections[index].constraints;
+            if constraints.addr.is_some() {
+                section.addr = constraints.addr
+            }
+            section.align = constraints.align;
+            section.bank = constraints.bank;
+            section.kind = constraints.kind;
+            section.keep = constraints.keep;
+        }
+    }
+
+    fn declare_weak(&mut self, (ident, span): (StringRef, R::Span)) {
+        #[cfg(test)]
+        self.log_event(Event::DeclareWeak {
+            symbol: (ident.clone(), span.clone()),
+        });
+
+        match self.query_term(&ident) {
+            NameEntry::Symbol(Name::Symbol(symbol)) => self.builder.declare_weak(symbol),
+            NameEntry::Symbol(Name::Builtin(_)) | NameEntry::OperandKeyword(_) => {
+                let name = self.strip_span(&span);
+                self.emit_diag(Message::CannotRedefineBuiltin { name }.at(span))
+            }
+        }
+    }
+
     fn define_symbol(&mut self, (ident, span): (StringRef, R::Span), def: SymbolDef<R::Span>) {
         #[cfg(test)]
         self.log_event(Event::DefineSymbol {
@@ -124,34 +325,79 @@ where
             def: def.clone(),
         });
 
-        match self.query_term(&ident) {
+        if let SymbolDef::String(string) = def {
+            return self.define_string((ident, span), string);
+        }
+        let entry = if is_anon_label(&ident) {
+            NameEntry::Symbol(Name::Symbol(self.define_anon_label(ident.clone())))
+        } else {
+            self.query_term(&ident)
+        };
+        match entry {
             NameEntry::Symbol(Name::Symbol(symbol)) => {
-                if !ident.starts_with('_') {
+                if !ident.starts_with('_') && !is_anon_label(&ident) {
                     self.names.local = HashMap::new();
                 }
                 match def {
-                    SymbolDef::Closure(expr) => {
-                        let location = self.builder.vars.alloc();
-                        self.builder.push(Fragment::Reloc(location));
-                        self.builder.define_symbol(
-                            symbol,
-                            SymbolDefRecord {
-                                def_ident_span: span,
-                                meaning: SymbolMeaning::Closure(Closure { expr, location }),
-                            },
-                        )
+                    SymbolDef::Closure(expr) => self.define_closure(symbol, span, expr, 0),
+                    SymbolDef::Function { params, body } => {
+                        self.define_closure(symbol, span, body, params)
                     }
                     SymbolDef::Section => {
+                        self.close_unions();
+                        self.close_load();
                         let section = self.builder.add_section(Some((symbol, span)));
                         self.builder.state = Some(BuilderState::SectionPrelude(section.0))
                     }
+                    SymbolDef::String(_) => unreachable!(),
                 }
             }
-            NameEntry::Symbol(Name::Builtin(_)) => todo!(),
+            NameEntry::Symbol(Name::Builtin(_)) => {
+                let name = self.strip_span(&span);
+                self.emit_diag(Message::CannotRedefineBuiltin { name }.at(span))
+            }
             NameEntry::OperandKeyword(_) => todo!(),
         }
     }
 
+    fn emit_assertion(
+        &mut self,
+        condition: Expr<Name, R::Span>,
+        message: Option<StringRef>,
+        span: R::Span,
+    ) {
+        #[cfg(test)]
+        self.log_event(Event::EmitAssertion {
+            condition: condition.clone(),
+            message: message.clone(),
+            span: span.clone(),
+        });
+
+        let context = LinkageContext {
+            content: &self.builder.content,
+            vars: &self.builder.vars,
+            location: Var::Unknown,
+        };
+        let message = message.map(|message| message.to_string().into_boxed_str());
+        match condition.to_num(&context, &mut IgnoreDiagnostics).exact() {
+            Some(0) => self.emit_diag(Message::AssertionFailed { message }.at(span)),
+            Some(_) => (),
+            None => {
+                let location = self.builder.vars.alloc();
+                self.builder.mark_location(location);
+                self.builder.content.assertions.push(Assertion {
+                    condition: Closure {
+                        expr: condition,
+                        location,
+                        params: 0,
+                    },
+                    message,
+                    span,
+                })
+            }
+        }
+    }
+
     fn emit_fragment(&mut self, fragment: Fragment<Expr<Name, R::Span>>) {
         #[cfg(test)]
         self.log_event(Event::EmitFragment {
@@ -161,21 +407,98 @@ where
         self.builder.push(fragment)
     }
 
-    fn is_non_zero(&mut self, value: Expr<Name, R::Span>) -> Option<bool> {
+    fn end_load(&mut self, span: R::Span) {
+        #[cfg(test)]
+        self.log_event(Event::EndLoad { span: span.clone() });
+
+        match self.builder.load.take() {
+            Some(load) => self.builder.end_load(load.end),
+            None => self.emit_diag(Message::UnmatchedEndl.at(span)),
+        }
+    }
+
+    fn end_union(&mut self, span: R::Span) {
+        #[cfg(test)]
+        self.log_event(Event::EndUnion { span: span.clone() });
+
+        match self.builder.unions.pop() {
+            Some(_) => self.builder.push(Fragment::EndUnion),
+            None => self.emit_not_in_union(span),
+        }
+    }
+
+    // The location counter is left unknown, so `.` is only constant after linking.
+    fn eval_const(&mut self, value: Expr<Name, R::Span>) -> Option<i32> {
         let context = LinkageContext {
             content: &self.builder.content,
             vars: &self.builder.vars,
-            location: 0.into(),
+            location: Var::Unknown,
         };
         let mut diagnostics = DiagnosticsContext {
             codebase: &mut self.codebase,
             registry: &mut self.metadata,
             diagnostics: &mut self.diagnostics,
         };
-        value
-            .to_num(&context, &mut diagnostics)
-            .exact()
-            .map(|n| n != 0)
+        value.to_num(&context, &mut diagnostics).exact()
+    }
+
+    fn eval_value(&mut self, value: Expr<Name, R::Span>) -> Const {
+        let context = LinkageContext {
+            content: &self.builder.content,
+            vars: &self.builder.vars,
+            location: Var::Unknown,
+        };
+        let mut diagnostics = DiagnosticsContext {
+            codebase: &mut self.codebase,
+            registry: &mut self.metadata,
+            diagnostics: &mut self.diagnostics,
+        };
+        value.to_const(&context, &mut diagnostics)
+    }
+
+    fn next_union(&mut self, span: R::Span) {
+        #[cfg(test)]
+        self.log_event(Event::NextUnion { span: span.clone() });
+
+        if self.builder.unions.is_empty() {
+            self.emit_not_in_union(span)
+        } else {
+            self.builder.push(Fragment::NextUnion)
+        }
+    }
+
+    fn pop_section(&mut self, span: R::Span) {
+        #[cfg(test)]
+        self.log_event(Event::PopSection { span: span.clone() });
+
+        match self.builder.stack.pop() {
+            Some(saved) => {
+                self.close_unions();
+                self.close_load();
+                self.builder.state = Some(saved.state);
+                self.builder.load = saved.load;
+                self.builder.unions = saved.unions;
+                self.names.local = saved.local_names
+            }
+            None => self.emit_diag(Message::UnmatchedPops.at(span)),
+        }
+    }
+
+    fn push_section(&mut self, span: R::Span) {
+        #[cfg(test)]
+        self.log_event(Event::PushSection { span: span.clone() });
+
+        let state = self
+            .builder
+            .state
+            .replace(BuilderState::AnonSectionPrelude { addr: None });
+        self.builder.stack.push(SavedSection {
+            state: state.unwrap(),
+            local_names: std::mem::take(&mut self.names.local),
+            load: self.builder.load.take(),
+            unions: std::mem::take(&mut self.builder.unions),
+            span,
+        })
     }
 
     fn set_origin(&mut self, addr: Expr<Name, R::Span>) {
@@ -192,6 +515,69 @@ where
     }
 }
 
+impl<'a, R: SpanSystem> CompositeSession<'a, R>
+where
+    Self: Diagnostics<R::Span>,
+{
+    fn define_closure(
+        &mut self,
+        symbol: SymbolId,
+        span: R::Span,
+        expr: Expr<Name, R::Span>,
+        params: usize,
+    ) {
+        let location = self.builder.vars.alloc();
+        self.builder.push(Fragment::Reloc(location));
+        self.builder.define_symbol(
+            symbol,
+            SymbolDefRecord {
+                def_ident_span: span,
+                meaning: SymbolMeaning::Closure(Closure {
+                    expr,
+                    location,
+                    params,
+                }),
+            },
+        )
+    }
+
+    pub(super) fn eval_name(&self, name: Name, span: &R::Span) -> Option<i32> {
+        let context = LinkageContext {
+            content: &self.builder.content,
+            vars: &self.builder.vars,
+            location: Var::Unknown,
+        };
+        Expr(vec![ExprOp::Atom(Atom::Name(name)).with_span(span.clone())])
+            .to_num(&context, &mut IgnoreDiagnostics)
+            .exact()
+    }
+
+    pub(super) fn close_unions(&mut self) {
+        for span in std::mem::take(&mut self.builder.unions).into_iter().rev() {
+            self.builder.push(Fragment::EndUnion);
+            self.emit_diag(Message::UnmatchedUnion.at(span))
+        }
+    }
+
+    fn emit_not_in_union(&mut self, span: R::Span) {
+        let directive = self.strip_span(&span);
+        self.emit_diag(Message::NotInUnion { directive }.at(span))
+    }
+
+    pub(super) fn close_load(&mut self) {
+        if let Some(load) = self.builder.load.take() {
+            self.builder.end_load(load.end);
+            self.emit_diag(Message::UnmatchedLoad.at(load.span))
+        }
+    }
+
+    pub(super) fn check_section_stack(&mut self) {
+        for saved in std::mem::take(&mut self.builder.stack) {
+            self.emit_diag(Message::UnmatchedPushs.at(saved.span))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -250,6 +636,22 @@ mod tests {
         assert_eq!(content.sections[0].constraints.addr, Some(origin))
     }
 
+    #[test]
+    fn constrain_section_in_section_prelude() {
+        let constraints = Constraints {
+            addr: Some(0x4000.into()),
+            align: Some(8),
+            bank: Some(2),
+            kind: Some(SectionKind::Romx),
+            ..Default::default()
+        };
+        let content = build_object(|session| {
+            session.define_symbol(("my_section".into(), ()), SymbolDef::Section);
+            session.constrain_section(constraints.clone())
+        });
+        assert_eq!(content.sections[0].constraints, constraints)
+    }
+
     #[test]
     fn emit_fragment_into_named_section() {
         let content = build_object(|session| {
@@ -259,6 +661,300 @@ mod tests {
         assert_eq!(content.sections[0].fragments, [Fragment::Byte(0x00)])
     }
 
+    #[test]
+    fn pop_section_resumes_pushed_section() {
+        let content = build_object(|session| {
+            session.define_symbol(("code".into(), ()), SymbolDef::Section);
+            session.emit_fragment(Fragment::Byte(0x00));
+            session.push_section(());
+            session.define_symbol(("data".into(), ()), SymbolDef::Section);
+            session.emit_fragment(Fragment::Byte(0x01));
+            session.pop_section(());
+            session.emit_fragment(Fragment::Byte(0x02))
+        });
+        assert_eq!(
+            content.sections[0].fragments,
+            [Fragment::Byte(0x00), Fragment::Byte(0x02)]
+        );
+        assert_eq!(content.sections[1].fragments, [Fragment::Byte(0x01)])
+    }
+
+    #[test]
+    fn pop_section_restores_section_prelude() {
+        let origin: Expr<_, _> = 0x0150.into();
+        let content = build_object(|session| {
+            session.define_symbol(("code".into(), ()), SymbolDef::Section);
+            session.push_section(());
+            session.define_symbol(("data".into(), ()), SymbolDef::Section);
+            session.pop_section(());
+            session.set_origin(origin.clone())
+        });
+        assert_eq!(content.sections[0].constraints.addr, Some(origin));
+        assert_eq!(content.sections[1].constraints.addr, None)
+    }
+
+    #[test]
+    fn pop_section_restores_local_scope() {
+        let name = "_local".into();
+        let mut fixture = TestFixture::<()>::new();
+        let mut session = fixture.session();
+        let entry1 = session.query_term(&name);
+        session.push_section(());
+        session.define_symbol(("data".into(), ()), SymbolDef::Section);
+        session.pop_section(());
+        let entry2 = session.query_term(&name);
+        assert_eq!(entry1, entry2)
+    }
+
+    #[test]
+    fn diagnose_pop_section_with_empty_stack() {
+        let mut fixture = TestFixture::<MockSpan<_>>::new();
+        let mut session = fixture.session();
+        session.pop_section(MockSpan::Basic("pops"));
+        assert_eq!(
+            session.log()[1..],
+            [Event::EmitDiag {
+                diag: Message::UnmatchedPops.at(MockSpan::Basic("pops")).into()
+            }]
+        )
+    }
+
+    #[test]
+    fn diagnose_unmatched_push_section_at_end_of_input() {
+        let mut fixture = TestFixture::<MockSpan<_>>::new();
+        let mut session = fixture.session();
+        session.push_section(MockSpan::Basic("pushs"));
+        session.finish();
+        assert_eq!(
+            session.log()[1..],
+            [Event::EmitDiag {
+                diag: Message::UnmatchedPushs.at(MockSpan::Basic("pushs")).into()
+            }]
+        )
+    }
+
+    #[test]
+    fn load_block_delimited_in_current_section() {
+        let content = build_object(|session| {
+            session.begin_load(wram0(), ());
+            session.emit_fragment(Fragment::Byte(0x00));
+            session.end_load(())
+        });
+        let section = SymbolId(content.symbols.len() - 2);
+        assert_eq!(
+            content.sections[0].fragments,
+            [
+                Fragment::Load(Expr::from_atom(Atom::Name(Name::Symbol(section)), ())),
+                Fragment::Byte(0x00),
+                Fragment::Reloc(load_end(&content)),
+                Fragment::EndLoad
+            ]
+        )
+    }
+
+    #[test]
+    fn load_block_reserves_ram_section() {
+        let content = build_object(|session| {
+            session.begin_load(wram0(), ());
+            session.emit_fragment(Fragment::Byte(0x00));
+            session.end_load(())
+        });
+        let section = SymbolId(content.symbols.len() - 2);
+        let end_label = SymbolId(content.symbols.len() - 1);
+        assert_eq!(content.sections[1].constraints, wram0());
+        assert_eq!(
+            content.sections[1].fragments,
+            [Fragment::Reserved(Expr(vec![
+                ExprOp::Atom(Atom::Name(Name::Symbol(end_label))).with_span(()),
+                ExprOp::Atom(Atom::Name(Name::Symbol(section))).with_span(()),
+                ExprOp::Binary(BinOp::Minus).with_span(()),
+            ]))]
+        )
+    }
+
+    #[test]
+    fn diagnose_nested_load_block() {
+        let mut fixture = TestFixture::<MockSpan<_>>::new();
+        let mut session = fixture.session();
+        session.begin_load(wram0(), MockSpan::Basic("load1"));
+        session.begin_load(wram0(), MockSpan::Basic("load2"));
+        assert_eq!(
+            session.log()[2..],
+            [Event::EmitDiag {
+                diag: Message::NestedLoad.at(MockSpan::Basic("load2")).into()
+            }]
+        )
+    }
+
+    #[test]
+    fn diagnose_end_load_without_load_block() {
+        let mut fixture = TestFixture::<MockSpan<_>>::new();
+        let mut session = fixture.session();
+        session.end_load(MockSpan::Basic("endl"));
+        assert_eq!(
+            session.log()[1..],
+            [Event::EmitDiag {
+                diag: Message::UnmatchedEndl.at(MockSpan::Basic("endl")).into()
+            }]
+        )
+    }
+
+    #[test]
+    fn section_definition_closes_load_block() {
+        let mut fixture = TestFixture::<MockSpan<_>>::new();
+        let mut session = fixture.session();
+        session.begin_load(wram0(), MockSpan::Basic("load"));
+        session.define_symbol(
+            ("my_section".into(), MockSpan::Basic("section")),
+            SymbolDef::Section,
+        );
+        assert_eq!(
+            session.log()[2..],
+            [Event::EmitDiag {
+                diag: Message::UnmatchedLoad.at(MockSpan::Basic("load")).into()
+            }]
+        );
+        assert_eq!(
+            session.builder.content.sections[0].fragments.last(),
+            Some(&Fragment::EndLoad)
+        )
+    }
+
+    #[test]
+    fn union_alternatives_delimited_in_current_section() {
+        let content = build_object(|session| {
+            session.begin_union(());
+            session.emit_fragment(Fragment::Byte(0x00));
+            session.next_union(());
+            session.emit_fragment(Fragment::Byte(0x01));
+            session.end_union(())
+        });
+        assert_eq!(
+            content.sections[0].fragments,
+            [
+                Fragment::Union,
+                Fragment::Byte(0x00),
+                Fragment::NextUnion,
+                Fragment::Byte(0x01),
+                Fragment::EndUnion
+            ]
+        )
+    }
+
+    #[test]
+    fn diagnose_union_in_rom_section() {
+        let mut fixture = TestFixture::<MockSpan<_>>::new();
+        let mut session = fixture.session();
+        session.define_symbol(
+            ("my_section".into(), MockSpan::Basic("section")),
+            SymbolDef::Section,
+        );
+        session.constrain_section(Constraints {
+            kind: Some(SectionKind::Rom0),
+            ..Default::default()
+        });
+        session.begin_union(MockSpan::Basic("union"));
+        assert_eq!(
+            session.log()[3..],
+            [Event::EmitDiag {
+                diag: Message::UnionOutsideRam.at(MockSpan::Basic("union")).into()
+            }]
+        )
+    }
+
+    #[test]
+    fn diagnose_next_union_outside_union() {
+        let mut fixture = TestFixture::<MockSpan<_>>::new();
+        let mut session = fixture.session();
+        session.next_union(MockSpan::Basic("nextu"));
+        assert_eq!(
+            session.log()[1..],
+            [Event::EmitDiag {
+                diag: Message::NotInUnion {
+                    directive: MockSpan::Basic("nextu")
+                }
+                .at(MockSpan::Basic("nextu"))
+                .into()
+            }]
+        )
+    }
+
+    #[test]
+    fn diagnose_redefinition_of_builtin() {
+        let mut fixture = TestFixture::<MockSpan<_>>::new();
+        let mut session = fixture.session();
+        session.define_symbol(("high".into(), MockSpan::Basic("high")), SymbolDef::Section);
+        assert_eq!(
+            session.log()[1..],
+            [Event::EmitDiag {
+                diag: Message::CannotRedefineBuiltin {
+                    name: MockSpan::Basic("high")
+                }
+                .at(MockSpan::Basic("high"))
+                .into()
+            }]
+        )
+    }
+
+    #[test]
+    fn diagnose_unmatched_union_at_end_of_input() {
+        let mut fixture = TestFixture::<MockSpan<_>>::new();
+        let mut session = fixture.session();
+        session.begin_union(MockSpan::Basic("union"));
+        session.finish();
+        assert_eq!(
+            session.log()[1..],
+            [Event::EmitDiag {
+                diag: Message::UnmatchedUnion.at(MockSpan::Basic("union")).into()
+            }]
+        );
+        assert_eq!(
+            session.builder.content.sections[0].fragments,
+            [Fragment::Union, Fragment::EndUnion]
+        )
+    }
+
+    #[test]
+    fn pop_section_resumes_load_block() {
+        let content = build_object(|session| {
+            session.begin_load(wram0(), ());
+            session.push_section(());
+            session.define_symbol(("data".into(), ()), SymbolDef::Section);
+            session.pop_section(());
+            session.end_load(())
+        });
+        assert_eq!(
+            content.sections[0].fragments[1..],
+            [Fragment::Reloc(load_end(&content)), Fragment::EndLoad]
+        );
+        assert_eq!(content.sections[2].fragments, [])
+    }
+
+    fn load_end<S>(content: &Content<StringRef, S>) -> VarId {
+        content
+            .symbols
+            .iter()
+            .rev()
+            .find_map(|symbol| match symbol {
+                Symbol::Local {
+                    def:
+                        SymbolDefRecord {
+                            meaning: SymbolMeaning::Closure(closure),
+                            ..
+                        },
+                } => Some(closure.location),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn wram0<S>() -> Constraints<S> {
+        Constraints {
+            kind: Some(SectionKind::Wram0),
+            ..Default::default()
+        }
+    }
+
     fn build_object<F: FnOnce(&mut MockSession<S>), S: Clone + Default + Merge>(
         f: F,
     ) -> Content<StringRef, S> {
@@ -299,6 +995,16 @@ mod tests {
         });
     }
 
+    #[test]
+    fn location_counter_is_not_const() {
+        build_object(|object_builder| {
+            assert_eq!(
+                object_builder.is_non_zero(Expr(vec![ExprOp::Atom(Atom::Location).with_span(())])),
+                None
+            )
+        });
+    }
+
     #[test]
     fn local_symbol_not_accessible_after_section_definition() {
         let name = "_local".into();
@@ -332,6 +1038,99 @@ mod tests {
             ("_loop".into(), ()),
             SymbolDef::Closure(Expr::from_atom(Atom::Location, ())),
         );
-        assert!(matches!(session.builder.content.symbols[0], Symbol::Local { .. }))
+        assert!(matches!(
+            session.builder.content.symbols[0],
+            Symbol::Local { .. }
+        ))
+    }
+
+    #[test]
+    fn symbol_declared_weak_before_definition_is_weak() {
+        let mut fixture = TestFixture::<()>::new();
+        let mut session = fixture.session();
+        session.declare_weak(("handler".into(), ()));
+        session.define_symbol(
+            ("handler".into(), ()),
+            SymbolDef::Closure(Expr::from_atom(Atom::Location, ())),
+        );
+        assert!(matches!(
+            session.builder.content.symbols[0],
+            Symbol::Weak { .. }
+        ))
+    }
+
+    #[test]
+    fn symbol_declared_weak_after_definition_is_weak() {
+        let mut fixture = TestFixture::<()>::new();
+        let mut session = fixture.session();
+        session.define_symbol(
+            ("handler".into(), ()),
+            SymbolDef::Closure(Expr::from_atom(Atom::Location, ())),
+        );
+        session.declare_weak(("handler".into(), ()));
+        assert!(matches!(
+            session.builder.content.symbols[0],
+            Symbol::Weak { .. }
+        ))
+    }
+
+    #[test]
+    fn check_constant_assertion_immediately() {
+        let mut fixture = TestFixture::<()>::new();
+        let mut session = fixture.session();
+        session.emit_assertion(0.into(), Some("message".into()), ());
+        assert_eq!(
+            session.log()[1],
+            Event::EmitDiag {
+                diag: Message::AssertionFailed {
+                    message: Some("message".into())
+                }
+                .at(())
+                .into()
+            }
+        );
+        assert_eq!(session.builder.content.assertions, [])
+    }
+
+    #[test]
+    fn defer_assertion_depending_on_location() {
+        let condition = Expr::from_atom(Atom::Location, ());
+        let content = build_object(|session| {
+            session.define_symbol(("my_section".into(), ()), SymbolDef::Section);
+            session.emit_assertion(condition.clone(), None, ())
+        });
+        assert_eq!(
+            content.assertions,
+            [Assertion {
+                condition: Closure {
+                    expr: condition,
+                    location: VarId(2),
+                    params: 0,
+                },
+                message: None,
+                span: (),
+            }]
+        );
+        assert_eq!(content.sections[0].fragments, [Fragment::Reloc(VarId(2))])
+    }
+
+    #[test]
+    fn set_origin_after_assertion_in_section_prelude() {
+        let origin: Expr<_, _> = 0x0150.into();
+        let content = build_object(|session| {
+            session.define_symbol(("my_section".into(), ()), SymbolDef::Section);
+            session.emit_assertion(Expr::from_atom(Atom::Location, ()), None, ());
+            session.set_origin(origin.clone())
+        });
+        assert_eq!(content.sections[0].constraints.addr, Some(origin))
+    }
+
+    #[test]
+    fn assertion_before_any_section_does_not_add_section() {
+        let content = build_object(|session| {
+            session.emit_assertion(Expr::from_atom(Atom::Location, ()), None, ())
+        });
+        assert_eq!(content.assertions.len(), 1);
+        assert_eq!(content.sections.len(), 0)
     }
 }
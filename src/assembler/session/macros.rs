@@ -100,13 +100,6 @@ pub struct MacroExpansionIter {
     pos: Option<MacroExpansionPos>,
 }
 
-#[derive(Debug)]
-pub(crate) struct MacroExpansion<E> {
-    metadata: E,
-    def: Rc<MacroDef>,
-    args: Box<[Box<[SemanticToken]>]>,
-}
-
 #[derive(Debug)]
 pub(crate) struct MacroDef {
     metadata: MacroDefId,
@@ -175,7 +168,7 @@ impl MacroExpansionIter {
         let param_expansion = pos
             .param_expansion
             .as_ref()
-            .and_then(|param_expansion| self.next_param_expansion_pos(&param_expansion));
+            .and_then(|param_expansion| self.next_param_expansion_pos(param_expansion));
         if param_expansion.is_some() {
             Some(MacroExpansionPos {
                 param_expansion,
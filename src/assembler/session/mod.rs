@@ -4,7 +4,9 @@ use self::macros::MacroArgs;
 use self::macros::{MacroTable, VecMacroTable};
 #[cfg(test)]
 use self::mock::MockSession;
+pub(crate) use self::resolve::AnonLabelRef;
 use self::resolve::*;
+use self::strings::StringExpansion;
 
 use super::keywords::{BuiltinMnemonic, Keyword, OperandKeyword, KEYWORDS};
 use super::string_ref::StringRef;
@@ -16,8 +18,9 @@ use super::syntax::{LexItem, Sigil, Token};
 use crate::codebase::fake::MockFileSystem;
 use crate::codebase::{Codebase, CodebaseError, FileSystem};
 use crate::diagnostics::*;
+use crate::eval::Const;
 use crate::expr::Expr;
-use crate::object::{Fragment, Metadata, Name, ObjectData, SpanData};
+use crate::object::{Constraints, Fragment, Metadata, Name, ObjectData, SpanData};
 use crate::span::*;
 
 use std::collections::HashMap;
@@ -30,6 +33,7 @@ mod lex;
 mod macros;
 mod reentrancy;
 mod resolve;
+mod strings;
 
 pub(super) trait Analysis:
     SpanSource
@@ -38,7 +42,10 @@ pub(super) trait Analysis:
     + Backend<<Self as SpanSource>::Span>
     + Diagnostics<<Self as SpanSource>::Span>
     + IdentTable
+    + Interpolate<<Self as SpanSource>::Span>
+    + AnonLabels<<Self as SpanSource>::Span>
     + MacroTable<<Self as SpanSource>::Span>
+    + Print
 {
     fn mnemonic_lookup(&mut self, mnemonic: StringRef) -> Option<MnemonicEntry>;
 }
@@ -52,25 +59,56 @@ pub(super) trait ReentrancyActions<S> {
 }
 
 pub(super) trait Backend<S: Clone> {
+    fn begin_load(&mut self, constraints: Constraints<S>, span: S);
+    fn begin_union(&mut self, span: S);
+    fn constrain_section(&mut self, constraints: Constraints<S>);
+    fn declare_weak(&mut self, symbol: (StringRef, S));
     fn define_symbol(&mut self, symbol: (StringRef, S), def: SymbolDef<S>);
+    fn emit_assertion(&mut self, condition: Expr<Name, S>, message: Option<StringRef>, span: S);
     fn emit_fragment(&mut self, fragment: Fragment<Expr<Name, S>>);
-    fn is_non_zero(&mut self, value: Expr<Name, S>) -> Option<bool>;
+    fn end_load(&mut self, span: S);
+    fn end_union(&mut self, span: S);
+    fn eval_const(&mut self, value: Expr<Name, S>) -> Option<i32>;
+    fn eval_value(&mut self, value: Expr<Name, S>) -> Const;
+
+    fn is_non_zero(&mut self, value: Expr<Name, S>) -> Option<bool> {
+        self.eval_const(value).map(|n| n != 0)
+    }
+
+    fn next_union(&mut self, span: S);
+    fn pop_section(&mut self, span: S);
+    fn push_section(&mut self, span: S);
     fn set_origin(&mut self, origin: Expr<Name, S>);
 }
 
+pub(super) trait Print {
+    fn print(&mut self, text: &str);
+}
+
 pub(super) trait IdentTable {
     fn query_term(&mut self, ident: &StringRef) -> NameEntry;
 }
 
+pub(super) trait Interpolate<S> {
+    fn interpolate(&mut self, string: StringRef, span: &S) -> StringRef;
+}
+
+pub(super) trait AnonLabels<S> {
+    fn query_anon_label(&mut self, reference: AnonLabelRef, span: S) -> Option<Name>;
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(super) enum SymbolDef<S> {
     Closure(Expr<Name, S>),
+    Function { params: usize, body: Expr<Name, S> },
     Section,
+    String(StringRef),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct MacroId(pub usize);
 
+#[cfg(test)]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub(crate) enum Visibility {
     Global,
@@ -89,7 +127,11 @@ impl<'a, R> CompositeSession<'a, R>
 where
     R: Default + SpanSystem,
 {
-    pub fn new(fs: &'a mut dyn FileSystem, diagnostics: &'a mut dyn FnMut(Diagnostic)) -> Self {
+    pub fn new(
+        fs: &'a mut dyn FileSystem,
+        diagnostics: &'a mut dyn FnMut(Diagnostic),
+        print: &'a mut dyn FnMut(&str),
+    ) -> Self {
         let mut mnemonics = HashMap::new();
         let mut names = BiLevelNameTable::new();
         for (ident, keyword) in KEYWORDS {
@@ -111,6 +153,7 @@ where
                 .insert((*ident).into(), NameEntry::Symbol(*name));
         }
         Self {
+            anon_labels: AnonLabelTable::new(),
             builder: ObjectBuilder::new(),
             codebase: Codebase::new(fs),
             diagnostics: OutputForwarder {
@@ -122,7 +165,10 @@ where
             metadata: R::default(),
             mnemonics,
             names,
+            print,
+            strings: HashMap::new(),
             tokens: Vec::new(),
+            expansions: Vec::new(),
         }
     }
 }
@@ -139,6 +185,18 @@ impl<'a> CompositeSession<'a, SpanData> {
     }
 }
 
+impl<'a, R: SpanSystem> CompositeSession<'a, R>
+where
+    Self: Diagnostics<R::Span>,
+{
+    pub fn finish(&mut self) {
+        self.check_anon_label_refs();
+        self.close_unions();
+        self.close_load();
+        self.check_section_stack()
+    }
+}
+
 impl<'a, R> Analysis for CompositeSession<'a, R>
 where
     for<'r> DiagnosticsContext<'r, 'a, R, OutputForwarder<'a>>: EmitDiag<R::Span, R::Stripped>,
@@ -170,12 +228,16 @@ pub(crate) trait TokenStream<R: SpanSource> {
 pub(super) struct CompositeSession<'a, R: SpanSystem> {
     codebase: Codebase<'a>,
     tokens: Vec<Box<dyn TokenStream<R>>>,
+    expansions: Vec<StringExpansion<R::Span>>,
     macros: VecMacroTable,
     metadata: R,
     mnemonics: HashMap<StringRef, MnemonicEntry>,
     names: BiLevelNameTable<StringRef>,
+    strings: HashMap<StringRef, StringRef>,
+    anon_labels: AnonLabelTable<R::Span>,
     builder: ObjectBuilder<R::Span>,
     diagnostics: OutputForwarder<'a>,
+    print: &'a mut dyn FnMut(&str),
     #[cfg(test)]
     log: Vec<Event<Name, MacroId, R::Span, R::Stripped>>,
 }
@@ -190,18 +252,43 @@ impl<'a, R: SpanSystem> SpanSource for CompositeSession<'a, R> {
     type Span = R::Span;
 }
 
-impl<'a, R: SpanSystem> NextToken for CompositeSession<'a, R> {
+impl<'a, R: SpanSystem> NextToken for CompositeSession<'a, R>
+where
+    Self: EmitDiag<R::Span, R::Stripped>,
+{
     fn next_token(&mut self) -> Option<LexItem<Self::Span>> {
-        let token = self
-            .tokens
-            .last_mut()
-            .unwrap()
-            .next_token(&mut self.metadata)
-            .unwrap();
-        if let Ok(Token::Sigil(Sigil::Eos)) = token.0 {
-            self.tokens.pop();
+        loop {
+            let token = match self.next_expanded_token() {
+                Some(token) => token,
+                None => {
+                    let token = self
+                        .tokens
+                        .last_mut()
+                        .unwrap()
+                        .next_token(&mut self.metadata)
+                        .unwrap();
+                    if let Ok(Token::Sigil(Sigil::Eos)) = token.0 {
+                        self.tokens.pop();
+                    }
+                    token
+                }
+            };
+            if let (Ok(Token::Ident(ident)), span) = &token {
+                if self.expand_string(ident, span) {
+                    continue;
+                }
+            }
+            return Some(token);
         }
-        Some(token)
+    }
+}
+
+impl<'a, R: SpanSystem> Print for CompositeSession<'a, R> {
+    fn print(&mut self, text: &str) {
+        #[cfg(test)]
+        self.log_event(Event::Print { text: text.into() });
+
+        (self.print)(text)
     }
 }
 
@@ -262,6 +349,19 @@ pub(super) enum Event<B, M, S, T> {
         path: StringRef,
         from: Option<S>,
     },
+    BeginLoad {
+        constraints: Constraints<S>,
+        span: S,
+    },
+    BeginUnion {
+        span: S,
+    },
+    ConstrainSection {
+        constraints: Constraints<S>,
+    },
+    DeclareWeak {
+        symbol: (StringRef, S),
+    },
     DefineMacro {
         name: (StringRef, S),
         params: (Box<[StringRef]>, Box<[S]>),
@@ -271,16 +371,39 @@ pub(super) enum Event<B, M, S, T> {
         symbol: (StringRef, S),
         def: SymbolDef<S>,
     },
+    EmitAssertion {
+        condition: Expr<B, S>,
+        message: Option<StringRef>,
+        span: S,
+    },
     EmitDiag {
         diag: CompactDiag<S, T>,
     },
     EmitFragment {
         fragment: Fragment<Expr<B, S>>,
     },
+    EndLoad {
+        span: S,
+    },
+    EndUnion {
+        span: S,
+    },
     ExpandMacro {
         name: (M, S),
         args: MacroArgs<S>,
     },
+    NextUnion {
+        span: S,
+    },
+    PopSection {
+        span: S,
+    },
+    Print {
+        text: String,
+    },
+    PushSection {
+        span: S,
+    },
     SetOrigin {
         addr: Expr<B, S>,
     },
@@ -290,6 +413,7 @@ pub(super) enum Event<B, M, S, T> {
 pub(super) struct TestFixture<S> {
     pub fs: MockFileSystem,
     drop: fn(Diagnostic),
+    ignore: fn(&str),
     _phantom_data: PhantomData<S>,
 }
 
@@ -299,12 +423,13 @@ impl<S: Clone + Default + Merge> TestFixture<S> {
         Self {
             fs: MockFileSystem::new(),
             drop,
+            ignore: |_| (),
             _phantom_data: PhantomData,
         }
     }
 
-    pub fn session(&mut self) -> MockSession<S> {
-        MockSession::new(&mut self.fs, &mut self.drop)
+    pub fn session(&mut self) -> MockSession<'_, S> {
+        MockSession::new(&mut self.fs, &mut self.drop, &mut self.ignore)
     }
 }
 
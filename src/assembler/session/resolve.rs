@@ -1,6 +1,8 @@
 use super::*;
 
-use std::collections::HashMap;
+use crate::object::SymbolId;
+
+use std::collections::{HashMap, VecDeque};
 
 pub struct BiLevelNameTable<R> {
     pub(super) global: HashMap<R, NameEntry>,
@@ -15,6 +17,14 @@ impl<R> BiLevelNameTable<R> {
         }
     }
 
+    pub(super) fn select_table(&self, ident: &str) -> &HashMap<R, NameEntry> {
+        if ident.starts_with('_') {
+            &self.local
+        } else {
+            &self.global
+        }
+    }
+
     fn select_table_mut(&mut self, ident: &str) -> &mut HashMap<R, NameEntry> {
         if ident.starts_with('_') {
             &mut self.local
@@ -26,12 +36,15 @@ impl<R> BiLevelNameTable<R> {
 
 impl<'a, R: SpanSystem> IdentTable for CompositeSession<'a, R> {
     fn query_term(&mut self, ident: &StringRef) -> NameEntry {
-        let table = self.names.select_table_mut(&ident);
+        let table = self.names.select_table_mut(ident);
         if let Some(entry) = table.get(ident) {
             entry.clone()
         } else {
             let representative = ident.to_ascii_uppercase();
-            if let Some(entry @ NameEntry::OperandKeyword(_)) = table.get(representative.as_str()) {
+            if let Some(
+                entry @ NameEntry::OperandKeyword(_) | entry @ NameEntry::Symbol(Name::Builtin(_)),
+            ) = table.get(representative.as_str())
+            {
                 let entry = entry.clone();
                 table.insert(ident.clone(), entry.clone());
                 entry.clone()
@@ -44,3 +57,317 @@ impl<'a, R: SpanSystem> IdentTable for CompositeSession<'a, R> {
         }
     }
 }
+
+pub(super) struct AnonLabelTable<S> {
+    chains: HashMap<StringRef, AnonLabelChain>,
+    forward_refs: Vec<(Name, S)>,
+}
+
+struct AnonLabelChain {
+    defined: Vec<Name>,
+    pending: VecDeque<Name>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct AnonLabelRef {
+    label: StringRef,
+    direction: Direction,
+    distance: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Direction {
+    Backward,
+    Forward,
+}
+
+impl<S> AnonLabelTable<S> {
+    pub fn new() -> Self {
+        AnonLabelTable {
+            chains: HashMap::new(),
+            forward_refs: Vec::new(),
+        }
+    }
+}
+
+impl AnonLabelChain {
+    fn new() -> Self {
+        AnonLabelChain {
+            defined: Vec::new(),
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+pub(super) fn is_anon_label(ident: &str) -> bool {
+    ident == ":" || is_numeric_label(ident)
+}
+
+fn is_numeric_label(ident: &str) -> bool {
+    !ident.is_empty() && ident.chars().all(|c| c.is_ascii_digit())
+}
+
+impl AnonLabelRef {
+    pub fn parse(ident: &StringRef) -> Option<Self> {
+        if let Some(arrows) = ident.strip_prefix(':') {
+            let direction = match arrows.chars().next()? {
+                '+' => Direction::Forward,
+                '-' => Direction::Backward,
+                _ => return None,
+            };
+            if arrows.chars().all(|c| c == arrows.chars().next().unwrap()) {
+                return Some(AnonLabelRef {
+                    label: ident.substring(..1),
+                    direction,
+                    distance: arrows.len(),
+                });
+            }
+            None
+        } else {
+            let label = ident.substring(..ident.len().checked_sub(1)?);
+            let direction = match ident.chars().last()? {
+                'b' => Direction::Backward,
+                'f' => Direction::Forward,
+                _ => return None,
+            };
+            if is_numeric_label(&label) {
+                Some(AnonLabelRef {
+                    label,
+                    direction,
+                    distance: 1,
+                })
+            } else {
+                None
+            }
+        }
+    }
+}
+
+impl<'a, R: SpanSystem> CompositeSession<'a, R> {
+    pub(super) fn define_anon_label(&mut self, label: StringRef) -> SymbolId {
+        let chain = self
+            .anon_labels
+            .chains
+            .entry(label.clone())
+            .or_insert_with(AnonLabelChain::new);
+        let name = match chain.pending.pop_front() {
+            Some(name) => name,
+            None => self.builder.alloc_symbol(label),
+        };
+        chain.defined.push(name);
+        match name {
+            Name::Symbol(symbol) => symbol,
+            Name::Builtin(_) => unreachable!(),
+        }
+    }
+
+    pub(super) fn check_anon_label_refs(&mut self)
+    where
+        Self: Diagnostics<R::Span>,
+    {
+        let pending: Vec<_> = self
+            .anon_labels
+            .chains
+            .values()
+            .flat_map(|chain| chain.pending.iter().copied())
+            .collect();
+        let mut reported = Vec::new();
+        for (name, span) in std::mem::take(&mut self.anon_labels.forward_refs) {
+            if pending.contains(&name) {
+                let reference = self.strip_span(&span);
+                self.emit_diag(Message::UnmatchedLabelRef { reference }.at(span.clone()));
+                match name {
+                    Name::Symbol(symbol) if !reported.contains(&name) => {
+                        self.builder.define_unknown(symbol, span);
+                        reported.push(name)
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+impl<'a, R> AnonLabels<R::Span> for CompositeSession<'a, R>
+where
+    R: SpanSystem,
+    Self: Diagnostics<R::Span>,
+{
+    fn query_anon_label(&mut self, reference: AnonLabelRef, span: R::Span) -> Option<Name> {
+        let AnonLabelRef {
+            label,
+            direction,
+            distance,
+        } = reference;
+        let chain = self
+            .anon_labels
+            .chains
+            .entry(label.clone())
+            .or_insert_with(AnonLabelChain::new);
+        match direction {
+            Direction::Backward => {
+                let name = chain
+                    .defined
+                    .len()
+                    .checked_sub(distance)
+                    .map(|index| chain.defined[index]);
+                if name.is_none() {
+                    let reference = self.strip_span(&span);
+                    self.emit_diag(Message::UnmatchedLabelRef { reference }.at(span))
+                }
+                name
+            }
+            Direction::Forward => {
+                while chain.pending.len() < distance {
+                    chain
+                        .pending
+                        .push_back(self.builder.alloc_symbol(label.clone()))
+                }
+                let name = chain.pending[distance - 1];
+                self.anon_labels.forward_refs.push((name, span));
+                Some(name)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::diagnostics::mock::MockSpan;
+    use crate::expr::Atom;
+
+    #[test]
+    fn backward_ref_resolves_to_preceding_anon_label() {
+        let mut fixture = TestFixture::<()>::new();
+        let mut session = fixture.session();
+        define_label(&mut session, ":");
+        let first = query(&mut session, ":-");
+        define_label(&mut session, ":");
+        assert_eq!(query(&mut session, ":--"), first);
+        assert_ne!(query(&mut session, ":-"), first)
+    }
+
+    #[test]
+    fn forward_ref_resolves_to_following_anon_label() {
+        let mut fixture = TestFixture::<()>::new();
+        let mut session = fixture.session();
+        let next = query(&mut session, "1f");
+        let after_next = query(&mut session, ":++");
+        define_label(&mut session, "1");
+        define_label(&mut session, ":");
+        define_label(&mut session, ":");
+        assert_eq!(query(&mut session, "1b"), next);
+        assert_eq!(query(&mut session, ":-"), after_next)
+    }
+
+    #[test]
+    fn numeric_labels_are_independent() {
+        let mut fixture = TestFixture::<()>::new();
+        let mut session = fixture.session();
+        define_label(&mut session, "1");
+        let one = query(&mut session, "1b");
+        define_label(&mut session, "2");
+        assert_eq!(query(&mut session, "1b"), one)
+    }
+
+    #[test]
+    fn anon_label_does_not_close_local_scope() {
+        let name = "_local".into();
+        let mut fixture = TestFixture::<()>::new();
+        let mut session = fixture.session();
+        let entry1 = session.query_term(&name);
+        define_label(&mut session, ":");
+        let entry2 = session.query_term(&name);
+        assert_eq!(entry1, entry2)
+    }
+
+    #[test]
+    fn diagnose_backward_ref_without_label() {
+        let mut fixture = TestFixture::<MockSpan<_>>::new();
+        let mut session = fixture.session();
+        assert_eq!(
+            session.query_anon_label(
+                AnonLabelRef::parse(&":-".into()).unwrap(),
+                MockSpan::Basic("ref")
+            ),
+            None
+        );
+        assert_eq!(
+            session.log(),
+            [Event::EmitDiag {
+                diag: Message::UnmatchedLabelRef {
+                    reference: MockSpan::Basic("ref")
+                }
+                .at(MockSpan::Basic("ref"))
+                .into()
+            }]
+        )
+    }
+
+    #[test]
+    fn diagnose_forward_ref_without_label_at_end_of_input() {
+        let mut fixture = TestFixture::<MockSpan<_>>::new();
+        let mut session = fixture.session();
+        session.define_symbol(
+            ("1".into(), MockSpan::Basic("label")),
+            SymbolDef::Closure(Expr::from_atom(Atom::Location, MockSpan::Basic("label"))),
+        );
+        for (ident, span) in &[("1f", "ref1"), ("1f", "ref2")] {
+            let reference = AnonLabelRef::parse(&(*ident).into()).unwrap();
+            session.query_anon_label(reference, MockSpan::Basic(*span));
+        }
+        session.finish();
+        assert_eq!(
+            &session.log()[1..],
+            ["ref1", "ref2"]
+                .iter()
+                .map(|span| Event::EmitDiag {
+                    diag: Message::UnmatchedLabelRef {
+                        reference: MockSpan::Basic(*span)
+                    }
+                    .at(MockSpan::Basic(*span))
+                    .into()
+                })
+                .collect::<Vec<_>>()
+        )
+    }
+
+    #[test]
+    fn parse_anon_label_refs() {
+        for (ident, label, direction, distance) in &[
+            (":+", ":", Direction::Forward, 1),
+            (":---", ":", Direction::Backward, 3),
+            ("1f", "1", Direction::Forward, 1),
+            ("42b", "42", Direction::Backward, 1),
+        ] {
+            assert_eq!(
+                AnonLabelRef::parse(&(*ident).into()),
+                Some(AnonLabelRef {
+                    label: (*label).into(),
+                    direction: *direction,
+                    distance: *distance
+                })
+            )
+        }
+    }
+
+    #[test]
+    fn ordinary_idents_are_not_anon_label_refs() {
+        for ident in &[":", ":+-", "f", "b", "x1b", "label"] {
+            assert_eq!(AnonLabelRef::parse(&(*ident).into()), None)
+        }
+    }
+
+    fn define_label(session: &mut MockSession<()>, label: &str) {
+        session.define_symbol(
+            (label.into(), ()),
+            SymbolDef::Closure(Expr::from_atom(Atom::Location, ())),
+        )
+    }
+
+    fn query(session: &mut MockSession<()>, ident: &str) -> Option<Name> {
+        session.query_anon_label(AnonLabelRef::parse(&ident.into()).unwrap(), ())
+    }
+}
@@ -0,0 +1,260 @@
+use super::*;
+
+use crate::assembler::syntax::Lexer;
+use crate::diagnostics::Message;
+
+use std::rc::Rc;
+
+pub(super) struct StringExpansion<S> {
+    name: StringRef,
+    tokens: Lexer,
+    span: S,
+    depth: usize,
+}
+
+impl<'a, R: SpanSystem> CompositeSession<'a, R>
+where
+    Self: Diagnostics<R::Span>,
+{
+    pub(super) fn define_string(&mut self, (ident, span): (StringRef, R::Span), string: StringRef) {
+        let representative = ident.to_ascii_uppercase();
+        match self.names.global.get(representative.as_str()) {
+            Some(NameEntry::OperandKeyword(_)) | Some(NameEntry::Symbol(Name::Builtin(_))) => {
+                let name = self.strip_span(&span);
+                self.emit_diag(Message::CannotRedefineBuiltin { name }.at(span))
+            }
+            _ => {
+                self.strings.insert(ident, string);
+            }
+        }
+    }
+
+    pub(super) fn expand_string(&mut self, ident: &StringRef, span: &R::Span) -> bool {
+        let string = match self.strings.get(ident) {
+            Some(string) => Rc::from(&**string),
+            None => return false,
+        };
+        if self
+            .expansions
+            .iter()
+            .any(|expansion| expansion.name == *ident)
+        {
+            let name = self.strip_span(span);
+            self.emit_diag(Message::RecursiveDefinition { name }.at(span.clone()))
+        } else {
+            self.expansions.push(StringExpansion {
+                name: ident.clone(),
+                tokens: Lexer::within_line(string),
+                span: span.clone(),
+                depth: self.tokens.len(),
+            })
+        }
+        true
+    }
+
+    fn format_placeholder(&mut self, placeholder: &str, span: &R::Span) -> Option<String> {
+        let (spec, name) = placeholder.split_once(':').unwrap_or(("", placeholder));
+        if let Some(string) = self.strings.get(name) {
+            if spec.is_empty() {
+                return Some(string.to_string());
+            }
+        } else if let Some(n) = self.eval_symbol(name, span) {
+            if let Some(text) = format_num(spec, n) {
+                return Some(text);
+            }
+        } else {
+            let name = name.into();
+            self.emit_diag(Message::CannotInterpolate { name }.at(span.clone()));
+            return None;
+        }
+        let spec = spec.into();
+        self.emit_diag(Message::InvalidFormatSpec { spec }.at(span.clone()));
+        None
+    }
+
+    fn eval_symbol(&self, ident: &str, span: &R::Span) -> Option<i32> {
+        match self.names.select_table(ident).get(ident) {
+            Some(NameEntry::Symbol(name)) => self.eval_name(*name, span),
+            _ => None,
+        }
+    }
+}
+
+impl<'a, R: SpanSystem> CompositeSession<'a, R> {
+    pub(super) fn next_expanded_token(&mut self) -> Option<LexItem<R::Span>> {
+        while let Some(expansion) = self.expansions.last_mut() {
+            if expansion.depth != self.tokens.len() {
+                return None;
+            }
+            match expansion.tokens.next_token(&mut ()) {
+                Some((Ok(Token::Sigil(Sigil::Eos)), _)) | None => {
+                    self.expansions.pop();
+                }
+                Some((token, _)) => return Some((token, expansion.span.clone())),
+            }
+        }
+        None
+    }
+}
+
+impl<'a, R: SpanSystem> Interpolate<R::Span> for CompositeSession<'a, R>
+where
+    Self: Diagnostics<R::Span>,
+{
+    fn interpolate(&mut self, string: StringRef, span: &R::Span) -> StringRef {
+        if !string.contains('{') {
+            return string;
+        }
+        let mut text = String::new();
+        let mut rest = &*string;
+        while let Some((head, tail)) = rest.split_once('{') {
+            text.push_str(head);
+            rest = match tail.split_once('}') {
+                Some((placeholder, tail)) => {
+                    if let Some(value) = self.format_placeholder(placeholder, span) {
+                        text.push_str(&value)
+                    }
+                    tail
+                }
+                None => {
+                    text.push('{');
+                    tail
+                }
+            }
+        }
+        text.push_str(rest);
+        text.as_str().into()
+    }
+}
+
+fn format_num(spec: &str, n: i32) -> Option<String> {
+    let (width, kind) = match spec.chars().last() {
+        Some(kind) if kind.is_ascii_alphabetic() => (&spec[..spec.len() - 1], kind),
+        _ => (spec, 'd'),
+    };
+    let zero_padded = width.starts_with('0');
+    let width: usize = if width.is_empty() {
+        0
+    } else {
+        width.parse().ok()?
+    };
+    let digits = match kind {
+        'b' => format!("{:b}", n),
+        'd' => n.to_string(),
+        'x' => format!("{:x}", n),
+        'X' => format!("{:X}", n),
+        _ => return None,
+    };
+    let padding = width.saturating_sub(digits.len());
+    Some(if zero_padded {
+        let (sign, digits) = digits.split_at(if n < 0 && kind == 'd' { 1 } else { 0 });
+        format!("{}{}{}", sign, "0".repeat(padding), digits)
+    } else {
+        format!("{}{}", " ".repeat(padding), digits)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::assembler::syntax::Literal;
+
+    #[test]
+    fn format_num_with_spec() {
+        assert_eq!(format_num("", 42), Some("42".into()));
+        assert_eq!(format_num("x", 0xbeef), Some("beef".into()));
+        assert_eq!(format_num("04X", 0x2a), Some("002A".into()));
+        assert_eq!(format_num("8b", 5), Some("     101".into()));
+        assert_eq!(format_num("05d", -42), Some("-0042".into()));
+        assert_eq!(format_num("q", 1), None)
+    }
+
+    #[test]
+    fn interpolate_numeric_and_string_symbols() {
+        let mut fixture = TestFixture::<()>::new();
+        let mut session = fixture.session();
+        session.define_symbol(("N".into(), ()), SymbolDef::Closure(42.into()));
+        session.define_symbol(("S".into(), ()), SymbolDef::String("v1".into()));
+        assert_eq!(
+            *session.interpolate("{S}: {N}, ${04x:N}, {".into(), &()),
+            *"v1: 42, $002a, {"
+        )
+    }
+
+    #[test]
+    fn diagnose_interpolation_of_undefined_symbol() {
+        let mut fixture = TestFixture::<()>::new();
+        let mut session = fixture.session();
+        assert_eq!(*session.interpolate("<{X}>".into(), &()), *"<>");
+        assert_eq!(
+            session.log(),
+            [Event::EmitDiag {
+                diag: Message::CannotInterpolate { name: "X".into() }
+                    .at(())
+                    .into()
+            }]
+        )
+    }
+
+    #[test]
+    fn diagnose_invalid_format_spec() {
+        let mut fixture = TestFixture::<()>::new();
+        let mut session = fixture.session();
+        session.define_symbol(("N".into(), ()), SymbolDef::Closure(1.into()));
+        session.interpolate("{z:N}".into(), &());
+        assert_eq!(
+            session.log()[1..],
+            [Event::EmitDiag {
+                diag: Message::InvalidFormatSpec { spec: "z".into() }
+                    .at(())
+                    .into()
+            }]
+        )
+    }
+
+    #[test]
+    fn expand_string_symbol_into_tokens() {
+        let mut fixture = TestFixture::<()>::new();
+        fixture.fs.add("file", b"    X Y");
+        let mut session = fixture.session();
+        session.define_symbol(("X".into(), ()), SymbolDef::String("db 1".into()));
+        let tokens = session.lex_file("file".into(), None).unwrap();
+        session.tokens.push(Box::new(tokens));
+        assert_eq!(
+            collect_tokens(&mut session),
+            [
+                Token::Ident("db".into()),
+                Token::Literal(Literal::Number(1)),
+                Token::Ident("Y".into()),
+                Sigil::Eos.into(),
+            ]
+        )
+    }
+
+    #[test]
+    fn diagnose_recursive_string_expansion() {
+        let mut fixture = TestFixture::<()>::new();
+        fixture.fs.add("file", b"    X");
+        let mut session = fixture.session();
+        session.define_symbol(("X".into(), ()), SymbolDef::String("Y".into()));
+        session.define_symbol(("Y".into(), ()), SymbolDef::String("X".into()));
+        let tokens = session.lex_file("file".into(), None).unwrap();
+        session.tokens.push(Box::new(tokens));
+        assert_eq!(collect_tokens(&mut session), [Sigil::Eos.into()]);
+        assert_eq!(
+            session.log()[2..],
+            [Event::EmitDiag {
+                diag: Message::RecursiveDefinition { name: () }.at(()).into()
+            }]
+        )
+    }
+
+    fn collect_tokens(session: &mut MockSession<()>) -> Vec<SemanticToken> {
+        let mut tokens = Vec::new();
+        while !session.tokens.is_empty() {
+            tokens.push(session.next_token().unwrap().0.unwrap())
+        }
+        tokens
+    }
+}
@@ -1,7 +1,7 @@
 use super::Token;
 
 use crate::diagnostics::CompactDiag;
-use crate::expr::BinOp;
+use crate::expr::{BinOp, UnaryOp};
 
 pub(crate) trait ParsingContext: Sized {
     type Ident;
@@ -279,6 +279,7 @@ pub(crate) enum Operator {
 
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) enum UnaryOperator {
+    Op(UnaryOp),
     Parentheses,
 }
 
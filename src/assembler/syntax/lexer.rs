@@ -2,6 +2,7 @@ use super::Sigil::*;
 use super::{LexItem, Literal, SemanticToken, Sigil, Token};
 
 use crate::assembler::string_ref::StringRef;
+use crate::diagnostics::Message;
 
 use std::borrow::Borrow;
 use std::ops::Range;
@@ -10,9 +11,12 @@ use std::str;
 
 #[derive(Clone, Copy, PartialEq)]
 enum TokenKind {
+    Char,
+    FixedPoint,
     Ident,
     Label,
     Number(Radix),
+    NumericLabel,
     Sigil(Sigil),
     String,
 }
@@ -25,21 +29,48 @@ impl From<Sigil> for TokenKind {
 
 #[derive(Clone, Copy, PartialEq)]
 enum Radix {
+    Binary,
     Decimal,
     Hexadecimal,
+    Octal,
+}
+
+impl Radix {
+    fn base(self) -> u32 {
+        match self {
+            Radix::Binary => 2,
+            Radix::Decimal => 10,
+            Radix::Hexadecimal => 16,
+            Radix::Octal => 8,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum LexError {
+    InvalidCharLiteral,
     NoDigits,
+    Overflow,
     UnterminatedString,
 }
 
+impl<S> From<LexError> for Message<S> {
+    fn from(error: LexError) -> Self {
+        match error {
+            LexError::InvalidCharLiteral => Message::InvalidCharLiteral,
+            LexError::NoDigits => Message::NoDigits,
+            LexError::Overflow => Message::LiteralOutOfRange,
+            LexError::UnterminatedString => Message::UnterminatedString,
+        }
+    }
+}
+
 struct Scanner<B> {
     src: B,
     range: Range<usize>,
     is_at_line_start: bool,
     is_at_file_end: bool,
+    prev_token: Option<(TokenKind, usize)>,
 }
 
 impl<B: Borrow<str>> Iterator for Scanner<B> {
@@ -49,7 +80,9 @@ impl<B: Borrow<str>> Iterator for Scanner<B> {
         self.skip_irrelevant_characters();
         if self.range.end < self.src.borrow().len() {
             self.range.start = self.range.end;
-            Some(self.lex_token())
+            let (token, range) = self.lex_token();
+            self.prev_token = token.ok().map(|kind| (kind, range.end));
+            Some((token, range))
         } else if !self.is_at_file_end {
             self.is_at_file_end = true;
             Some((Ok(Eos.into()), self.range.end..self.range.end))
@@ -66,6 +99,7 @@ impl<B: Borrow<str>> Scanner<B> {
             range: Range { start: 0, end: 0 },
             is_at_line_start: true,
             is_at_file_end: false,
+            prev_token: None,
         }
     }
 
@@ -77,7 +111,7 @@ impl<B: Borrow<str>> Scanner<B> {
     }
 
     fn skip_characters_if<P: FnMut(char) -> bool>(&mut self, mut predicate: P) {
-        while self.current_char().map_or(false, &mut predicate) {
+        while self.current_char().is_some_and(&mut predicate) {
             self.advance();
         }
     }
@@ -86,6 +120,21 @@ impl<B: Borrow<str>> Scanner<B> {
         self.src.borrow()[self.range.end..].chars().next()
     }
 
+    fn next_char(&mut self) -> Option<char> {
+        self.src.borrow()[self.range.end..].chars().nth(1)
+    }
+
+    // `&` and `%` are radix prefixes only where an operand may start, so that `7&3` and `X%2` are
+    // operations. An identifier separated by whitespace may be a keyword, as in `DB %101`.
+    fn follows_operand(&self) -> bool {
+        match self.prev_token {
+            Some((TokenKind::Ident, end)) => end == self.range.start,
+            Some((TokenKind::Sigil(sigil), _)) => sigil == Dot || sigil == RParen,
+            Some((TokenKind::Label, _)) | None => false,
+            Some(_) => true,
+        }
+    }
+
     fn advance(&mut self) -> Option<char> {
         let current = self.current_char();
         self.is_at_line_start = current == Some('\n');
@@ -96,20 +145,35 @@ impl<B: Borrow<str>> Scanner<B> {
     fn lex_token(&mut self) -> <Self as Iterator>::Item {
         let first_char = self.current_char().unwrap();
         let next_token = match first_char {
+            ':' => self.lex_anon_label(),
+            '&' if !self.follows_operand() && self.next_char().is_some_and(is_oct_digit) => {
+                self.lex_number(Radix::Octal)
+            }
+            '&' => self.lex_one_or_two(Amp, ('&', AmpAmp)),
+            '!' => self.lex_one_or_two(Bang, ('=', BangEq)),
+            '^' => self.take(Caret),
             ',' => self.take(Comma),
             '.' => self.take(Dot),
             '\n' => self.take(Eol),
             '=' => self.lex_eq_or_eq_eq(),
+            '>' => self.lex_angle_bracket(Gt, GtEq, GtGt),
             '(' => self.take(LParen),
+            '<' => self.lex_angle_bracket(Lt, LtEq, LtLt),
             '-' => self.take(Minus),
-            '|' => self.take(Pipe),
+            '%' if !self.follows_operand() && self.next_char().is_some_and(is_bin_digit) => {
+                self.lex_number(Radix::Binary)
+            }
+            '%' => self.take(Percent),
+            '|' => self.lex_one_or_two(Pipe, ('|', PipePipe)),
             '+' => self.take(Plus),
             ')' => self.take(RParen),
             '/' => self.take(Slash),
             '*' => self.take(Star),
+            '~' => self.take(Tilde),
             '0'..='9' => self.lex_decimal_number(),
-            '$' => self.lex_hex_number(),
+            '$' => self.lex_number(Radix::Hexadecimal),
             '"' => self.lex_quoted_string(),
+            '\'' => self.lex_char(),
             _ => self.lex_ident(),
         };
         (next_token, self.range.clone())
@@ -128,16 +192,90 @@ impl<B: Borrow<str>> Scanner<B> {
         }
     }
 
+    fn lex_one_or_two(
+        &mut self,
+        one: Sigil,
+        (next, two): (char, Sigil),
+    ) -> Result<TokenKind, LexError> {
+        self.advance();
+        if self.current_char() == Some(next) {
+            self.take(two)
+        } else {
+            Ok(one.into())
+        }
+    }
+
+    fn lex_angle_bracket(
+        &mut self,
+        single: Sigil,
+        or_eq: Sigil,
+        double: Sigil,
+    ) -> Result<TokenKind, LexError> {
+        let bracket = self.advance();
+        match self.current_char() {
+            Some('=') => self.take(or_eq),
+            next if next == bracket => self.take(double),
+            _ => Ok(single.into()),
+        }
+    }
+
+    fn lex_anon_label(&mut self) -> Result<TokenKind, LexError> {
+        if self.is_at_line_start {
+            return self.take(TokenKind::Label);
+        }
+        self.advance();
+        match self.current_char() {
+            Some(arrow @ '+') | Some(arrow @ '-') => self.skip_characters_if(|c| c == arrow),
+            _ => self.find_word_end(),
+        }
+        Ok(TokenKind::Ident)
+    }
+
     fn lex_decimal_number(&mut self) -> Result<TokenKind, LexError> {
+        let is_label = self.is_at_line_start;
+        let is_zero = self.current_char() == Some('0');
         self.advance();
-        self.skip_characters_if(is_dec_digit);
-        Ok(TokenKind::Number(Radix::Decimal))
+        if is_zero {
+            match (self.current_char(), self.next_char()) {
+                (Some('x'), Some(digit)) | (Some('X'), Some(digit)) if is_hex_digit(digit) => {
+                    return self.lex_number(Radix::Hexadecimal)
+                }
+                (Some('b'), Some(digit)) | (Some('B'), Some(digit)) if is_bin_digit(digit) => {
+                    return self.lex_number(Radix::Binary)
+                }
+                _ => (),
+            }
+        }
+        self.skip_characters_if(|c| is_dec_digit(c) || c == '_');
+        match self.current_char() {
+            Some(':') if is_label => self.take(TokenKind::NumericLabel),
+            Some('b') | Some('f') if !self.next_char().is_some_and(is_ident_continuation) => {
+                self.take(TokenKind::Ident)
+            }
+            Some('.') if self.next_char().is_some_and(is_dec_digit) => {
+                self.advance();
+                self.skip_characters_if(is_dec_digit);
+                Ok(TokenKind::FixedPoint)
+            }
+            _ => Ok(TokenKind::Number(Radix::Decimal)),
+        }
     }
 
-    fn lex_hex_number(&mut self) -> Result<TokenKind, LexError> {
+    fn lex_number(&mut self, radix: Radix) -> Result<TokenKind, LexError> {
         self.advance();
-        self.skip_characters_if(is_hex_digit);
-        Ok(TokenKind::Number(Radix::Hexadecimal))
+        self.skip_characters_if(|c| c.is_digit(radix.base()) || c == '_');
+        Ok(TokenKind::Number(radix))
+    }
+
+    fn lex_char(&mut self) -> Result<TokenKind, LexError> {
+        self.advance();
+        self.skip_characters_if(|c| c != '\'' && c != '\n');
+        if self.current_char() == Some('\'') {
+            self.advance();
+            Ok(TokenKind::Char)
+        } else {
+            Err(LexError::InvalidCharLiteral)
+        }
     }
 
     fn lex_quoted_string(&mut self) -> Result<TokenKind, LexError> {
@@ -176,11 +314,19 @@ fn is_ident_continuation(character: char) -> bool {
 }
 
 fn is_dec_digit(character: char) -> bool {
-    character.is_digit(10)
+    character.is_ascii_digit()
 }
 
 fn is_hex_digit(character: char) -> bool {
-    character.is_digit(16)
+    character.is_ascii_hexdigit()
+}
+
+fn is_oct_digit(character: char) -> bool {
+    character.is_digit(8)
+}
+
+fn is_bin_digit(character: char) -> bool {
+    character == '0' || character == '1'
 }
 
 pub struct Lexer {
@@ -189,15 +335,26 @@ pub struct Lexer {
 
 fn mk_token(kind: TokenKind, lexeme: StringRef) -> Result<SemanticToken, LexError> {
     match kind {
+        TokenKind::Char => {
+            let mut chars = lexeme[1..lexeme.len() - 1].chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(Token::Literal(Literal::Number(c as i32))),
+                _ => Err(LexError::InvalidCharLiteral),
+            }
+        }
+        TokenKind::FixedPoint => {
+            let lexeme = strip_separators(&lexeme);
+            let (integer, fraction) = lexeme.split_once('.').unwrap();
+            crate::fixed::parse(integer, fraction)
+                .map(|n| Token::Literal(Literal::Number(n)))
+                .ok_or(LexError::Overflow)
+        }
         TokenKind::Ident => Ok(Token::Ident(lexeme)),
         TokenKind::Label => Ok(Token::Label(lexeme)),
-        TokenKind::Number(Radix::Decimal) => Ok(Token::Literal(Literal::Number(
-            i32::from_str_radix(lexeme.as_ref(), 10).unwrap(),
-        ))),
-        TokenKind::Number(Radix::Hexadecimal) => match i32::from_str_radix(&lexeme[1..], 16) {
-            Ok(n) => Ok(Token::Literal(Literal::Number(n))),
-            Err(_) => Err(LexError::NoDigits),
-        },
+        TokenKind::Number(radix) => {
+            parse_number(&lexeme, radix).map(|n| Token::Literal(Literal::Number(n)))
+        }
+        TokenKind::NumericLabel => Ok(Token::Label(lexeme.substring(..lexeme.len() - 1))),
         TokenKind::Sigil(sigil) => Ok(Token::Sigil(sigil)),
         TokenKind::String => Ok(Token::Literal(Literal::String(
             lexeme.substring(1..(lexeme.len() - 1)),
@@ -205,6 +362,28 @@ fn mk_token(kind: TokenKind, lexeme: StringRef) -> Result<SemanticToken, LexErro
     }
 }
 
+fn parse_number(lexeme: &str, radix: Radix) -> Result<i32, LexError> {
+    let digits = match radix {
+        Radix::Decimal => lexeme,
+        _ if lexeme.starts_with('0') => &lexeme[2..],
+        _ => &lexeme[1..],
+    };
+    let digits = strip_separators(digits);
+    if digits.is_empty() {
+        return Err(LexError::NoDigits);
+    }
+    match radix {
+        Radix::Decimal => digits.parse().map_err(|_| LexError::Overflow),
+        _ => u32::from_str_radix(&digits, radix.base())
+            .map(|n| n as i32)
+            .map_err(|_| LexError::Overflow),
+    }
+}
+
+fn strip_separators(lexeme: &str) -> String {
+    lexeme.chars().filter(|&c| c != '_').collect()
+}
+
 impl Lexer {
     pub fn new(src: Rc<str>) -> Self {
         Lexer {
@@ -212,6 +391,12 @@ impl Lexer {
         }
     }
 
+    pub fn within_line(src: Rc<str>) -> Self {
+        let mut scanner = Scanner::new(src);
+        scanner.is_at_line_start = false;
+        Lexer { scanner }
+    }
+
     pub(crate) fn next_token<R>(&mut self, _registry: &mut R) -> Option<LexItem<Range<usize>>> {
         self.scanner.next().map(|(result, range)| {
             (
@@ -352,6 +537,88 @@ mod tests {
         assert_eq_tokens("$19af", [Literal(Number(0x19af))])
     }
 
+    #[test]
+    fn lex_prefixed_hex_number() {
+        assert_eq_tokens("0xFFFF_FFFF", [Literal(Number(-1))])
+    }
+
+    #[test]
+    fn lex_binary_number() {
+        assert_eq_tokens("%1010_0101", [Literal(Number(0xa5))]);
+        assert_eq_tokens("0b11", [Literal(Number(3))])
+    }
+
+    #[test]
+    fn lex_octal_number() {
+        assert_eq_tokens("&17", [Literal(Number(0o17))])
+    }
+
+    #[test]
+    fn lex_decimal_number_with_separators() {
+        assert_eq_tokens("1_000_000", [Literal(Number(1_000_000))])
+    }
+
+    #[test]
+    fn lex_percent_and_amp_as_operators_before_non_digits() {
+        assert_eq_tokens(
+            "%x &y",
+            [
+                Percent.into(),
+                Ident("x".into()),
+                Amp.into(),
+                Ident("y".into()),
+            ],
+        )
+    }
+
+    #[test]
+    fn lex_percent_and_amp_as_operators_after_operands() {
+        assert_eq_tokens(
+            " 7&3 X%1 7 &3 (1)%1 X &3",
+            [
+                Literal(Number(7)),
+                Amp.into(),
+                Literal(Number(3)),
+                Ident("X".into()),
+                Percent.into(),
+                Literal(Number(1)),
+                Literal(Number(7)),
+                Amp.into(),
+                Literal(Number(3)),
+                LParen.into(),
+                Literal(Number(1)),
+                RParen.into(),
+                Percent.into(),
+                Literal(Number(1)),
+                Ident("X".into()),
+                Literal(Number(0o3)),
+            ],
+        )
+    }
+
+    #[test]
+    fn lex_radix_prefixes_after_operators() {
+        assert_eq_tokens(
+            " DB %11, -&7, (&7)",
+            [
+                Ident("DB".into()),
+                Literal(Number(3)),
+                Comma.into(),
+                Minus.into(),
+                Literal(Number(7)),
+                Comma.into(),
+                LParen.into(),
+                Literal(Number(7)),
+                RParen.into(),
+            ],
+        )
+    }
+
+    #[test]
+    fn lex_char_literal() {
+        assert_eq_tokens("'A'", [Literal(Number(0x41))])
+    }
+
     #[test]
     fn lex_label() {
         assert_eq_tokens(
@@ -360,6 +627,52 @@ mod tests {
         )
     }
 
+    #[test]
+    fn lex_anon_label() {
+        assert_eq_tokens(
+            ":  nop\n",
+            [Label(":".into()), Ident("nop".into()), Eol.into()],
+        )
+    }
+
+    #[test]
+    fn lex_anon_label_refs() {
+        assert_eq_tokens(
+            "    :+ :++ :- :---",
+            [
+                Ident(":+".into()),
+                Ident(":++".into()),
+                Ident(":-".into()),
+                Ident(":---".into()),
+            ],
+        )
+    }
+
+    #[test]
+    fn lex_numeric_label() {
+        assert_eq_tokens("12: nop", [Label("12".into()), Ident("nop".into())])
+    }
+
+    #[test]
+    fn lex_numeric_label_refs() {
+        assert_eq_tokens("    1b 23f", [Ident("1b".into()), Ident("23f".into())])
+    }
+
+    #[test]
+    fn lex_fixed_point_number() {
+        assert_eq_tokens("1.5", [Literal(Number(0x1_8000))])
+    }
+
+    #[test]
+    fn lex_fixed_point_number_out_of_range() {
+        assert_eq_lex_results("32768.0", vec![Err(LexError::Overflow)])
+    }
+
+    #[test]
+    fn lex_decimal_number_followed_by_ident() {
+        assert_eq_tokens("    1bc", [Literal(Number(1)), Ident("bc".into())])
+    }
+
     #[test]
     fn lex_label_after_eol() {
         assert_eq_tokens("    \nlabel", [Eol.into(), Label("label".into())])
@@ -410,6 +723,45 @@ mod tests {
         assert_eq_tokens("==", [EqEq.into()])
     }
 
+    #[test]
+    fn lex_bitwise_operators() {
+        assert_eq_tokens(
+            "& | ^ ~ << >>",
+            [
+                Amp.into(),
+                Pipe.into(),
+                Caret.into(),
+                Tilde.into(),
+                LtLt.into(),
+                GtGt.into(),
+            ],
+        )
+    }
+
+    #[test]
+    fn lex_comparison_operators() {
+        assert_eq_tokens(
+            "!= < > <= >=",
+            [
+                BangEq.into(),
+                Lt.into(),
+                Gt.into(),
+                LtEq.into(),
+                GtEq.into(),
+            ],
+        )
+    }
+
+    #[test]
+    fn lex_logical_operators() {
+        assert_eq_tokens("&& || !", [AmpAmp.into(), PipePipe.into(), Bang.into()])
+    }
+
+    #[test]
+    fn lex_percent() {
+        assert_eq_tokens("%", [Percent.into()])
+    }
+
     #[test]
     fn ignore_comment() {
         assert_eq_tokens("; comment", [])
@@ -434,4 +786,19 @@ mod tests {
     fn lex_number_without_digits() {
         assert_eq_lex_results("$", vec![Err(LexError::NoDigits)])
     }
+
+    #[test]
+    fn lex_decimal_number_too_large_for_i32() {
+        assert_eq_lex_results("2147483648", vec![Err(LexError::Overflow)])
+    }
+
+    #[test]
+    fn lex_hex_number_too_large_for_32_bits() {
+        assert_eq_lex_results("$1_0000_0000", vec![Err(LexError::Overflow)])
+    }
+
+    #[test]
+    fn lex_char_literal_with_two_chars() {
+        assert_eq_lex_results("'ab'", vec![Err(LexError::InvalidCharLiteral)])
+    }
 }
@@ -28,18 +28,32 @@ pub enum Token<I, L> {
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Sigil {
+    Amp,
+    AmpAmp,
+    Bang,
+    BangEq,
+    Caret,
     Comma,
     Dot,
     Eos,
     Eol,
     EqEq,
+    Gt,
+    GtEq,
+    GtGt,
     LParen,
+    Lt,
+    LtEq,
+    LtLt,
     Minus,
+    Percent,
     Pipe,
+    PipePipe,
     Plus,
     RParen,
     Slash,
     Star,
+    Tilde,
 }
 
 impl<I, L> From<Sigil> for Token<I, L> {
@@ -6,12 +6,13 @@ use super::{Parser, LINE_FOLLOW_SET};
 
 use crate::assembler::syntax;
 use crate::diagnostics::{CompactDiag, Message};
-use crate::expr::BinOp;
+use crate::expr::{BinOp, UnaryOp};
 
 type ParserResult<P, C, S> = Result<P, (P, ExpandedExprParsingError<C, S>)>;
 type ExpandedExprParsingError<D, S> = ExprParsingError<S, <D as ParsingContext>::Stripped>;
 
 enum ExprParsingError<S, R> {
+    InvalidToken,
     NothingParsed,
     Other(CompactDiag<S, R>),
 }
@@ -25,25 +26,52 @@ impl<I, L> Token<I, L> {
     fn as_suffix_operator(&self) -> Option<SuffixOperator> {
         use SuffixOperator::*;
         match self {
+            Token::Sigil(Amp) => Some(Binary(BinOp::BitAnd)),
+            Token::Sigil(AmpAmp) => Some(Binary(BinOp::LogicalAnd)),
+            Token::Sigil(BangEq) => Some(Binary(BinOp::Inequality)),
+            Token::Sigil(Caret) => Some(Binary(BinOp::BitXor)),
             Token::Sigil(EqEq) => Some(Binary(BinOp::Equality)),
+            Token::Sigil(Gt) => Some(Binary(BinOp::Greater)),
+            Token::Sigil(GtEq) => Some(Binary(BinOp::GreaterOrEqual)),
+            Token::Sigil(GtGt) => Some(Binary(BinOp::ShiftRight)),
             Token::Sigil(Minus) => Some(Binary(BinOp::Minus)),
             Token::Sigil(LParen) => Some(FnCall),
+            Token::Sigil(Lt) => Some(Binary(BinOp::Less)),
+            Token::Sigil(LtEq) => Some(Binary(BinOp::LessOrEqual)),
+            Token::Sigil(LtLt) => Some(Binary(BinOp::ShiftLeft)),
+            Token::Sigil(Percent) => Some(Binary(BinOp::Modulo)),
             Token::Sigil(Pipe) => Some(Binary(BinOp::BitOr)),
+            Token::Sigil(PipePipe) => Some(Binary(BinOp::LogicalOr)),
             Token::Sigil(Plus) => Some(Binary(BinOp::Plus)),
             Token::Sigil(Slash) => Some(Binary(BinOp::Division)),
             Token::Sigil(Star) => Some(Binary(BinOp::Multiplication)),
             _ => None,
         }
     }
+
+    fn as_prefix_operator(&self) -> Option<UnaryOp> {
+        match self {
+            Token::Sigil(Bang) => Some(UnaryOp::Not),
+            Token::Sigil(Minus) => Some(UnaryOp::Negation),
+            Token::Sigil(Tilde) => Some(UnaryOp::BitNot),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, PartialOrd)]
 enum Precedence {
     None,
+    LogicalOr,
+    LogicalAnd,
     Comparison,
     BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
     Addition,
     Multiplication,
+    Unary,
     FnCall,
 }
 
@@ -51,10 +79,22 @@ impl SuffixOperator {
     fn precedence(&self) -> Precedence {
         use SuffixOperator::*;
         match self {
-            Binary(BinOp::Equality) => Precedence::Comparison,
+            Binary(BinOp::LogicalOr) => Precedence::LogicalOr,
+            Binary(BinOp::LogicalAnd) => Precedence::LogicalAnd,
+            Binary(BinOp::Equality)
+            | Binary(BinOp::Inequality)
+            | Binary(BinOp::Less)
+            | Binary(BinOp::LessOrEqual)
+            | Binary(BinOp::Greater)
+            | Binary(BinOp::GreaterOrEqual) => Precedence::Comparison,
             Binary(BinOp::BitOr) => Precedence::BitOr,
+            Binary(BinOp::BitXor) => Precedence::BitXor,
+            Binary(BinOp::BitAnd) => Precedence::BitAnd,
+            Binary(BinOp::ShiftLeft) | Binary(BinOp::ShiftRight) => Precedence::Shift,
             Binary(BinOp::Plus) | Binary(BinOp::Minus) => Precedence::Addition,
-            Binary(BinOp::Multiplication) | Binary(BinOp::Division) => Precedence::Multiplication,
+            Binary(BinOp::Multiplication) | Binary(BinOp::Division) | Binary(BinOp::Modulo) => {
+                Precedence::Multiplication
+            }
             FnCall => Precedence::FnCall,
         }
     }
@@ -69,6 +109,7 @@ where
         self.parse_expression()
             .unwrap_or_else(|(mut parser, error)| {
                 match error {
+                    ExprParsingError::InvalidToken => (),
                     ExprParsingError::NothingParsed => parser = parser.diagnose_unexpected_token(),
                     ExprParsingError::Other(diagnostic) => parser.actions.emit_diag(diagnostic),
                 }
@@ -172,6 +213,24 @@ where
     }
 
     fn parse_primary_expr(mut self) -> ParserResult<Self, A, S> {
+        let prefix_operator = self
+            .state
+            .token
+            .0
+            .as_ref()
+            .ok()
+            .and_then(Token::as_prefix_operator);
+        if let Some(operator) = prefix_operator {
+            let span = self.state.token.1;
+            bump!(self);
+            let mut result = self.parse_infix_expr(Precedence::Unary);
+            result.with_parser(|parser| {
+                parser
+                    .actions
+                    .act_on_operator(Operator::Unary(UnaryOperator::Op(operator)), span)
+            });
+            return result;
+        }
         match self.state.token {
             (Ok(Token::Sigil(LParen)), span) => {
                 bump!(self);
@@ -204,6 +263,11 @@ where
                 bump!(self);
                 Ok(self)
             }
+            Err(_) => {
+                self.actions.act_on_atom(ExprAtom::Error, span);
+                bump!(self);
+                Err((self, ExprParsingError::InvalidToken))
+            }
             _ => {
                 self.actions.act_on_atom(ExprAtom::Error, span.clone());
                 let stripped = self.actions.strip_span(&span);
@@ -351,6 +415,108 @@ mod tests {
         assert_eq_rpn_expr(tokens, expected)
     }
 
+    #[test]
+    fn shift_precedes_bitwise_and() {
+        let tokens = input_tokens![
+            x @ Ident(IdentKind::Other),
+            amp @ Amp,
+            y @ Literal(()),
+            shl @ LtLt,
+            z @ Literal(()),
+        ];
+        let expected = expr()
+            .ident("x")
+            .literal("y")
+            .literal("z")
+            .binary(BinOp::ShiftLeft, "shl")
+            .binary(BinOp::BitAnd, "amp");
+        assert_eq_rpn_expr(tokens, expected)
+    }
+
+    #[test]
+    fn bitwise_operators_precede_comparison() {
+        let tokens = input_tokens![
+            x @ Ident(IdentKind::Other),
+            caret @ Caret,
+            y @ Literal(()),
+            ne @ BangEq,
+            z @ Literal(()),
+        ];
+        let expected = expr()
+            .ident("x")
+            .literal("y")
+            .binary(BinOp::BitXor, "caret")
+            .literal("z")
+            .binary(BinOp::Inequality, "ne");
+        assert_eq_rpn_expr(tokens, expected)
+    }
+
+    #[test]
+    fn comparison_precedes_logical_operators() {
+        let tokens = input_tokens![
+            a @ Literal(()),
+            lt @ Lt,
+            b @ Literal(()),
+            or @ PipePipe,
+            c @ Literal(()),
+            and @ AmpAmp,
+            d @ Literal(()),
+            ge @ GtEq,
+            e @ Literal(()),
+        ];
+        let expected = expr()
+            .literal("a")
+            .literal("b")
+            .binary(BinOp::Less, "lt")
+            .literal("c")
+            .literal("d")
+            .literal("e")
+            .binary(BinOp::GreaterOrEqual, "ge")
+            .binary(BinOp::LogicalAnd, "and")
+            .binary(BinOp::LogicalOr, "or");
+        assert_eq_rpn_expr(tokens, expected)
+    }
+
+    #[test]
+    fn parse_modulo() {
+        let tokens = input_tokens![x @ Ident(IdentKind::Other), percent @ Percent, y @ Literal(())];
+        let expected = expr()
+            .ident("x")
+            .literal("y")
+            .binary(BinOp::Modulo, "percent");
+        assert_eq_rpn_expr(tokens, expected)
+    }
+
+    #[test]
+    fn unary_minus_precedes_multiplication() {
+        let tokens = input_tokens![
+            minus @ Minus,
+            x @ Ident(IdentKind::Other),
+            star @ Star,
+            y @ Literal(()),
+        ];
+        let expected = expr()
+            .ident("x")
+            .unary(UnaryOp::Negation, "minus")
+            .literal("y")
+            .multiply("star");
+        assert_eq_rpn_expr(tokens, expected)
+    }
+
+    #[test]
+    fn parse_nested_prefix_operators() {
+        let tokens = input_tokens![
+            bang @ Bang,
+            tilde @ Tilde,
+            x @ Ident(IdentKind::Other),
+        ];
+        let expected = expr()
+            .ident("x")
+            .unary(UnaryOp::BitNot, "tilde")
+            .unary(UnaryOp::Not, "bang");
+        assert_eq_rpn_expr(tokens, expected)
+    }
+
     #[test]
     fn parse_nullary_fn_call() {
         let tokens = input_tokens![name @ Ident(IdentKind::Other), left @ LParen, right @ RParen];
@@ -247,6 +247,12 @@ where
                 bump!(self);
                 self.parse_key(ident, span)
             }
+            (Err(_), _) => {
+                while !self.token_is_in(LINE_FOLLOW_SET) {
+                    bump!(self);
+                }
+                self.parse_line_terminator()
+            }
             (_, span) => {
                 bump!(self);
                 let stripped = self.actions.strip_span(&span);
@@ -355,7 +361,7 @@ where
                         bump!(parser);
                         parser.actions.act_on_token((other, span))
                     }
-                    (Err(_), _) => unimplemented!(),
+                    (Err(_), _) => bump!(parser),
                 }
             }
             parser.change_context(MacroArgContext::did_parse_macro_arg)
@@ -389,7 +395,7 @@ where
                     bump!(self);
                     self.actions.act_on_token(token, span)
                 }
-                (Err(_), _) => unimplemented!(),
+                (Err(_), _) => bump!(self),
             }
         }
     }
@@ -443,7 +449,10 @@ where
     }
 
     fn diagnose_unexpected_token(mut self) -> Self {
-        if self.token_kind() == Some(Token::Sigil(Eos)) {
+        // Lexical errors have already been diagnosed by the token source.
+        if self.state.token.0.is_err() {
+            bump!(self)
+        } else if self.token_kind() == Some(Token::Sigil(Eos)) {
             if self.state.recovery.is_none() {
                 self.actions
                     .emit_diag(Message::UnexpectedEof.at(self.state.token.1.clone()));
@@ -468,7 +477,7 @@ mod tests {
     use crate::assembler::syntax::actions::mock::IdentKind::*;
     use crate::assembler::syntax::actions::mock::*;
     use crate::diagnostics::{CompactDiag, Merge, Message};
-    use crate::expr::BinOp;
+    use crate::expr::{BinOp, UnaryOp};
 
     use std::borrow::Borrow;
     use std::collections::HashMap;
@@ -964,9 +973,6 @@ mod tests {
         tokens.into_iter().cloned().map(|(t, r)| (Ok(t), r.into()))
     }
 
-    #[derive(Debug, PartialEq)]
-    pub struct MacroId(pub TokenRef);
-
     pub(super) fn expr() -> SymExpr {
         SymExpr(Vec::new())
     }
@@ -1034,6 +1040,22 @@ mod tests {
             self
         }
 
+        pub fn binary(mut self, op: BinOp, token: impl Into<TokenRef>) -> Self {
+            self.0.push(ExprAction::ApplyOperator(
+                Operator::Binary(op),
+                token.into().into(),
+            ));
+            self
+        }
+
+        pub fn unary(mut self, op: UnaryOp, token: impl Into<TokenRef>) -> Self {
+            self.0.push(ExprAction::ApplyOperator(
+                Operator::Unary(UnaryOperator::Op(op)),
+                token.into().into(),
+            ));
+            self
+        }
+
         pub fn plus(mut self, token: impl Into<TokenRef>) -> Self {
             self.0.push(ExprAction::ApplyOperator(
                 Operator::Binary(BinOp::Plus),
@@ -1079,9 +1101,6 @@ mod tests {
         }
     }
 
-    #[derive(Clone, Debug, PartialEq)]
-    pub struct SymCommand(pub TokenRef);
-
     #[derive(Clone, Debug, PartialEq)]
     pub struct MockIdent(pub IdentKind, pub TokenRef);
 
@@ -6,8 +6,9 @@ use std::io::Write;
 fn main() {
     let mut args = std::env::args();
     args.next();
-    let args: Vec<String> = args.collect();
-    if args.len() <= 0 {
+    let (flags, args): (Vec<String>, Vec<String>) = args.partition(|arg| arg.starts_with("--"));
+    let gc_sections = flags.iter().any(|flag| flag == "--gc-sections");
+    if args.is_empty() {
         println!("{}: error: no input files", args[0]);
         std::process::exit(1)
     }
@@ -15,6 +16,7 @@ fn main() {
     let mut config = Config {
         input: InputConfig::default(),
         diagnostics: DiagnosticsConfig::Output(&mut diagnostics),
+        print: PrintConfig::default(),
     };
     let mut assembler = Assembler::new(&mut config);
     let objects: Vec<_> = args
@@ -25,8 +27,13 @@ fn main() {
                 .unwrap_or_else(|| std::process::exit(1))
         })
         .collect();
-    let mut linker = Linker::new(&mut config);
-    let program = linker.link(objects).unwrap();
+    let mut linker = Linker::new(&mut config).gc_sections(gc_sections);
+    let program = linker
+        .link(objects)
+        .unwrap_or_else(|| std::process::exit(1));
+    for name in program.discarded.iter() {
+        eprintln!("discarded section `{}`", name)
+    }
     let mut rom_file = File::create(args[0].to_owned() + ".o").unwrap();
     rom_file.write_all(&program.into_rom()).unwrap()
 }
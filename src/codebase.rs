@@ -62,7 +62,7 @@ impl StringSrcBuf {
         }
     }
 
-    pub fn lines(&self, line_range: impl ops::RangeBounds<LineIndex>) -> TextLines {
+    pub fn lines(&self, line_range: impl ops::RangeBounds<LineIndex>) -> TextLines<'_> {
         use std::ops::Bound::*;
         let start = match line_range.start_bound() {
             Included(&n) => n,
@@ -1,5 +1,7 @@
+use super::Tag;
+
 use crate::codebase::{CodebaseError, TextCache};
-use crate::object::Width;
+use crate::object::{SectionKind, Width};
 use crate::span::StrippedBufSpan;
 use crate::IncDec;
 
@@ -8,7 +10,23 @@ use std::fmt;
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) enum Message<S> {
     AfOutsideStackOperation,
+    AmbiguousSize,
     AlwaysUnconditional,
+    AddrOutsideRegion {
+        addr: i32,
+        kind: SectionKind,
+    },
+    ArgCount {
+        name: S,
+        actual: usize,
+        expected: usize,
+    },
+    ArithmeticOverflow,
+    AssertionFailed {
+        message: Option<Box<str>>,
+    },
+    AssertionNotConst,
+    BankRequiresBankedKind,
     CannotBeUsedAsTarget,
     CannotCoerceBuiltinNameIntoNum {
         name: S,
@@ -17,23 +35,53 @@ pub(crate) enum Message<S> {
         category: KeywordOperandCategory,
         operand: S,
     },
+    CannotInterpolate {
+        name: Box<str>,
+    },
+    CannotRedefineBuiltin {
+        name: S,
+    },
     CannotSpecifyTarget,
     CodebaseError {
         error: CodebaseError,
     },
     ConditionOutsideBranch,
+    DataInRamSection {
+        kind: SectionKind,
+    },
     DestCannotBeConst,
+    DuplicateSectionArg {
+        arg: S,
+    },
+    DuplicateSymbol {
+        name: S,
+    },
     DestMustBeA,
     DestMustBeHl,
+    DivisionByZero,
     ExpectedFound {
         expected: ValueKind,
         found: ValueKind,
     },
     ExpectedString,
+    ExpectedSymbolName,
     IncompatibleOperand,
+    InvalidCharLiteral,
+    InvalidFormatSpec {
+        spec: Box<str>,
+    },
+    InvalidLoadTarget {
+        arg: S,
+    },
+    InvalidSectionArg {
+        arg: S,
+    },
     CalledHere {
         name: S,
     },
+    JumpOutOfRange {
+        excess: i32,
+    },
     KeywordInExpr {
         keyword: S,
     },
@@ -48,7 +96,12 @@ pub(crate) enum Message<S> {
         src: S,
         dest: S,
     },
+    LiteralOutOfRange,
     MacroRequiresName,
+    MisalignedAddr {
+        addr: i32,
+        align: u8,
+    },
     MissingTarget,
     MustBeBit {
         mnemonic: S,
@@ -57,6 +110,11 @@ pub(crate) enum Message<S> {
     MustBeDeref {
         operand: S,
     },
+    NestedLoad,
+    NoDigits,
+    NotInUnion {
+        directive: S,
+    },
     NotAMnemonic {
         name: S,
     },
@@ -68,21 +126,51 @@ pub(crate) enum Message<S> {
         actual: usize,
         expected: usize,
     },
+    OutOfRange {
+        value: i32,
+        min: i32,
+        max: i32,
+    },
+    OverlappingSections {
+        addr: i32,
+    },
+    RecursiveDefinition {
+        name: S,
+    },
     RequiresConstantTarget {
         mnemonic: S,
     },
     RequiresRegPair,
     RequiresSimpleOperand,
+    SectionDoesNotFit {
+        kind: SectionKind,
+    },
     SrcMustBeSp,
     StringInInstruction,
     UnexpectedEof,
     UnexpectedToken {
         token: S,
     },
+    UnionOutsideRam,
+    UnmatchedEndl,
+    UnmatchedLabelRef {
+        reference: S,
+    },
+    UnmatchedLoad,
     UnmatchedParenthesis,
+    UnmatchedPops,
+    UnmatchedPushs,
+    UnmatchedUnion,
     UnresolvedSymbol {
         symbol: S,
     },
+    UnterminatedString,
+    UserError {
+        message: Box<str>,
+    },
+    UserWarning {
+        message: Box<str>,
+    },
     ValueOutOfRange {
         value: i32,
         width: Width,
@@ -101,17 +189,58 @@ pub enum ValueKind {
     Builtin,
     Num,
     Section,
+    String,
     Symbol,
 }
 
+impl<S> Message<S> {
+    pub fn tag(&self) -> Tag {
+        match self {
+            Message::UserWarning { .. } => Tag::Warning,
+            _ => Tag::Error,
+        }
+    }
+}
+
 impl Message<StrippedBufSpan> {
-    pub fn render<'a>(&self, codebase: &'a TextCache) -> String {
+    pub fn render(&self, codebase: &TextCache) -> String {
         use self::Message::*;
         match self {
             AfOutsideStackOperation => {
                 "register pair `af` can only be used with `push` and `pop`".into()
             }
+            AmbiguousSize => "size of item is ambiguous and cannot be resolved".into(),
+            AddrOutsideRegion { addr, kind } => {
+                let range = kind.addr_range();
+                format!(
+                    "address ${:04x} is outside `{}` (${:04x}-${:04x})",
+                    addr,
+                    kind,
+                    range.start(),
+                    range.end()
+                )
+            }
             AlwaysUnconditional => "instruction cannot be made conditional".into(),
+            ArgCount {
+                name,
+                actual,
+                expected,
+            } => format!(
+                "`{}` expects {} argument{}, found {}",
+                codebase.snippet(name),
+                expected,
+                pluralize(*expected),
+                actual
+            ),
+            ArithmeticOverflow => "result of operation does not fit in 32 bits".into(),
+            AssertionFailed { message } => match message {
+                Some(message) => format!("assertion failed: {}", message),
+                None => "assertion failed".into(),
+            },
+            AssertionNotConst => "assertion condition is not a constant".into(),
+            BankRequiresBankedKind => {
+                "`BANK` requires section type `ROMX`, `VRAM`, `SRAM` or `WRAMX`".into()
+            }
             CalledHere { name } => format!("in macro `{}`, called here", codebase.snippet(name)),
             CannotBeUsedAsTarget => {
                 "operand cannot be used as target for branching instructions".into()
@@ -125,17 +254,50 @@ impl Message<StrippedBufSpan> {
                 category,
                 codebase.snippet(operand),
             ),
+            CannotInterpolate { name } => {
+                format!("`{}` is not a constant or string symbol", name)
+            }
+            CannotRedefineBuiltin { name } => {
+                format!("cannot redefine builtin `{}`", codebase.snippet(name))
+            }
             CannotSpecifyTarget => "branch target cannot be specified explicitly".into(),
             CodebaseError { error } => error.to_string(),
             ConditionOutsideBranch => {
                 "condition codes can only be used as operands for branching instructions".into()
             }
+            DataInRamSection { kind } => format!("`{}` sections cannot contain data", kind),
             DestCannotBeConst => "destination operand cannot be a constant".into(),
             DestMustBeA => "destination of ALU operation must be `a`".into(),
             DestMustBeHl => "destination operand must be `hl`".into(),
+            DivisionByZero => "division by zero".into(),
+            DuplicateSectionArg { arg } => format!(
+                "section argument `{}` conflicts with an earlier one",
+                codebase.snippet(arg)
+            ),
+            DuplicateSymbol { name } => {
+                format!(
+                    "symbol `{}` is defined more than once",
+                    codebase.snippet(name)
+                )
+            }
             ExpectedFound { expected, found } => format!("expected {}, found {}", expected, found),
             ExpectedString => "expected string argument".into(),
+            ExpectedSymbolName => "expected symbol name".into(),
             IncompatibleOperand => "operand cannot be used with this instruction".into(),
+            InvalidLoadTarget { arg } => format!(
+                "`LOAD` target `{}` is not a RAM region",
+                codebase.snippet(arg)
+            ),
+            InvalidCharLiteral => "character literal must contain exactly one character".into(),
+            InvalidFormatSpec { spec } => format!("invalid format specifier `{}`", spec),
+            InvalidSectionArg { arg } => {
+                format!("invalid section argument `{}`", codebase.snippet(arg))
+            }
+            JumpOutOfRange { excess } => format!(
+                "jump target out of range by {} byte{}, consider using `JP`",
+                excess,
+                pluralize(*excess as usize)
+            ),
             KeywordInExpr { keyword } => format!(
                 "keyword `{}` cannot appear in expression",
                 codebase.snippet(keyword),
@@ -170,7 +332,13 @@ impl Message<StrippedBufSpan> {
                     codebase.snippet(dest),
                 )
             }
+            LiteralOutOfRange => "literal does not fit in 32 bits".into(),
             MacroRequiresName => "macro definition must be preceded by label".into(),
+            MisalignedAddr { addr, align } => format!(
+                "address ${:04x} is not aligned to {} bytes",
+                addr,
+                1u32 << align
+            ),
             MissingTarget => "branch instruction requires target".into(),
             MustBeBit { mnemonic } => format!(
                 "first operand of `{}` must be bit number",
@@ -181,6 +349,11 @@ impl Message<StrippedBufSpan> {
                 "operand `{}` must be dereferenced",
                 codebase.snippet(operand),
             ),
+            NestedLoad => "`LOAD` blocks cannot be nested".into(),
+            NoDigits => "numeric literal has no digits".into(),
+            NotInUnion { directive } => {
+                format!("`{}` outside of `UNION` block", codebase.snippet(directive))
+            }
             NotAMnemonic { name } => format!("`{}` is not a mnemonic", codebase.snippet(name)),
             #[cfg(test)]
             OnlyIdentsCanBeCalled => "only identifiers can be called".into(),
@@ -198,23 +371,45 @@ impl Message<StrippedBufSpan> {
                 pluralize(*expected),
                 actual
             ),
+            OutOfRange { value, min, max } => {
+                format!("value {} must be between {} and {}", value, min, max)
+            }
+            OverlappingSections { addr } => {
+                format!("section overlaps another section at ${:04x}", addr)
+            }
+            RecursiveDefinition { name } => {
+                format!("`{}` is defined in terms of itself", codebase.snippet(name))
+            }
             RequiresConstantTarget { mnemonic } => format!(
                 "instruction `{}` requires a constant target",
                 codebase.snippet(mnemonic),
             ),
             RequiresRegPair => "instruction requires a register pair".into(),
             RequiresSimpleOperand => "instruction requires 8-bit register or `(hl)`".into(),
+            SectionDoesNotFit { kind } => format!("no room left for section in `{}`", kind),
             SrcMustBeSp => "source operand must be `sp`".into(),
             StringInInstruction => "strings cannot appear in instruction operands".into(),
             UnexpectedEof => "unexpected end of file".into(),
             UnexpectedToken { token } => {
                 format!("encountered unexpected token `{}`", codebase.snippet(token))
             }
+            UnionOutsideRam => "`UNION` is only allowed in RAM sections".into(),
+            UnmatchedEndl => "`ENDL` without matching `LOAD`".into(),
+            UnmatchedLabelRef { reference } => format!(
+                "no label matches reference `{}`",
+                codebase.snippet(reference)
+            ),
+            UnmatchedLoad => "`LOAD` without matching `ENDL`".into(),
             UnmatchedParenthesis => "unmatched parenthesis".into(),
+            UnmatchedPops => "`POPS` without matching `PUSHS`".into(),
+            UnmatchedPushs => "`PUSHS` without matching `POPS`".into(),
+            UnmatchedUnion => "`UNION` without matching `ENDU`".into(),
             UnresolvedSymbol { symbol } => format!(
                 "symbol `{}` could not be resolved",
                 codebase.snippet(symbol)
             ),
+            UnterminatedString => "unterminated string literal".into(),
+            UserError { message } | UserWarning { message } => message.to_string(),
             ValueOutOfRange { value, width } => {
                 format!("value {} cannot be represented in a {}", value, width)
             }
@@ -238,6 +433,7 @@ impl fmt::Display for ValueKind {
             ValueKind::Builtin => "built-in name",
             ValueKind::Num => "numeric value",
             ValueKind::Section => "section name",
+            ValueKind::String => "string",
             ValueKind::Symbol => "symbol",
         })
     }
@@ -251,6 +447,21 @@ fn pluralize(n: usize) -> &'static str {
     }
 }
 
+impl fmt::Display for SectionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            SectionKind::Hram => "HRAM",
+            SectionKind::Oam => "OAM",
+            SectionKind::Rom0 => "ROM0",
+            SectionKind::Romx => "ROMX",
+            SectionKind::Sram => "SRAM",
+            SectionKind::Vram => "VRAM",
+            SectionKind::Wram0 => "WRAM0",
+            SectionKind::Wramx => "WRAMX",
+        })
+    }
+}
+
 impl fmt::Display for Width {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str(match self {
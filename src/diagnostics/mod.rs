@@ -50,6 +50,7 @@ pub struct Clause {
 pub enum Tag {
     Error,
     Note,
+    Warning,
 }
 
 /// A code excerpt with an optional highlight.
@@ -156,6 +157,7 @@ impl fmt::Display for Tag {
         f.write_str(match self {
             Tag::Error => "error",
             Tag::Note => "note",
+            Tag::Warning => "warning",
         })
     }
 }
@@ -165,9 +167,9 @@ fn mk_squiggle(range: &ColumnRange) -> String {
     let space_count = range.start.0 - 1;
     let caret_count = max(range.end.0 - range.start.0, 1);
 
-    use std::iter::{once, repeat};
-    let spaces = repeat(' ').take(space_count as usize);
-    let carets = repeat('^').take(caret_count as usize);
+    use std::iter::once;
+    let spaces = std::iter::repeat_n(' ', space_count as usize);
+    let carets = std::iter::repeat_n('^', caret_count as usize);
     once('\n').chain(spaces).chain(carets).collect()
 }
 
@@ -347,7 +349,7 @@ impl CompactDiag<Span, StrippedBufSpan> {
         let StrippedBufSpan { buf_id, range } = registry.strip_span(&self.main.highlight);
         let main_clause = ExpandedDiagnosticClause {
             buf_id,
-            tag: Tag::Error,
+            tag: self.main.message.tag(),
             message: self.main.message,
             location: Some(range),
         };
@@ -390,7 +392,7 @@ impl ExpandedDiagnosticClause {
     fn render(&self, codebase: &TextCache) -> Clause {
         let buf = codebase.buf(self.buf_id);
         let excerpt = self.location.as_ref().map(|range| {
-            let range = buf.text_range(&range);
+            let range = buf.text_range(range);
             let highlight = {
                 assert_eq!(range.start.line, range.end.line);
                 ColumnRange {
@@ -1,10 +1,12 @@
-use crate::diagnostics::{BackendDiagnostics, Message, ValueKind};
-use crate::expr::{Atom, BinOp, ExprOp, ParamId};
+use crate::diagnostics::{BackendDiagnostics, IgnoreDiagnostics, Message, ValueKind};
+use crate::expr::{Atom, BinOp, ExprOp, ParamId, UnaryOp};
+use crate::fixed;
 use crate::object::var::Var;
 use crate::object::*;
-use crate::span::{Spanned, WithSpan};
+use crate::span::{Source, Spanned, WithSpan};
 
 use std::borrow::Borrow;
+use std::rc::Rc;
 
 impl<S: Clone> Expr<S> {
     pub(crate) fn to_num<C, V, D, I>(
@@ -12,6 +14,20 @@ impl<S: Clone> Expr<S> {
         context: &LinkageContext<C, V>,
         diagnostics: &mut D,
     ) -> Var
+    where
+        C: Borrow<Content<I, S>>,
+        V: Borrow<VarTable>,
+        D: BackendDiagnostics<S>,
+    {
+        let value = self.to_const(context, diagnostics);
+        expect_num(value, &self.span(), diagnostics)
+    }
+
+    pub(crate) fn to_const<C, V, D, I>(
+        &self,
+        context: &LinkageContext<C, V>,
+        diagnostics: &mut D,
+    ) -> Const
     where
         C: Borrow<Content<I, S>>,
         V: Borrow<VarTable>,
@@ -21,13 +37,60 @@ impl<S: Clone> Expr<S> {
             &EvalContext {
                 linkage: context,
                 args: &[],
+                call_span: None,
                 location_var: None,
+                frame: None,
             },
             diagnostics,
         )
     }
 }
 
+impl<S: Clone> Closure<S> {
+    pub(crate) fn to_num<C, V, D, I>(
+        &self,
+        context: &LinkageContext<C, V>,
+        diagnostics: &mut D,
+    ) -> Var
+    where
+        C: Borrow<Content<I, S>>,
+        V: Borrow<VarTable>,
+        D: BackendDiagnostics<S>,
+    {
+        let value = self.expr.eval_subst(
+            &EvalContext {
+                linkage: context,
+                args: &[],
+                call_span: None,
+                location_var: Some(self.location),
+                frame: None,
+            },
+            diagnostics,
+        );
+        expect_num(value, &self.expr.span(), diagnostics)
+    }
+}
+
+fn expect_num<S: Clone, D: BackendDiagnostics<S>>(
+    value: Const,
+    span: &S,
+    diagnostics: &mut D,
+) -> Var {
+    match value {
+        Const::Num(value) => value,
+        Const::String(_) => {
+            diagnostics.emit_diag(
+                Message::ExpectedFound {
+                    expected: ValueKind::Num,
+                    found: ValueKind::String,
+                }
+                .at(span.clone()),
+            );
+            Var::Unknown
+        }
+    }
+}
+
 trait EvalSubst<'a, S: Clone> {
     type Output;
 
@@ -41,7 +104,20 @@ trait EvalSubst<'a, S: Clone> {
 struct EvalContext<'a, C, V, S: Clone> {
     linkage: &'a LinkageContext<C, V>,
     args: Args<'a, S>,
+    call_span: Option<&'a S>,
     location_var: Option<VarId>,
+    frame: Option<&'a Frame<'a, S>>,
+}
+
+struct Frame<'a, S> {
+    closure: &'a Closure<S>,
+    caller: Option<&'a Frame<'a, S>>,
+}
+
+impl<'a, C, V, S: Clone> EvalContext<'a, C, V, S> {
+    fn callers(&self) -> impl Iterator<Item = &'a Closure<S>> {
+        std::iter::successors(self.frame, |frame| frame.caller).map(|frame| frame.closure)
+    }
 }
 
 impl<'a, C, V: Borrow<VarTable>, S: Clone> EvalContext<'a, C, V, S> {
@@ -57,6 +133,22 @@ impl<'a, C, V: Borrow<VarTable>, S: Clone> EvalContext<'a, C, V, S> {
 enum Value<'a, S: Clone> {
     Symbol(Option<DefRef<'a, S>>),
     Num(Var),
+    String(Rc<str>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Const {
+    Num(Var),
+    String(Rc<str>),
+}
+
+impl<'a, S: Clone> From<Const> for Value<'a, S> {
+    fn from(value: Const) -> Self {
+        match value {
+            Const::Num(value) => Value::Num(value),
+            Const::String(string) => Value::String(string),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -67,7 +159,7 @@ enum DefRef<'a, S> {
 }
 
 impl<'a, S: Clone> EvalSubst<'a, S> for &'a Expr<S> {
-    type Output = Var;
+    type Output = Const;
 
     fn eval_subst<C: Borrow<Content<I, S>>, V: Borrow<VarTable>, D: BackendDiagnostics<S>, I>(
         self,
@@ -75,36 +167,65 @@ impl<'a, S: Clone> EvalSubst<'a, S> for &'a Expr<S> {
         diagnostics: &mut D,
     ) -> Self::Output {
         let mut stack = Vec::<Spanned<Value<_>, _>>::new();
-        for Spanned { item, span } in &self.0 {
+        for (i, Spanned { item, span }) in self.0.iter().enumerate() {
             let value = match item {
+                ExprOp::Atom(Atom::Name(name)) if is_def_arg(&stack, self.0.get(i + 1)) => {
+                    Value::Symbol(
+                        (*name)
+                            .with_span(span)
+                            .resolve(context.linkage, &mut IgnoreDiagnostics),
+                    )
+                }
                 ExprOp::Atom(atom) => atom.with_span(span).eval_subst(context, diagnostics),
                 ExprOp::Binary(operator) => {
                     let rhs = stack.pop().unwrap();
                     let lhs = stack.pop().unwrap().eval_subst(context, diagnostics);
                     let rhs = rhs.eval_subst(context, diagnostics);
-                    Value::Num(operator.apply(&lhs, &rhs))
+                    Value::Num(operator.apply_checked(&lhs, &rhs, span, diagnostics))
+                }
+                ExprOp::Unary(operator) => {
+                    let operand = stack.pop().unwrap().eval_subst(context, diagnostics);
+                    Value::Num(operator.apply_checked(&operand, span, diagnostics))
                 }
                 ExprOp::FnCall(n) => {
                     let arg_index = stack.len() - n;
                     let name = stack[arg_index - 1].clone();
-                    let value = Value::Num(name.eval_subst(
-                        &EvalContext {
-                            linkage: context.linkage,
-                            args: &stack[arg_index..],
-                            location_var: context.location_var,
-                        },
-                        diagnostics,
-                    ));
+                    let context = EvalContext {
+                        call_span: Some(span),
+                        ..*context
+                    };
+                    let value = name.call(&stack[arg_index..], &context, diagnostics);
                     stack.truncate(arg_index - 1);
-                    value
+                    value.into()
                 }
             };
             stack.push(value.with_span(span))
         }
-        stack.pop().unwrap().eval_subst(context, diagnostics)
+        stack.pop().unwrap().call(&[], context, diagnostics)
     }
 }
 
+fn is_def_arg<S: Clone, T>(
+    stack: &[Spanned<Value<S>, T>],
+    next: Option<&Spanned<ExprOp<Name>, S>>,
+) -> bool {
+    let is_def = matches!(
+        stack.last(),
+        Some(Spanned {
+            item: Value::Symbol(Some(DefRef::Builtin(BuiltinId::Def))),
+            ..
+        })
+    );
+    is_def
+        && matches!(
+            next,
+            Some(Spanned {
+                item: ExprOp::FnCall(1),
+                ..
+            })
+        )
+}
+
 type Args<'a, S> = &'a [Spanned<Value<'a, S>, &'a S>];
 
 impl<'a, S: Clone> EvalSubst<'a, S> for Spanned<Value<'a, S>, &S> {
@@ -115,16 +236,37 @@ impl<'a, S: Clone> EvalSubst<'a, S> for Spanned<Value<'a, S>, &S> {
         context: &'a EvalContext<'a, C, V, S>,
         diagnostics: &mut D,
     ) -> Self::Output {
+        let span = self.span;
+        let value = self.call(&[], context, diagnostics);
+        expect_num(value, span, diagnostics)
+    }
+}
+
+impl<'a, S: Clone> Spanned<Value<'a, S>, &'a S> {
+    fn call<C, V, D, I>(
+        self,
+        args: Args<'a, S>,
+        context: &'a EvalContext<'a, C, V, S>,
+        diagnostics: &mut D,
+    ) -> Const
+    where
+        C: Borrow<Content<I, S>>,
+        V: Borrow<VarTable>,
+        D: BackendDiagnostics<S>,
+    {
         match self.item {
-            Value::Symbol(Some(name)) => name.with_span(self.span).eval_subst(context, diagnostics),
-            Value::Symbol(None) => Var::Unknown,
-            Value::Num(value) => value,
+            Value::Symbol(Some(name)) => name
+                .with_span(self.span)
+                .eval_subst(&EvalContext { args, ..*context }, diagnostics),
+            Value::Symbol(None) => Const::Num(Var::Unknown),
+            Value::Num(value) => Const::Num(value),
+            Value::String(string) => Const::String(string),
         }
     }
 }
 
 impl<'a, S: Clone> EvalSubst<'a, S> for Spanned<DefRef<'a, S>, &S> {
-    type Output = Var;
+    type Output = Const;
 
     fn eval_subst<C: Borrow<Content<I, S>>, V: Borrow<VarTable>, D: BackendDiagnostics<S>, I>(
         self,
@@ -132,26 +274,80 @@ impl<'a, S: Clone> EvalSubst<'a, S> for Spanned<DefRef<'a, S>, &S> {
         diagnostics: &mut D,
     ) -> Self::Output {
         match self.item {
-            DefRef::Builtin(BuiltinId::Sizeof) => context
-                .args
-                .get(0)
-                .map(|value| value.sizeof(context.linkage, diagnostics))
-                .unwrap_or_else(|| {
+            DefRef::Builtin(builtin) => {
+                let args = context.args;
+                if args.is_empty() {
                     let name = diagnostics.strip_span(self.span);
                     diagnostics.emit_diag(
                         Message::CannotCoerceBuiltinNameIntoNum { name }.at(self.span.clone()),
                     );
-                    Var::Unknown
-                }),
-            DefRef::Closure(closure) => closure.expr.eval_subst(
-                &EvalContext {
-                    location_var: Some(closure.location),
-                    ..*context
-                },
-                diagnostics,
-            ),
-            DefRef::Section(section) => context.linkage.vars.borrow()[section.addr].clone(),
+                    return Const::Num(Var::Unknown);
+                }
+                if !self.check_arg_count(args.len(), builtin.arity(), diagnostics) {
+                    return Const::Num(Var::Unknown);
+                }
+                builtin.call(context.call_span.unwrap_or(self.span), context, diagnostics)
+            }
+            DefRef::Closure(closure) => {
+                if !self.check_arg_count(context.args.len(), closure.params, diagnostics) {
+                    return Const::Num(Var::Unknown);
+                }
+                if context
+                    .callers()
+                    .any(|caller| std::ptr::eq(caller, closure))
+                {
+                    let name = diagnostics.strip_span(self.span);
+                    diagnostics
+                        .emit_diag(Message::RecursiveDefinition { name }.at(self.span.clone()));
+                    return Const::Num(Var::Unknown);
+                }
+                let vars = context.linkage.vars.borrow();
+                if let Some(value) = vars.closure_value(closure.location) {
+                    return value;
+                }
+                let frame = Frame {
+                    closure,
+                    caller: context.frame,
+                };
+                let value = closure.expr.eval_subst(
+                    &EvalContext {
+                        location_var: Some(closure.location),
+                        frame: Some(&frame),
+                        ..*context
+                    },
+                    diagnostics,
+                );
+                if closure.params == 0 {
+                    vars.memoize_closure_value(closure.location, &value)
+                }
+                value
+            }
+            DefRef::Section(section) => {
+                Const::Num(context.linkage.vars.borrow()[section.addr].clone())
+            }
+        }
+    }
+}
+
+impl<'a, S: Clone> Spanned<DefRef<'a, S>, &S> {
+    fn check_arg_count<D: BackendDiagnostics<S>>(
+        &self,
+        actual: usize,
+        expected: usize,
+        diagnostics: &mut D,
+    ) -> bool {
+        if actual != expected {
+            let name = diagnostics.strip_span(self.span);
+            diagnostics.emit_diag(
+                Message::ArgCount {
+                    name,
+                    actual,
+                    expected,
+                }
+                .at(self.span.clone()),
+            )
         }
+        actual == expected
     }
 }
 
@@ -175,6 +371,7 @@ impl<'a, S: Clone + 'a> EvalSubst<'a, S> for Spanned<&Atom<Name>, &S> {
                 .with_span(self.span)
                 .to_value(context.linkage, diagnostics),
             Atom::Param(ParamId(id)) => context.args[*id].item.clone(),
+            Atom::String(string) => Value::String(Rc::clone(string)),
         }
     }
 }
@@ -194,7 +391,7 @@ impl<S: Clone> Spanned<Name, &S> {
         diagnostics: &mut D,
     ) -> Option<DefRef<'a, S>> {
         match self.item {
-            Name::Builtin(BuiltinId::Sizeof) => Some(DefRef::Builtin(BuiltinId::Sizeof)),
+            Name::Builtin(builtin) => Some(DefRef::Builtin(builtin)),
             Name::Symbol(id) => id.with_span(self.span).resolve(context, diagnostics),
         }
     }
@@ -208,12 +405,14 @@ impl<S: Clone> Spanned<SymbolId, &S> {
     ) -> Option<DefRef<'a, S>> {
         let SymbolId(id) = self.item;
         match &context.content.borrow().symbols[id] {
-            Symbol::Exported { def, .. } | Symbol::Local { def } => Some(match &def.meaning {
-                SymbolMeaning::Closure(closure) => DefRef::Closure(closure),
-                SymbolMeaning::Section(SectionId(id)) => {
-                    DefRef::Section(&context.content.borrow().sections[*id])
-                }
-            }),
+            Symbol::Exported { def, .. } | Symbol::Weak { def, .. } | Symbol::Local { def } => {
+                Some(match &def.meaning {
+                    SymbolMeaning::Closure(closure) => DefRef::Closure(closure),
+                    SymbolMeaning::Section(SectionId(id)) => {
+                        DefRef::Section(&context.content.borrow().sections[*id])
+                    }
+                })
+            }
             Symbol::Unknown { .. } => {
                 let symbol = diagnostics.strip_span(self.span);
                 diagnostics.emit_diag(Message::UnresolvedSymbol { symbol }.at(self.span.clone()));
@@ -224,32 +423,364 @@ impl<S: Clone> Spanned<SymbolId, &S> {
 }
 
 impl BinOp {
+    fn apply_checked<S: Clone, D: BackendDiagnostics<S>>(
+        self,
+        lhs: &Var,
+        rhs: &Var,
+        span: &S,
+        diagnostics: &mut D,
+    ) -> Var {
+        let result = self.apply(lhs, rhs);
+        if result == Var::Unknown && lhs.exact().is_some() {
+            let message = match (self, rhs.exact()) {
+                (BinOp::Division, Some(0)) | (BinOp::Modulo, Some(0)) => {
+                    Some(Message::DivisionByZero)
+                }
+                (BinOp::ShiftLeft, Some(shift)) | (BinOp::ShiftRight, Some(shift))
+                    if !(0..32).contains(&shift) =>
+                {
+                    Some(Message::OutOfRange {
+                        value: shift,
+                        min: 0,
+                        max: 31,
+                    })
+                }
+                (BinOp::Division, Some(_))
+                | (BinOp::Minus, Some(_))
+                | (BinOp::Modulo, Some(_))
+                | (BinOp::Multiplication, Some(_))
+                | (BinOp::Plus, Some(_)) => Some(Message::ArithmeticOverflow),
+                (BinOp::ShiftLeft, Some(_)) => Some(Message::ArithmeticOverflow),
+                _ => None,
+            };
+            if let Some(message) = message {
+                diagnostics.emit_diag(message.at(span.clone()))
+            }
+        }
+        result
+    }
+
     fn apply(self, lhs: &Var, rhs: &Var) -> Var {
         match self {
+            BinOp::BitAnd => lhs & rhs,
             BinOp::BitOr => lhs | rhs,
+            BinOp::BitXor => lhs ^ rhs,
             BinOp::Division => lhs / rhs,
-            BinOp::Equality => match (lhs.exact(), rhs.exact()) {
-                (Some(lhs), Some(rhs)) => (if lhs == rhs { 1 } else { 0 }).into(),
-                _ => Var::Unknown,
-            },
+            BinOp::Equality => lhs.is_equal_to(rhs),
+            BinOp::Greater => rhs.is_less_than(lhs),
+            BinOp::GreaterOrEqual => lhs.is_less_than(rhs).logical_not(),
+            BinOp::Inequality => lhs.is_equal_to(rhs).logical_not(),
+            BinOp::Less => lhs.is_less_than(rhs),
+            BinOp::LessOrEqual => rhs.is_less_than(lhs).logical_not(),
+            BinOp::LogicalAnd => lhs.logical_and(rhs),
+            BinOp::LogicalOr => lhs
+                .logical_not()
+                .logical_and(&rhs.logical_not())
+                .logical_not(),
             BinOp::Minus => lhs - rhs,
+            BinOp::Modulo => lhs % rhs,
             BinOp::Multiplication => lhs * rhs,
             BinOp::Plus => lhs + rhs,
+            BinOp::ShiftLeft => lhs << rhs,
+            BinOp::ShiftRight => lhs >> rhs,
+        }
+    }
+}
+
+impl UnaryOp {
+    fn apply_checked<S: Clone, D: BackendDiagnostics<S>>(
+        self,
+        operand: &Var,
+        span: &S,
+        diagnostics: &mut D,
+    ) -> Var {
+        let result = self.apply(operand);
+        if result == Var::Unknown && operand.exact().is_some() {
+            diagnostics.emit_diag(Message::ArithmeticOverflow.at(span.clone()))
+        }
+        result
+    }
+
+    fn apply(self, operand: &Var) -> Var {
+        match self {
+            UnaryOp::BitNot => !operand,
+            UnaryOp::Negation => -operand,
+            UnaryOp::Not => operand.logical_not(),
+        }
+    }
+}
+
+impl BuiltinId {
+    fn arity(self) -> usize {
+        match self {
+            BuiltinId::Atan2
+            | BuiltinId::Div
+            | BuiltinId::Max
+            | BuiltinId::Min
+            | BuiltinId::Mul
+            | BuiltinId::Strcat
+            | BuiltinId::Strcmp
+            | BuiltinId::Strin => 2,
+            BuiltinId::Strsub => 3,
+            _ => 1,
+        }
+    }
+
+    fn call<'a, C, V, D, I, S>(
+        self,
+        span: &S,
+        context: &'a EvalContext<'a, C, V, S>,
+        diagnostics: &mut D,
+    ) -> Const
+    where
+        C: Borrow<Content<I, S>>,
+        V: Borrow<VarTable>,
+        D: BackendDiagnostics<S>,
+        S: Clone,
+    {
+        let args = context.args;
+        let vars = context.linkage.vars.borrow();
+        let mut str_arg = |index: usize| args[index].to_str_arg(context, diagnostics);
+        match self {
+            BuiltinId::Strcat => {
+                return match (str_arg(0), str_arg(1)) {
+                    (Some(lhs), Some(rhs)) => Const::String(format!("{}{}", lhs, rhs).into()),
+                    _ => Const::Num(Var::Unknown),
+                }
+            }
+            BuiltinId::Strcmp => {
+                return Const::Num(match (str_arg(0), str_arg(1)) {
+                    (Some(lhs), Some(rhs)) => (lhs.cmp(&rhs) as i32).into(),
+                    _ => Var::Unknown,
+                })
+            }
+            BuiltinId::Strin => {
+                return Const::Num(match (str_arg(0), str_arg(1)) {
+                    (Some(string), Some(pattern)) => string
+                        .find(&*pattern)
+                        .map_or(0, |index| string[..index].chars().count() as i32 + 1)
+                        .into(),
+                    _ => Var::Unknown,
+                })
+            }
+            BuiltinId::Strlen => {
+                return Const::Num(str_arg(0).map_or(Var::Unknown, |string| {
+                    (string.chars().count() as i32).into()
+                }))
+            }
+            BuiltinId::Strlwr => {
+                return str_arg(0).map_or(Const::Num(Var::Unknown), |string| {
+                    Const::String(string.to_lowercase().into())
+                })
+            }
+            BuiltinId::Strsub => {
+                let string = str_arg(0);
+                let position = args[1].to_num_arg(context, diagnostics).exact();
+                let len = args[2].to_num_arg(context, diagnostics).exact();
+                return match (string, position, len) {
+                    (Some(string), Some(position), Some(len)) => Const::String(
+                        string
+                            .chars()
+                            .skip((position.max(1) - 1) as usize)
+                            .take(len.max(0) as usize)
+                            .collect::<String>()
+                            .into(),
+                    ),
+                    _ => Const::Num(Var::Unknown),
+                };
+            }
+            BuiltinId::Strupr => {
+                return str_arg(0).map_or(Const::Num(Var::Unknown), |string| {
+                    Const::String(string.to_uppercase().into())
+                })
+            }
+            _ => (),
         }
+        let mut num_arg = |index: usize| args[index].to_num_arg(context, diagnostics);
+        Const::Num(match self {
+            BuiltinId::Abs => {
+                let operand = num_arg(0);
+                match operand.exact() {
+                    Some(_) => apply_exact(i32::checked_abs, &operand, span, diagnostics),
+                    None => operand.abs(),
+                }
+            }
+            BuiltinId::Atan2 => {
+                let (y, x) = (num_arg(0), num_arg(1));
+                apply_exact2(fixed::atan2, &y, &x, span, diagnostics)
+            }
+            BuiltinId::Ceil => {
+                let operand = num_arg(0);
+                apply_exact(fixed::ceil, &operand, span, diagnostics)
+            }
+            BuiltinId::Cos => {
+                let operand = num_arg(0);
+                apply_exact(fixed::cos, &operand, span, diagnostics)
+            }
+            BuiltinId::Def => match args[0].item {
+                Value::Symbol(Some(_)) => 1.into(),
+                Value::Symbol(None) => 0.into(),
+                Value::Num(_) | Value::String(_) => {
+                    diagnostics.emit_diag(
+                        Message::ExpectedFound {
+                            expected: ValueKind::Symbol,
+                            found: args[0].item.kind().unwrap(),
+                        }
+                        .at(args[0].span.clone()),
+                    );
+                    Var::Unknown
+                }
+            },
+            BuiltinId::Div => {
+                let (lhs, rhs) = (num_arg(0), num_arg(1));
+                if lhs.exact().is_some() && rhs.exact() == Some(0) {
+                    diagnostics.emit_diag(Message::DivisionByZero.at(span.clone()));
+                    Var::Unknown
+                } else {
+                    apply_exact2(fixed::div, &lhs, &rhs, span, diagnostics)
+                }
+            }
+            BuiltinId::Endof => args[0]
+                .to_section(diagnostics)
+                .map_or(Var::Unknown, |section| {
+                    &vars[section.addr] + &vars[section.size]
+                }),
+            BuiltinId::Floor => {
+                let operand = num_arg(0);
+                apply_exact(fixed::floor, &operand, span, diagnostics)
+            }
+            BuiltinId::High => {
+                let value = num_arg(0);
+                &(&value >> &8.into()) & &0xff.into()
+            }
+            BuiltinId::Low => &num_arg(0) & &0xff.into(),
+            BuiltinId::Max => num_arg(0).max(&num_arg(1)),
+            BuiltinId::Min => num_arg(0).min(&num_arg(1)),
+            BuiltinId::Mul => {
+                let (lhs, rhs) = (num_arg(0), num_arg(1));
+                apply_exact2(fixed::mul, &lhs, &rhs, span, diagnostics)
+            }
+            BuiltinId::Round => {
+                let operand = num_arg(0);
+                apply_exact(fixed::round, &operand, span, diagnostics)
+            }
+            BuiltinId::Sin => {
+                let operand = num_arg(0);
+                apply_exact(fixed::sin, &operand, span, diagnostics)
+            }
+            BuiltinId::Sizeof => args[0]
+                .to_section(diagnostics)
+                .map_or(Var::Unknown, |section| vars[section.size].clone()),
+            BuiltinId::Startof => args[0]
+                .to_section(diagnostics)
+                .map_or(Var::Unknown, |section| vars[section.addr].clone()),
+            BuiltinId::Strcat
+            | BuiltinId::Strcmp
+            | BuiltinId::Strin
+            | BuiltinId::Strlen
+            | BuiltinId::Strlwr
+            | BuiltinId::Strsub
+            | BuiltinId::Strupr => unreachable!(),
+        })
+    }
+}
+
+fn apply_exact<S: Clone, D: BackendDiagnostics<S>>(
+    f: fn(i32) -> Option<i32>,
+    operand: &Var,
+    span: &S,
+    diagnostics: &mut D,
+) -> Var {
+    match operand.exact() {
+        Some(operand) => check_overflow(f(operand), span, diagnostics),
+        None => Var::Unknown,
+    }
+}
+
+fn apply_exact2<S: Clone, D: BackendDiagnostics<S>>(
+    f: fn(i32, i32) -> Option<i32>,
+    lhs: &Var,
+    rhs: &Var,
+    span: &S,
+    diagnostics: &mut D,
+) -> Var {
+    match (lhs.exact(), rhs.exact()) {
+        (Some(lhs), Some(rhs)) => check_overflow(f(lhs, rhs), span, diagnostics),
+        _ => Var::Unknown,
     }
 }
 
-impl<'a, S: Clone> Spanned<Value<'a, S>, &S> {
-    fn sizeof<C, V, D, I>(&self, context: &'a LinkageContext<C, V>, diagnostics: &mut D) -> Var
+fn check_overflow<S: Clone, D: BackendDiagnostics<S>>(
+    result: Option<i32>,
+    span: &S,
+    diagnostics: &mut D,
+) -> Var {
+    result.map_or_else(
+        || {
+            diagnostics.emit_diag(Message::ArithmeticOverflow.at(span.clone()));
+            Var::Unknown
+        },
+        Into::into,
+    )
+}
+
+impl<'a, S: Clone> Spanned<Value<'a, S>, &'a S> {
+    fn to_num_arg<C, V, D, I>(
+        &self,
+        context: &'a EvalContext<'a, C, V, S>,
+        diagnostics: &mut D,
+    ) -> Var
     where
         C: Borrow<Content<I, S>>,
         V: Borrow<VarTable>,
         D: BackendDiagnostics<S>,
     {
         match self.item {
-            Value::Symbol(Some(DefRef::Section(section))) => {
-                context.vars.borrow()[section.size].clone()
+            Value::Symbol(Some(DefRef::Builtin(_))) => {
+                diagnostics.emit_diag(
+                    Message::ExpectedFound {
+                        expected: ValueKind::Num,
+                        found: ValueKind::Builtin,
+                    }
+                    .at(self.span.clone()),
+                );
+                Var::Unknown
+            }
+            _ => self.clone().eval_subst(context, diagnostics),
+        }
+    }
+
+    fn to_str_arg<C, V, D, I>(
+        &self,
+        context: &'a EvalContext<'a, C, V, S>,
+        diagnostics: &mut D,
+    ) -> Option<Rc<str>>
+    where
+        C: Borrow<Content<I, S>>,
+        V: Borrow<VarTable>,
+        D: BackendDiagnostics<S>,
+    {
+        let found = match self.item {
+            Value::Symbol(None) => return None,
+            Value::Symbol(Some(DefRef::Builtin(_))) => ValueKind::Builtin,
+            _ => match self.clone().call(&[], context, diagnostics) {
+                Const::String(string) => return Some(string),
+                Const::Num(_) => ValueKind::Num,
+            },
+        };
+        diagnostics.emit_diag(
+            Message::ExpectedFound {
+                expected: ValueKind::String,
+                found,
             }
+            .at(self.span.clone()),
+        );
+        None
+    }
+
+    fn to_section<D: BackendDiagnostics<S>>(&self, diagnostics: &mut D) -> Option<&'a Section<S>> {
+        match self.item {
+            Value::Symbol(Some(DefRef::Section(section))) => Some(section),
             ref other => {
                 if let Some(found) = other.kind() {
                     diagnostics.emit_diag(
@@ -260,7 +791,7 @@ impl<'a, S: Clone> Spanned<Value<'a, S>, &S> {
                         .at(self.span.clone()),
                     )
                 }
-                Var::Unknown
+                None
             }
         }
     }
@@ -274,11 +805,37 @@ impl<'a, S: Clone> Value<'a, S> {
             Value::Symbol(Some(DefRef::Section(_))) => Some(ValueKind::Section),
             Value::Symbol(None) => None,
             Value::Num(_) => Some(ValueKind::Num),
+            Value::String(_) => Some(ValueKind::String),
         }
     }
 }
 
-pub const BUILTIN_SYMBOLS: &[(&str, Name)] = &[("sizeof", Name::Builtin(BuiltinId::Sizeof))];
+pub const BUILTIN_SYMBOLS: &[(&str, Name)] = &[
+    ("ABS", Name::Builtin(BuiltinId::Abs)),
+    ("ATAN2", Name::Builtin(BuiltinId::Atan2)),
+    ("CEIL", Name::Builtin(BuiltinId::Ceil)),
+    ("COS", Name::Builtin(BuiltinId::Cos)),
+    ("DEF", Name::Builtin(BuiltinId::Def)),
+    ("DIV", Name::Builtin(BuiltinId::Div)),
+    ("ENDOF", Name::Builtin(BuiltinId::Endof)),
+    ("FLOOR", Name::Builtin(BuiltinId::Floor)),
+    ("HIGH", Name::Builtin(BuiltinId::High)),
+    ("LOW", Name::Builtin(BuiltinId::Low)),
+    ("MAX", Name::Builtin(BuiltinId::Max)),
+    ("MIN", Name::Builtin(BuiltinId::Min)),
+    ("MUL", Name::Builtin(BuiltinId::Mul)),
+    ("ROUND", Name::Builtin(BuiltinId::Round)),
+    ("SIN", Name::Builtin(BuiltinId::Sin)),
+    ("SIZEOF", Name::Builtin(BuiltinId::Sizeof)),
+    ("STARTOF", Name::Builtin(BuiltinId::Startof)),
+    ("STRCAT", Name::Builtin(BuiltinId::Strcat)),
+    ("STRCMP", Name::Builtin(BuiltinId::Strcmp)),
+    ("STRIN", Name::Builtin(BuiltinId::Strin)),
+    ("STRLEN", Name::Builtin(BuiltinId::Strlen)),
+    ("STRLWR", Name::Builtin(BuiltinId::Strlwr)),
+    ("STRSUB", Name::Builtin(BuiltinId::Strsub)),
+    ("STRUPR", Name::Builtin(BuiltinId::Strupr)),
+];
 
 #[cfg(test)]
 mod tests {
@@ -295,7 +852,7 @@ mod tests {
     fn eval_section_addr() {
         let addr = 0x0100;
         let content = &mk_program_with_empty_section();
-        let vars = &VarTable(vec![addr.into(), 0.into()]);
+        let vars = &VarTable::from(vec![addr.into(), 0.into()]);
         let context = LinkageContext {
             content,
             vars,
@@ -311,7 +868,7 @@ mod tests {
     fn eval_section_size() {
         let content = &mk_program_with_empty_section();
         let size = 42;
-        let vars = &VarTable(vec![0.into(), size.into()]);
+        let vars = &VarTable::from(vec![0.into(), size.into()]);
         let context = &LinkageContext {
             content,
             vars,
@@ -333,6 +890,7 @@ mod tests {
         let immediate =
             Expr::from_items(&[SymbolId(0).into(), 42.into(), ExprOp::FnCall(1).into()]);
         let content = &Content {
+            assertions: vec![],
             sections: vec![],
             symbols: vec![Symbol::Exported {
                 ident: "f",
@@ -341,12 +899,13 @@ mod tests {
                     meaning: SymbolMeaning::Closure(Closure {
                         expr: Expr::from_items(&[ParamId(0).into(), 1.into(), BinOp::Plus.into()]),
                         location: VarId(0),
+                        params: 1,
                     }),
                 },
             }],
             vars: 1,
         };
-        let vars = &VarTable(Vec::new());
+        let vars = &VarTable::new();
         let context = &LinkageContext {
             content,
             vars,
@@ -365,6 +924,7 @@ mod tests {
             BinOp::Plus.into(),
         ]);
         let content = &Content {
+            assertions: vec![],
             sections: vec![],
             symbols: vec![Symbol::Exported {
                 ident: "f",
@@ -373,12 +933,13 @@ mod tests {
                     meaning: SymbolMeaning::Closure(Closure {
                         expr: Expr::from_items(&[ParamId(0).into(), 1.into(), BinOp::Plus.into()]),
                         location: VarId(0),
+                        params: 1,
                     }),
                 },
             }],
             vars: 1,
         };
-        let vars = &VarTable(Vec::new());
+        let vars = &VarTable::new();
         let context = &LinkageContext {
             content,
             vars,
@@ -391,7 +952,7 @@ mod tests {
     fn eval_section_name_call() {
         let addr = 0x1337;
         let content = &mk_program_with_empty_section();
-        let vars = &VarTable(vec![addr.into(), 0.into()]);
+        let vars = &VarTable::from(vec![addr.into(), 0.into()]);
         let context = LinkageContext {
             content,
             vars,
@@ -419,6 +980,66 @@ mod tests {
         )
     }
 
+    #[test]
+    fn eval_bit_manipulation() {
+        let cases: &[(i32, BinOp, i32, i32)] = &[
+            (0x3c, BinOp::BitAnd, 0x0f, 0x0c),
+            (0x3c, BinOp::BitXor, 0x0f, 0x33),
+            (1, BinOp::ShiftLeft, 7, 0x80),
+            (0x80, BinOp::ShiftRight, 4, 0x08),
+            (17, BinOp::Modulo, 5, 2),
+        ];
+        for &(lhs, op, rhs, result) in cases {
+            assert_eq!(
+                eval_in_empty_program(
+                    Expr::from_items(&[lhs.into(), rhs.into(), op.into()]),
+                    &mut IgnoreDiagnostics
+                ),
+                result.into()
+            )
+        }
+    }
+
+    #[test]
+    fn eval_comparisons() {
+        let cases: &[(i32, BinOp, i32, i32)] = &[
+            (1, BinOp::Inequality, 2, 1),
+            (1, BinOp::Less, 2, 1),
+            (2, BinOp::LessOrEqual, 2, 1),
+            (1, BinOp::Greater, 2, 0),
+            (1, BinOp::GreaterOrEqual, 2, 0),
+            (1, BinOp::LogicalAnd, 0, 0),
+            (1, BinOp::LogicalOr, 0, 1),
+        ];
+        for &(lhs, op, rhs, result) in cases {
+            assert_eq!(
+                eval_in_empty_program(
+                    Expr::from_items(&[lhs.into(), rhs.into(), op.into()]),
+                    &mut IgnoreDiagnostics
+                ),
+                result.into()
+            )
+        }
+    }
+
+    #[test]
+    fn eval_unary_operators() {
+        let cases: &[(UnaryOp, i32, i32)] = &[
+            (UnaryOp::BitNot, 0, -1),
+            (UnaryOp::Negation, 5, -5),
+            (UnaryOp::Not, 5, 0),
+        ];
+        for &(op, operand, result) in cases {
+            assert_eq!(
+                eval_in_empty_program(
+                    Expr::from_items(&[operand.into(), op.into()]),
+                    &mut IgnoreDiagnostics
+                ),
+                result.into()
+            )
+        }
+    }
+
     #[test]
     fn eval_division() {
         assert_eq!(
@@ -453,7 +1074,90 @@ mod tests {
     }
 
     #[test]
-    fn diagnose_using_sizeof_as_immediate() {
+    fn eval_numeric_builtins() {
+        let cases: &[(BuiltinId, &[i32], i32)] = &[
+            (BuiltinId::Abs, &[-7], 7),
+            (BuiltinId::Atan2, &[0x1_0000, 0x1_0000], 0x2000),
+            (BuiltinId::Ceil, &[0x1_0001], 0x2_0000),
+            (BuiltinId::Cos, &[0x8000], -0x1_0000),
+            (BuiltinId::Div, &[0x3_0000, 0x2_0000], 0x1_8000),
+            (BuiltinId::Floor, &[0x1_ffff], 0x1_0000),
+            (BuiltinId::High, &[0x1234], 0x12),
+            (BuiltinId::Low, &[0x1234], 0x34),
+            (BuiltinId::Max, &[3, 5], 5),
+            (BuiltinId::Min, &[3, 5], 3),
+            (BuiltinId::Mul, &[0x1_8000, 0x2_0000], 0x3_0000),
+            (BuiltinId::Round, &[0x1_8000], 0x2_0000),
+            (BuiltinId::Sin, &[0x4000], 0x1_0000),
+        ];
+        for &(builtin, args, result) in cases {
+            let mut items = vec![builtin.into()];
+            items.extend(args.iter().map(|&arg| arg.into()));
+            items.push(ExprOp::FnCall(args.len()).into());
+            assert_eq!(
+                eval_in_empty_program(Expr::from_items(&items), &mut IgnoreDiagnostics),
+                result.into()
+            )
+        }
+    }
+
+    #[test]
+    fn eval_string_builtins() {
+        let string = |string: &str| ExprOp::Atom(Atom::String(string.into()));
+        let cases = vec![
+            (
+                BuiltinId::Strcat,
+                vec![string("foo"), string("bar")],
+                Const::String("foobar".into()),
+            ),
+            (
+                BuiltinId::Strcmp,
+                vec![string("abc"), string("abd")],
+                Const::Num((-1).into()),
+            ),
+            (
+                BuiltinId::Strin,
+                vec![string("hello"), string("ll")],
+                Const::Num(3.into()),
+            ),
+            (
+                BuiltinId::Strin,
+                vec![string("hello"), string("x")],
+                Const::Num(0.into()),
+            ),
+            (
+                BuiltinId::Strlen,
+                vec![string("héllo")],
+                Const::Num(5.into()),
+            ),
+            (
+                BuiltinId::Strlwr,
+                vec![string("ABC")],
+                Const::String("abc".into()),
+            ),
+            (
+                BuiltinId::Strsub,
+                vec![string("hello"), 2.into(), 3.into()],
+                Const::String("ell".into()),
+            ),
+            (
+                BuiltinId::Strupr,
+                vec![string("abc")],
+                Const::String("ABC".into()),
+            ),
+        ];
+        for (builtin, args, result) in cases {
+            let arity = args.len();
+            let mut items = vec![ExprOp::from(builtin)];
+            items.extend(args);
+            items.push(ExprOp::FnCall(arity));
+            let expr = crate::expr::Expr(items.into_iter().map(Into::into).collect());
+            assert_eq!(const_in_empty_program(expr, &mut IgnoreDiagnostics), result)
+        }
+    }
+
+    #[test]
+    fn diagnose_num_as_string_arg() {
         let log = Log::default();
         let registry = &mut TestDiagnosticsListener::new();
         let mut diagnostics = MockDiagnostics::new(log.clone());
@@ -464,49 +1168,528 @@ mod tests {
             registry,
             diagnostics: &mut diagnostics,
         };
-        let immediate = Expr::from_atom(
-            Atom::Name(Name::Builtin(BuiltinId::Sizeof)),
-            MockSpan::from(0),
-        );
+        let immediate = crate::expr::Expr(vec![
+            ExprOp::Atom(Atom::Name(Name::Builtin(BuiltinId::Strlen))).with_span(MockSpan::from(0)),
+            ExprOp::Atom(Atom::Const(42)).with_span(MockSpan::from(1)),
+            ExprOp::FnCall(1).with_span(MockSpan::from(2)),
+        ]);
         let value = eval_in_empty_program(immediate, &mut view);
         drop(diagnostics);
         assert_eq!(value, Var::Unknown);
         assert_eq!(
             log.into_inner(),
             [DiagnosticsEvent::EmitDiag(
-                Message::CannotCoerceBuiltinNameIntoNum { name: 0.into() }
-                    .at(0.into())
-                    .into()
+                Message::ExpectedFound {
+                    expected: ValueKind::String,
+                    found: ValueKind::Num,
+                }
+                .at(1.into())
+                .into()
             )]
         )
     }
 
     #[test]
-    fn diagnose_calling_undefined_symbol() {
-        let content = &Content {
-            sections: vec![],
-            symbols: vec![Symbol::Unknown { ident: "f" }],
-            vars: 0,
-        };
-        let vars = &VarTable(vec![]);
-        let context = LinkageContext {
-            content,
-            vars,
-            location: Var::Unknown,
-        };
-        let log = Log::default();
-        let registry = &mut TestDiagnosticsListener::new();
-        let mut diagnostics = MockDiagnostics::new(log.clone());
-        let mut fs = MockFileSystem::new();
-        let codebase = &mut Codebase::new(&mut fs);
-        let mut view = DiagnosticsContext {
-            codebase,
-            registry,
-            diagnostics: &mut diagnostics,
-        };
-        let name_span = MockSpan::from("name");
-        let call_span = MockSpan::from("call");
-        let immediate = crate::expr::Expr(vec![
+    fn diagnose_division_by_zero() {
+        for operator in &[BinOp::Division, BinOp::Modulo] {
+            assert_eq!(
+                eval_binary_with_diagnostics(1, *operator, 0),
+                (
+                    Var::Unknown,
+                    vec![DiagnosticsEvent::EmitDiag(
+                        Message::DivisionByZero.at(2.into()).into()
+                    )]
+                )
+            )
+        }
+    }
+
+    #[test]
+    fn diagnose_arithmetic_overflow() {
+        let cases: &[(i32, BinOp, i32)] = &[
+            (i32::MAX, BinOp::Plus, 1),
+            (i32::MIN, BinOp::Minus, 1),
+            (0x10000, BinOp::Multiplication, 0x10000),
+            (i32::MIN, BinOp::Division, -1),
+            (0x4000_0000, BinOp::ShiftLeft, 2),
+        ];
+        for &(lhs, operator, rhs) in cases {
+            assert_eq!(
+                eval_binary_with_diagnostics(lhs, operator, rhs),
+                (
+                    Var::Unknown,
+                    vec![DiagnosticsEvent::EmitDiag(
+                        Message::ArithmeticOverflow.at(2.into()).into()
+                    )]
+                )
+            )
+        }
+    }
+
+    #[test]
+    fn diagnose_fixed_point_division_by_zero() {
+        assert_eq!(
+            eval_fixed_with_diagnostics(BuiltinId::Div, 0x1_0000, 0),
+            (
+                Var::Unknown,
+                vec![DiagnosticsEvent::EmitDiag(
+                    Message::DivisionByZero.at(3.into()).into()
+                )]
+            )
+        )
+    }
+
+    #[test]
+    fn diagnose_fixed_point_overflow() {
+        for &builtin in &[BuiltinId::Div, BuiltinId::Mul] {
+            let (lhs, rhs) = match builtin {
+                BuiltinId::Div => (0x100_0000, 0x100),
+                _ => (0x100_0000, 0x100_0000),
+            };
+            assert_eq!(
+                eval_fixed_with_diagnostics(builtin, lhs, rhs),
+                (
+                    Var::Unknown,
+                    vec![DiagnosticsEvent::EmitDiag(
+                        Message::ArithmeticOverflow.at(3.into()).into()
+                    )]
+                )
+            )
+        }
+    }
+
+    #[test]
+    fn diagnose_abs_overflow() {
+        assert_eq!(
+            eval_with_diagnostics(vec![
+                ExprOp::Atom(Atom::Name(Name::Builtin(BuiltinId::Abs))),
+                ExprOp::Atom(Atom::Const(i32::MIN)),
+                ExprOp::FnCall(1),
+            ]),
+            (
+                Var::Unknown,
+                vec![DiagnosticsEvent::EmitDiag(
+                    Message::ArithmeticOverflow.at(2.into()).into()
+                )]
+            )
+        )
+    }
+
+    #[test]
+    fn diagnose_invalid_shift_amount() {
+        let cases: &[(BinOp, i32)] = &[
+            (BinOp::ShiftLeft, -1),
+            (BinOp::ShiftLeft, 32),
+            (BinOp::ShiftRight, -1),
+            (BinOp::ShiftRight, 32),
+        ];
+        for &(operator, shift) in cases {
+            assert_eq!(
+                eval_binary_with_diagnostics(1, operator, shift),
+                (
+                    Var::Unknown,
+                    vec![DiagnosticsEvent::EmitDiag(
+                        Message::OutOfRange {
+                            value: shift,
+                            min: 0,
+                            max: 31
+                        }
+                        .at(2.into())
+                        .into()
+                    )]
+                )
+            )
+        }
+    }
+
+    fn eval_fixed_with_diagnostics(
+        builtin: BuiltinId,
+        lhs: i32,
+        rhs: i32,
+    ) -> (Var, Vec<DiagnosticsEvent<MockSpan<usize>>>) {
+        eval_with_diagnostics(vec![
+            ExprOp::Atom(Atom::Name(Name::Builtin(builtin))),
+            ExprOp::Atom(Atom::Const(lhs)),
+            ExprOp::Atom(Atom::Const(rhs)),
+            ExprOp::FnCall(2),
+        ])
+    }
+
+    fn eval_binary_with_diagnostics(
+        lhs: i32,
+        operator: BinOp,
+        rhs: i32,
+    ) -> (Var, Vec<DiagnosticsEvent<MockSpan<usize>>>) {
+        eval_with_diagnostics(vec![
+            ExprOp::Atom(Atom::Const(lhs)),
+            ExprOp::Atom(Atom::Const(rhs)),
+            ExprOp::Binary(operator),
+        ])
+    }
+
+    fn eval_with_diagnostics(
+        ops: Vec<ExprOp<Name>>,
+    ) -> (Var, Vec<DiagnosticsEvent<MockSpan<usize>>>) {
+        let log = Log::default();
+        let registry = &mut TestDiagnosticsListener::new();
+        let mut diagnostics = MockDiagnostics::new(log.clone());
+        let mut fs = MockFileSystem::new();
+        let codebase = &mut Codebase::new(&mut fs);
+        let mut view = DiagnosticsContext {
+            codebase,
+            registry,
+            diagnostics: &mut diagnostics,
+        };
+        let immediate = crate::expr::Expr(
+            ops.into_iter()
+                .enumerate()
+                .map(|(i, op)| op.with_span(MockSpan::from(i)))
+                .collect(),
+        );
+        let value = eval_in_empty_program(immediate, &mut view);
+        drop(diagnostics);
+        (value, log.into_inner())
+    }
+
+    #[test]
+    fn diagnose_string_as_num() {
+        let log = Log::default();
+        let registry = &mut TestDiagnosticsListener::new();
+        let mut diagnostics = MockDiagnostics::new(log.clone());
+        let mut fs = MockFileSystem::new();
+        let codebase = &mut Codebase::new(&mut fs);
+        let mut view = DiagnosticsContext {
+            codebase,
+            registry,
+            diagnostics: &mut diagnostics,
+        };
+        let immediate = crate::expr::Expr(vec![
+            ExprOp::Atom(Atom::String("abc".into())).with_span(MockSpan::from(0)),
+            ExprOp::Unary(UnaryOp::Negation).with_span(MockSpan::from(1)),
+        ]);
+        let value = eval_in_empty_program(immediate, &mut view);
+        drop(diagnostics);
+        assert_eq!(value, Var::Unknown);
+        assert_eq!(
+            log.into_inner(),
+            [DiagnosticsEvent::EmitDiag(
+                Message::ExpectedFound {
+                    expected: ValueKind::Num,
+                    found: ValueKind::String,
+                }
+                .at(0.into())
+                .into()
+            )]
+        )
+    }
+
+    #[test]
+    fn eval_section_bounds() {
+        let content = &mk_program_with_empty_section();
+        let vars = &VarTable::from(vec![0x4000.into(), 0x10.into()]);
+        let context = &LinkageContext {
+            content,
+            vars,
+            location: Var::Unknown,
+        };
+        for &(builtin, result) in &[(BuiltinId::Startof, 0x4000), (BuiltinId::Endof, 0x4010)] {
+            assert_eq!(
+                Expr::from_items(&[builtin.into(), SymbolId(0).into(), ExprOp::FnCall(1).into()])
+                    .to_num(context, &mut IgnoreDiagnostics),
+                result.into()
+            )
+        }
+    }
+
+    #[test]
+    fn def_of_undefined_symbol_is_zero_without_diagnostics() {
+        let content = &Content {
+            assertions: vec![],
+            sections: vec![],
+            symbols: vec![Symbol::Unknown { ident: "x" }],
+            vars: 0,
+        };
+        let vars = &VarTable::from(vec![]);
+        let context = LinkageContext {
+            content,
+            vars,
+            location: Var::Unknown,
+        };
+        let log = Log::default();
+        let registry = &mut TestDiagnosticsListener::new();
+        let mut diagnostics = MockDiagnostics::new(log.clone());
+        let mut fs = MockFileSystem::new();
+        let codebase = &mut Codebase::new(&mut fs);
+        let mut view = DiagnosticsContext {
+            codebase,
+            registry,
+            diagnostics: &mut diagnostics,
+        };
+        let immediate = crate::expr::Expr(vec![
+            ExprOp::Atom(Atom::Name(Name::Builtin(BuiltinId::Def))).with_span(MockSpan::from(0)),
+            ExprOp::Atom(Atom::Name(Name::Symbol(SymbolId(0)))).with_span(MockSpan::from(1)),
+            ExprOp::FnCall(1).with_span(MockSpan::from(2)),
+        ]);
+        let value = immediate.to_num(&context, &mut view);
+        drop(diagnostics);
+        assert_eq!(value, 0.into());
+        assert_eq!(log.into_inner(), [])
+    }
+
+    #[test]
+    fn def_of_section_is_one() {
+        let content = &mk_program_with_empty_section();
+        let vars = &VarTable::from(vec![0.into(), 0.into()]);
+        let context = &LinkageContext {
+            content,
+            vars,
+            location: Var::Unknown,
+        };
+        assert_eq!(
+            Expr::from_items(&[
+                BuiltinId::Def.into(),
+                SymbolId(0).into(),
+                ExprOp::FnCall(1).into()
+            ])
+            .to_num(context, &mut IgnoreDiagnostics),
+            1.into()
+        )
+    }
+
+    #[test]
+    fn diagnose_builtin_arg_count() {
+        let log = Log::default();
+        let registry = &mut TestDiagnosticsListener::new();
+        let mut diagnostics = MockDiagnostics::new(log.clone());
+        let mut fs = MockFileSystem::new();
+        let codebase = &mut Codebase::new(&mut fs);
+        let mut view = DiagnosticsContext {
+            codebase,
+            registry,
+            diagnostics: &mut diagnostics,
+        };
+        let immediate = crate::expr::Expr(vec![
+            ExprOp::Atom(Atom::Name(Name::Builtin(BuiltinId::Max))).with_span(MockSpan::from(0)),
+            ExprOp::Atom(Atom::Const(1)).with_span(MockSpan::from(1)),
+            ExprOp::FnCall(1).with_span(MockSpan::from(2)),
+        ]);
+        let value = eval_in_empty_program(immediate, &mut view);
+        drop(diagnostics);
+        assert_eq!(value, Var::Unknown);
+        assert_eq!(
+            log.into_inner(),
+            [DiagnosticsEvent::EmitDiag(
+                Message::ArgCount {
+                    name: 0.into(),
+                    actual: 1,
+                    expected: 2,
+                }
+                .at(0.into())
+                .into()
+            )]
+        )
+    }
+
+    #[test]
+    fn diagnose_builtin_as_numeric_arg() {
+        let log = Log::default();
+        let registry = &mut TestDiagnosticsListener::new();
+        let mut diagnostics = MockDiagnostics::new(log.clone());
+        let mut fs = MockFileSystem::new();
+        let codebase = &mut Codebase::new(&mut fs);
+        let mut view = DiagnosticsContext {
+            codebase,
+            registry,
+            diagnostics: &mut diagnostics,
+        };
+        let immediate = crate::expr::Expr(vec![
+            ExprOp::Atom(Atom::Name(Name::Builtin(BuiltinId::Low))).with_span(MockSpan::from(0)),
+            ExprOp::Atom(Atom::Name(Name::Builtin(BuiltinId::High))).with_span(MockSpan::from(1)),
+            ExprOp::FnCall(1).with_span(MockSpan::from(2)),
+        ]);
+        let value = eval_in_empty_program(immediate, &mut view);
+        drop(diagnostics);
+        assert_eq!(value, Var::Unknown);
+        assert_eq!(
+            log.into_inner(),
+            [DiagnosticsEvent::EmitDiag(
+                Message::ExpectedFound {
+                    expected: ValueKind::Num,
+                    found: ValueKind::Builtin,
+                }
+                .at(1.into())
+                .into()
+            )]
+        )
+    }
+
+    #[test]
+    fn diagnose_calling_function_with_wrong_arg_count() {
+        let content = &Content {
+            assertions: vec![],
+            sections: vec![],
+            symbols: vec![Symbol::Exported {
+                ident: "f",
+                def: SymbolDefRecord {
+                    def_ident_span: MockSpan::from("f"),
+                    meaning: SymbolMeaning::Closure(Closure {
+                        expr: Expr::from_atom(ParamId(0).into(), MockSpan::from("param")),
+                        location: VarId(0),
+                        params: 1,
+                    }),
+                },
+            }],
+            vars: 1,
+        };
+        let vars = &VarTable::from(vec![Var::Unknown]);
+        let context = LinkageContext {
+            content,
+            vars,
+            location: Var::Unknown,
+        };
+        let log = Log::default();
+        let registry = &mut TestDiagnosticsListener::new();
+        let mut diagnostics = MockDiagnostics::new(log.clone());
+        let mut fs = MockFileSystem::new();
+        let codebase = &mut Codebase::new(&mut fs);
+        let mut view = DiagnosticsContext {
+            codebase,
+            registry,
+            diagnostics: &mut diagnostics,
+        };
+        let immediate = crate::expr::Expr(vec![
+            ExprOp::Atom(Atom::Name(Name::Symbol(SymbolId(0)))).with_span(MockSpan::from("f")),
+            ExprOp::FnCall(0).with_span(MockSpan::from("call")),
+        ]);
+        let value = immediate.to_num(&context, &mut view);
+        drop(diagnostics);
+        assert_eq!(value, Var::Unknown);
+        assert_eq!(
+            log.into_inner(),
+            [DiagnosticsEvent::EmitDiag(
+                Message::ArgCount {
+                    name: MockSpan::from("f"),
+                    actual: 0,
+                    expected: 1,
+                }
+                .at(MockSpan::from("f"))
+                .into()
+            )]
+        )
+    }
+
+    #[test]
+    fn diagnose_recursive_definition() {
+        let content = &Content {
+            assertions: vec![],
+            sections: vec![],
+            symbols: vec![Symbol::Exported {
+                ident: "x",
+                def: SymbolDefRecord {
+                    def_ident_span: MockSpan::from("x"),
+                    meaning: SymbolMeaning::Closure(Closure {
+                        expr: Expr::from_atom(
+                            Atom::Name(Name::Symbol(SymbolId(0))),
+                            MockSpan::from("inner"),
+                        ),
+                        location: VarId(0),
+                        params: 0,
+                    }),
+                },
+            }],
+            vars: 1,
+        };
+        let vars = &VarTable::from(vec![Var::Unknown]);
+        let context = LinkageContext {
+            content,
+            vars,
+            location: Var::Unknown,
+        };
+        let log = Log::default();
+        let registry = &mut TestDiagnosticsListener::new();
+        let mut diagnostics = MockDiagnostics::new(log.clone());
+        let mut fs = MockFileSystem::new();
+        let codebase = &mut Codebase::new(&mut fs);
+        let mut view = DiagnosticsContext {
+            codebase,
+            registry,
+            diagnostics: &mut diagnostics,
+        };
+        let immediate = Expr::from_atom(
+            Atom::Name(Name::Symbol(SymbolId(0))),
+            MockSpan::from("outer"),
+        );
+        let value = immediate.to_num(&context, &mut view);
+        drop(diagnostics);
+        assert_eq!(value, Var::Unknown);
+        assert_eq!(
+            log.into_inner(),
+            [DiagnosticsEvent::EmitDiag(
+                Message::RecursiveDefinition {
+                    name: MockSpan::from("inner")
+                }
+                .at(MockSpan::from("inner"))
+                .into()
+            )]
+        )
+    }
+
+    #[test]
+    fn diagnose_using_sizeof_as_immediate() {
+        let log = Log::default();
+        let registry = &mut TestDiagnosticsListener::new();
+        let mut diagnostics = MockDiagnostics::new(log.clone());
+        let mut fs = MockFileSystem::new();
+        let codebase = &mut Codebase::new(&mut fs);
+        let mut view = DiagnosticsContext {
+            codebase,
+            registry,
+            diagnostics: &mut diagnostics,
+        };
+        let immediate = Expr::from_atom(
+            Atom::Name(Name::Builtin(BuiltinId::Sizeof)),
+            MockSpan::from(0),
+        );
+        let value = eval_in_empty_program(immediate, &mut view);
+        drop(diagnostics);
+        assert_eq!(value, Var::Unknown);
+        assert_eq!(
+            log.into_inner(),
+            [DiagnosticsEvent::EmitDiag(
+                Message::CannotCoerceBuiltinNameIntoNum { name: 0.into() }
+                    .at(0.into())
+                    .into()
+            )]
+        )
+    }
+
+    #[test]
+    fn diagnose_calling_undefined_symbol() {
+        let content = &Content {
+            assertions: vec![],
+            sections: vec![],
+            symbols: vec![Symbol::Unknown { ident: "f" }],
+            vars: 0,
+        };
+        let vars = &VarTable::from(vec![]);
+        let context = LinkageContext {
+            content,
+            vars,
+            location: Var::Unknown,
+        };
+        let log = Log::default();
+        let registry = &mut TestDiagnosticsListener::new();
+        let mut diagnostics = MockDiagnostics::new(log.clone());
+        let mut fs = MockFileSystem::new();
+        let codebase = &mut Codebase::new(&mut fs);
+        let mut view = DiagnosticsContext {
+            codebase,
+            registry,
+            diagnostics: &mut diagnostics,
+        };
+        let name_span = MockSpan::from("name");
+        let call_span = MockSpan::from("call");
+        let immediate = crate::expr::Expr(vec![
             ExprOp::Atom(Atom::Name(Name::Symbol(SymbolId(0)))).with_span(name_span.clone()),
             ExprOp::FnCall(0).with_span(MockSpan::merge(name_span.clone(), call_span)),
         ]);
@@ -545,6 +1728,7 @@ mod tests {
 
     fn test_diagnosis_of_wrong_sizeof_arg(inner: Atom<Name>, found: ValueKind) {
         let content = &Content {
+            assertions: vec![],
             sections: vec![],
             symbols: vec![Symbol::Exported {
                 ident: "f",
@@ -553,12 +1737,13 @@ mod tests {
                     meaning: SymbolMeaning::Closure(Closure {
                         expr: Expr::from_atom(42.into(), MockSpan::from("42")),
                         location: VarId(0),
+                        params: 0,
                     }),
                 },
             }],
             vars: 1,
         };
-        let vars = &VarTable(vec![]);
+        let vars = &VarTable::from(vec![]);
         let context = LinkageContext {
             content,
             vars,
@@ -600,8 +1785,9 @@ mod tests {
 
     fn mk_program_with_empty_section() -> Content<&'static str, ()> {
         Content {
+            assertions: vec![],
             sections: vec![Section {
-                constraints: Constraints { addr: None },
+                constraints: Constraints::default(),
                 addr: VarId(0),
                 size: VarId(1),
                 fragments: vec![],
@@ -622,11 +1808,12 @@ mod tests {
         diagnostics: &mut impl BackendDiagnostics<S>,
     ) -> Var {
         let content = &Content::<&str, _> {
+            assertions: vec![],
             sections: vec![],
             symbols: vec![],
             vars: 0,
         };
-        let vars = &VarTable(vec![]);
+        let vars = &VarTable::from(vec![]);
         let context = &LinkageContext {
             content,
             vars,
@@ -634,4 +1821,23 @@ mod tests {
         };
         immediate.to_num(context, diagnostics)
     }
+
+    fn const_in_empty_program<S: Clone>(
+        immediate: Expr<S>,
+        diagnostics: &mut impl BackendDiagnostics<S>,
+    ) -> Const {
+        let content = &Content::<&str, _> {
+            assertions: vec![],
+            sections: vec![],
+            symbols: vec![],
+            vars: 0,
+        };
+        let vars = &VarTable::from(vec![]);
+        let context = &LinkageContext {
+            content,
+            vars,
+            location: Var::Unknown,
+        };
+        immediate.to_const(context, diagnostics)
+    }
 }
@@ -1,5 +1,7 @@
 use crate::span::{Source, SpanSource, Spanned, WithSpan};
 
+use std::rc::Rc;
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Expr<N, S>(pub Vec<Spanned<ExprOp<N>, S>>);
 
@@ -8,6 +10,7 @@ pub enum ExprOp<N> {
     Atom(Atom<N>),
     Binary(BinOp),
     FnCall(usize),
+    Unary(UnaryOp),
 }
 
 impl<N, S> Default for Expr<N, S> {
@@ -87,6 +90,12 @@ impl<N> From<BinOp> for ExprOp<N> {
     }
 }
 
+impl<N> From<UnaryOp> for ExprOp<N> {
+    fn from(op: UnaryOp) -> Self {
+        ExprOp::Unary(op)
+    }
+}
+
 impl<A> From<FnCall> for ExprOp<A> {
     fn from(FnCall(n): FnCall) -> Self {
         ExprOp::FnCall(n)
@@ -95,12 +104,31 @@ impl<A> From<FnCall> for ExprOp<A> {
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BinOp {
+    BitAnd,
     BitOr,
+    BitXor,
     Division,
     Equality,
+    Greater,
+    GreaterOrEqual,
+    Inequality,
+    Less,
+    LessOrEqual,
+    LogicalAnd,
+    LogicalOr,
     Minus,
+    Modulo,
     Multiplication,
     Plus,
+    ShiftLeft,
+    ShiftRight,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnaryOp {
+    BitNot,
+    Negation,
+    Not,
 }
 
 pub struct FnCall(pub usize);
@@ -111,6 +139,7 @@ pub enum Atom<N> {
     Location,
     Name(N),
     Param(ParamId),
+    String(Rc<str>),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -0,0 +1,184 @@
+//! 16.16 fixed-point arithmetic.
+//!
+//! Angles are expressed in turns, so `1.0` is a full circle. Everything is computed with integer
+//! arithmetic to keep results identical on every host.
+
+use std::convert::TryFrom;
+
+pub const ONE: i32 = 1 << 16;
+
+const FRACTION_MASK: i32 = ONE - 1;
+
+const Q30_ONE: i64 = 1 << 30;
+const TWO_PI_Q30: i64 = 6_746_518_852;
+
+// atan(2^-i) expressed in turns with 32 fractional bits.
+const ATAN_TURNS_Q32: [i64; 24] = [
+    0x2000_0000,
+    0x12e4_051e,
+    0x09fb_385b,
+    0x0511_11d4,
+    0x028b_0d43,
+    0x0145_d7e1,
+    0x00a2_f61e,
+    0x0051_7c55,
+    0x0028_be53,
+    0x0014_5f2f,
+    0x000a_2f98,
+    0x0005_17cc,
+    0x0002_8be6,
+    0x0001_45f3,
+    0x0000_a2fa,
+    0x0000_517d,
+    0x0000_28be,
+    0x0000_145f,
+    0x0000_0a30,
+    0x0000_0518,
+    0x0000_028c,
+    0x0000_0146,
+    0x0000_00a3,
+    0x0000_0051,
+];
+
+pub fn parse(integer: &str, fraction: &str) -> Option<i32> {
+    let integer: i64 = integer.parse().ok()?;
+    let mut numerator = 0i64;
+    let mut denominator = 1i64;
+    for digit in fraction.bytes().take(9) {
+        numerator = numerator * 10 + i64::from(digit - b'0');
+        denominator *= 10;
+    }
+    let fraction = ((numerator << 16) + denominator / 2) / denominator;
+    i32::try_from((integer << 16) + fraction).ok()
+}
+
+pub fn mul(lhs: i32, rhs: i32) -> Option<i32> {
+    i32::try_from((i64::from(lhs) * i64::from(rhs)) >> 16).ok()
+}
+
+pub fn div(lhs: i32, rhs: i32) -> Option<i32> {
+    if rhs == 0 {
+        return None;
+    }
+    i32::try_from((i64::from(lhs) << 16) / i64::from(rhs)).ok()
+}
+
+pub fn floor(value: i32) -> Option<i32> {
+    Some(value & !FRACTION_MASK)
+}
+
+pub fn ceil(value: i32) -> Option<i32> {
+    value.checked_add(FRACTION_MASK).and_then(floor)
+}
+
+pub fn round(value: i32) -> Option<i32> {
+    value.checked_add(ONE / 2).and_then(floor)
+}
+
+pub fn sin(angle: i32) -> Option<i32> {
+    let turn = angle & FRACTION_MASK;
+    let quadrant = turn >> 14;
+    let offset = i64::from(turn & 0x3fff);
+    let offset = if quadrant & 1 == 1 {
+        0x4000 - offset
+    } else {
+        offset
+    };
+    let x = (offset * TWO_PI_Q30) >> 16;
+    let x2 = (x * x) >> 30;
+    let mut series = Q30_ONE;
+    for divisor in &[110, 72, 42, 20, 6] {
+        series = Q30_ONE - ((x2 * series) >> 30) / divisor;
+    }
+    let sin = (((x * series) >> 30) + (1 << 13)) >> 14;
+    Some(if quadrant >= 2 { -sin } else { sin } as i32)
+}
+
+pub fn cos(angle: i32) -> Option<i32> {
+    sin(angle.wrapping_add(ONE / 4))
+}
+
+pub fn atan2(y: i32, x: i32) -> Option<i32> {
+    if x == 0 && y == 0 {
+        return Some(0);
+    }
+    let (mut x, mut y) = (i64::from(x) << 24, i64::from(y) << 24);
+    let mut angle = 0;
+    if x < 0 {
+        angle = if y >= 0 { 1 << 31 } else { -(1 << 31) };
+        x = -x;
+        y = -y;
+    }
+    for (i, step) in ATAN_TURNS_Q32.iter().enumerate() {
+        let (dx, dy) = (y >> i, x >> i);
+        if y > 0 {
+            x += dx;
+            y -= dy;
+            angle += step
+        } else {
+            x -= dx;
+            y += dy;
+            angle -= step
+        }
+    }
+    Some(((angle + (1 << 15)) >> 16) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_fixed_point_literals() {
+        assert_eq!(parse("1", "5"), Some(0x1_8000));
+        assert_eq!(parse("0", "25"), Some(0x4000));
+        assert_eq!(parse("3", "14159"), Some(0x3_243f));
+        assert_eq!(parse("32768", "0"), None)
+    }
+
+    #[test]
+    fn multiply_and_divide() {
+        assert_eq!(mul(0x1_8000, 0x2_0000), Some(0x3_0000));
+        assert_eq!(mul(-0x8000, 0x8000), Some(-0x4000));
+        assert_eq!(div(0x3_0000, 0x2_0000), Some(0x1_8000));
+        assert_eq!(div(ONE, 0), None)
+    }
+
+    #[test]
+    fn round_to_integers() {
+        assert_eq!(floor(-0x1_8000), Some(-0x2_0000));
+        assert_eq!(ceil(0x1_0001), Some(0x2_0000));
+        assert_eq!(round(0x1_8000), Some(0x2_0000));
+        assert_eq!(round(0x1_7fff), Some(0x1_0000));
+        assert_eq!(ceil(i32::MAX), None)
+    }
+
+    #[test]
+    fn sine_at_quadrant_boundaries() {
+        assert_eq!(sin(0), Some(0));
+        assert_eq!(sin(0x4000), Some(ONE));
+        assert_eq!(sin(0x8000), Some(0));
+        assert_eq!(sin(0xc000), Some(-ONE));
+        assert_eq!(sin(0x1_4000), Some(ONE));
+        assert_eq!(cos(0), Some(ONE))
+    }
+
+    #[test]
+    fn sine_of_eighth_turn() {
+        let expected = 46341; // sqrt(2) / 2
+        assert_eq!(sin(0x2000), Some(expected));
+        assert_eq!(sin(-0x2000), Some(-expected));
+        assert_eq!(cos(0x6000), Some(-expected))
+    }
+
+    #[test]
+    fn arctangent_in_each_quadrant() {
+        assert_eq!(atan2(0, ONE), Some(0));
+        assert_eq!(atan2(ONE, ONE), Some(0x2000));
+        assert_eq!(atan2(ONE, 0), Some(0x4000));
+        assert_eq!(atan2(ONE, -ONE), Some(0x6000));
+        assert_eq!(atan2(0, -ONE), Some(0x8000));
+        assert_eq!(atan2(-ONE, -ONE), Some(-0x6000));
+        assert_eq!(atan2(-ONE, 0), Some(-0x4000))
+    }
+}
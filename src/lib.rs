@@ -10,13 +10,14 @@ pub use crate::codebase::FileSystem;
 pub use crate::diagnostics::*;
 pub use crate::linker::Linker;
 pub use crate::object::Object;
-pub use crate::program::{Chunk, Program};
+pub use crate::program::{Chunk, Gap, Header, Program, RomOptions, RomSize};
 
 mod assembler;
 mod codebase;
 mod diagnostics;
 mod eval;
 mod expr;
+mod fixed;
 mod linker;
 mod object;
 mod program;
@@ -29,28 +30,29 @@ mod log;
 pub struct Config<'a> {
     pub input: InputConfig<'a>,
     pub diagnostics: DiagnosticsConfig<'a>,
+    pub print: PrintConfig<'a>,
 }
 
+#[derive(Default)]
 pub enum InputConfig<'a> {
+    #[default]
     Default,
     Custom(&'a mut dyn codebase::FileSystem),
 }
 
-impl<'a> Default for InputConfig<'a> {
-    fn default() -> Self {
-        InputConfig::Default
-    }
-}
-
+#[derive(Default)]
 pub enum DiagnosticsConfig<'a> {
+    #[default]
     Ignore,
     Output(&'a mut dyn FnMut(Diagnostic)),
 }
 
-impl<'a> Default for DiagnosticsConfig<'a> {
-    fn default() -> Self {
-        DiagnosticsConfig::Ignore
-    }
+/// Destination of the text emitted by `PRINT` and `PRINTLN` during assembly.
+#[derive(Default)]
+pub enum PrintConfig<'a> {
+    #[default]
+    Stdout,
+    Custom(&'a mut dyn FnMut(&str)),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -0,0 +1,233 @@
+use crate::expr::{Atom, ExprOp};
+use crate::object::*;
+
+impl<I: Clone, S: Clone> Content<I, S> {
+    // Removes the sections that cannot be reached from a root and returns their names. Roots are
+    // sections with a fixed address, sections marked `KEEP`, sections without an exported name,
+    // and sections referenced by assertions.
+    pub(super) fn discard_unreachable_sections(&mut self) -> Vec<I> {
+        let live = self.live_sections();
+        let mut mapping = Vec::with_capacity(self.sections.len());
+        let mut sections = Vec::new();
+        for (section, is_live) in std::mem::take(&mut self.sections).into_iter().zip(live) {
+            mapping.push(if is_live {
+                sections.push(section);
+                Some(sections.len() - 1)
+            } else {
+                None
+            })
+        }
+        self.sections = sections;
+        let mut discarded = Vec::new();
+        for symbol in &mut self.symbols {
+            let (ident, def) = match symbol {
+                Symbol::Exported { ident, def } | Symbol::Weak { ident, def } => (Some(ident), def),
+                Symbol::Local { def } => (None, def),
+                Symbol::Unknown { .. } => continue,
+            };
+            if let SymbolMeaning::Section(SectionId(id)) = &mut def.meaning {
+                match (mapping[*id], ident) {
+                    (Some(new_id), _) => *id = new_id,
+                    (None, Some(ident)) => {
+                        discarded.push((*id, ident.clone()));
+                        *symbol = Symbol::Unknown {
+                            ident: ident.clone(),
+                        }
+                    }
+                    (None, None) => unreachable!(),
+                }
+            }
+        }
+        discarded.sort_by_key(|(id, _)| *id);
+        discarded.into_iter().map(|(_, ident)| ident).collect()
+    }
+
+    fn live_sections(&self) -> Vec<bool> {
+        let mut owners = vec![None; self.vars];
+        let mut is_named = vec![false; self.sections.len()];
+        for (index, section) in self.sections.iter().enumerate() {
+            for fragment in &section.fragments {
+                if let Fragment::Reloc(VarId(id)) = fragment {
+                    owners[*id] = Some(index)
+                }
+            }
+        }
+        for symbol in &self.symbols {
+            if let Symbol::Exported { def, .. } | Symbol::Weak { def, .. } = symbol {
+                if let SymbolMeaning::Section(SectionId(id)) = def.meaning {
+                    is_named[id] = true
+                }
+            }
+        }
+        let mut symbols = vec![false; self.symbols.len()];
+        let mut worklist: Vec<_> = self
+            .sections
+            .iter()
+            .enumerate()
+            .filter(|(index, section)| {
+                let constraints = &section.constraints;
+                constraints.addr.is_some() || constraints.keep || !is_named[*index]
+            })
+            .map(|(index, _)| index)
+            .collect();
+        for assertion in &self.assertions {
+            self.collect_closure_sections(
+                &assertion.condition,
+                &owners,
+                &mut symbols,
+                &mut worklist,
+            )
+        }
+        let mut live = vec![false; self.sections.len()];
+        while let Some(index) = worklist.pop() {
+            if std::mem::replace(&mut live[index], true) {
+                continue;
+            }
+            let section = &self.sections[index];
+            if index > 0 && section.follows_previous() {
+                worklist.push(index - 1)
+            }
+            let exprs = section
+                .constraints
+                .addr
+                .iter()
+                .chain(
+                    section
+                        .fragments
+                        .iter()
+                        .filter_map(|fragment| match fragment {
+                            Fragment::Immediate(expr, _)
+                            | Fragment::Jump(_, expr)
+                            | Fragment::LdInlineAddr(_, expr)
+                            | Fragment::Embedded(_, expr)
+                            | Fragment::Relative(expr)
+                            | Fragment::Reserved(expr)
+                            | Fragment::Load(expr) => Some(expr),
+                            _ => None,
+                        }),
+                );
+            for expr in exprs {
+                self.collect_reached_sections(expr, &owners, &mut symbols, &mut worklist)
+            }
+        }
+        live
+    }
+
+    fn collect_reached_sections(
+        &self,
+        expr: &Expr<S>,
+        owners: &[Option<usize>],
+        symbols: &mut [bool],
+        reached: &mut Vec<usize>,
+    ) {
+        for item in &expr.0 {
+            let id = match item.item {
+                ExprOp::Atom(Atom::Name(Name::Symbol(SymbolId(id)))) => id,
+                _ => continue,
+            };
+            if std::mem::replace(&mut symbols[id], true) {
+                continue;
+            }
+            let def = match &self.symbols[id] {
+                Symbol::Exported { def, .. } | Symbol::Weak { def, .. } | Symbol::Local { def } => {
+                    def
+                }
+                Symbol::Unknown { .. } => continue,
+            };
+            match &def.meaning {
+                SymbolMeaning::Closure(closure) => {
+                    self.collect_closure_sections(closure, owners, symbols, reached)
+                }
+                SymbolMeaning::Section(SectionId(section)) => reached.push(*section),
+            }
+        }
+    }
+
+    fn collect_closure_sections(
+        &self,
+        closure: &Closure<S>,
+        owners: &[Option<usize>],
+        symbols: &mut [bool],
+        reached: &mut Vec<usize>,
+    ) {
+        // A label keeps its section alive, a constant doesn't.
+        if closure
+            .expr
+            .0
+            .iter()
+            .any(|item| item.item == ExprOp::Atom(Atom::Location))
+        {
+            reached.extend(owners[closure.location.0])
+        }
+        self.collect_reached_sections(&closure.expr, owners, symbols, reached)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assertion_keeps_referenced_section() {
+        let mut content = Content {
+            assertions: vec![Assertion {
+                condition: Closure {
+                    expr: Expr::from_atom(Atom::Name(Name::Symbol(SymbolId(2))), ()),
+                    location: VarId(4),
+                    params: 0,
+                },
+                message: None,
+                span: (),
+            }],
+            sections: vec![mk_section(0), mk_section(2)],
+            symbols: vec![
+                mk_section_symbol("unused", 0),
+                mk_section_symbol("used", 1),
+                Symbol::Exported {
+                    ident: "label",
+                    def: SymbolDefRecord {
+                        def_ident_span: (),
+                        meaning: SymbolMeaning::Closure(Closure {
+                            expr: Expr::from_atom(Atom::Location, ()),
+                            location: VarId(5),
+                            params: 0,
+                        }),
+                    },
+                },
+            ],
+            vars: 6,
+        };
+        content.sections[1]
+            .fragments
+            .push(Fragment::Reloc(VarId(5)));
+        assert_eq!(content.discard_unreachable_sections(), ["unused"]);
+        assert_eq!(
+            content
+                .sections()
+                .map(|section| section.addr)
+                .collect::<Vec<_>>(),
+            [VarId(2)]
+        );
+        assert_eq!(content.symbols[0], Symbol::Unknown { ident: "unused" });
+        assert_eq!(content.symbols[1], mk_section_symbol("used", 0))
+    }
+
+    fn mk_section(first_var: usize) -> Section<()> {
+        Section {
+            constraints: Constraints::default(),
+            addr: VarId(first_var),
+            size: VarId(first_var + 1),
+            fragments: vec![],
+        }
+    }
+
+    fn mk_section_symbol(ident: &'static str, section: usize) -> Symbol<&'static str, ()> {
+        Symbol::Exported {
+            ident,
+            def: SymbolDefRecord {
+                def_ident_span: (),
+                meaning: SymbolMeaning::Section(SectionId(section)),
+            },
+        }
+    }
+}
@@ -1,10 +1,11 @@
 use super::Session;
 
+use crate::diagnostics::{Diagnostics, DiagnosticsContext, EmitDiag, Message};
 use crate::expr::{Atom, ExprOp};
 use crate::object::*;
 #[cfg(test)]
 use crate::span::fake::FakeSpanSystem;
-use crate::span::SpanSource;
+use crate::span::{SpanSource, StripSpan};
 
 use std::hash::Hash;
 #[cfg(test)]
@@ -16,31 +17,51 @@ impl<'a, D, M: Default + SpanSource, I: Clone + Eq + Hash> Session<'a, D, M, I>
         N: SpanSource<Span = M::Span>,
         Self: ImportMetadata<N>,
         <Self as ImportMetadata<N>>::SpanPatcher: PatchSpan<M::Span>,
+        for<'r> DiagnosticsContext<'r, 'a, M, D>: Diagnostics<M::Span>,
     {
         let patcher = self.import_metadata(object.metadata);
         self.import_content(object.content, patcher);
     }
 
-    fn import_content<P: PatchSpan<M::Span>>(&mut self, content: Content<I, M::Span>, patcher: P) {
+    fn import_content<P: PatchSpan<M::Span>>(&mut self, content: Content<I, M::Span>, patcher: P)
+    where
+        for<'r> DiagnosticsContext<'r, 'a, M, D>: Diagnostics<M::Span>,
+    {
         // Import symbol identifiers and create symbol mapping
         let mut symbol_mapping = Vec::with_capacity(content.symbols.len());
         let mut symbol_defined_in_object = Vec::with_capacity(content.symbols.len());
+        let mut duplicates = Vec::new();
         for symbol in content.symbols {
             let global_id = self.content.symbols.len();
             match &symbol {
-                Symbol::Exported { ident, .. } => {
+                Symbol::Exported { ident, .. } | Symbol::Weak { ident, .. } => {
                     match self.idents.get(ident) {
                         Some(SymbolId(id)) => {
-                            self.content.symbols[*id] = symbol;
-                            symbol_mapping.push(*id)
+                            let id = *id;
+                            let is_defined = match (&self.content.symbols[id], &symbol) {
+                                (Symbol::Exported { .. }, Symbol::Exported { def, .. }) => {
+                                    duplicates.push(def.def_ident_span.clone());
+                                    false
+                                }
+                                (
+                                    Symbol::Exported { .. } | Symbol::Weak { .. },
+                                    Symbol::Weak { .. },
+                                ) => false,
+                                _ => true,
+                            };
+                            if is_defined {
+                                self.content.symbols[id] = symbol
+                            }
+                            symbol_mapping.push(id);
+                            symbol_defined_in_object.push(is_defined)
                         }
                         None => {
                             self.idents.insert(ident.clone(), SymbolId(global_id));
                             symbol_mapping.push(global_id);
-                            self.content.symbols.push(symbol)
+                            self.content.symbols.push(symbol);
+                            symbol_defined_in_object.push(true)
                         }
                     }
-                    symbol_defined_in_object.push(true)
                 }
                 Symbol::Local { .. } => {
                     symbol_mapping.push(global_id);
@@ -48,7 +69,7 @@ impl<'a, D, M: Default + SpanSource, I: Clone + Eq + Hash> Session<'a, D, M, I>
                     symbol_defined_in_object.push(true)
                 }
                 Symbol::Unknown { ident } => {
-                    match self.idents.get(&ident) {
+                    match self.idents.get(ident) {
                         Some(id) => symbol_mapping.push(id.0),
                         None => {
                             self.idents.insert(ident.clone(), SymbolId(global_id));
@@ -70,6 +91,13 @@ impl<'a, D, M: Default + SpanSource, I: Clone + Eq + Hash> Session<'a, D, M, I>
             self.content.sections.push(section)
         }
 
+        // Import assertions
+        self.content.assertions.reserve(content.assertions.len());
+        for mut assertion in content.assertions {
+            patcher.patch_assertion(&mut assertion);
+            self.content.assertions.push(assertion)
+        }
+
         // Import symbols
         for (local_id, global_id) in patcher.symbol_mapping.iter().cloned().enumerate() {
             if symbol_defined_in_object[local_id] {
@@ -79,6 +107,18 @@ impl<'a, D, M: Default + SpanSource, I: Clone + Eq + Hash> Session<'a, D, M, I>
 
         // Import variables
         self.content.vars += content.vars;
+
+        // Report strong symbols already defined by another object
+        let mut diagnostics = DiagnosticsContext {
+            codebase: &mut self.codebase,
+            registry: &mut self.metadata,
+            diagnostics: &mut self.diagnostics,
+        };
+        for mut span in duplicates {
+            patcher.span_patcher.patch_span(&mut span);
+            let name = diagnostics.strip_span(&span);
+            diagnostics.emit_diag(Message::DuplicateSymbol { name }.at(span))
+        }
     }
 
     fn content_patcher<P>(
@@ -116,6 +156,7 @@ impl<'a, D, I> ImportMetadata<Metadata> for Session<'a, D, SpanData, I> {
         // Import source files
         for path in Vec::from(metadata.source_files) {
             self.codebase.open(&path).unwrap();
+            self.source_file_count += 1
         }
 
         let patcher = SpanPatcher {
@@ -126,10 +167,9 @@ impl<'a, D, I> ImportMetadata<Metadata> for Session<'a, D, SpanData, I> {
         // Import source file inclusions
         for mut source_file_inclusion in metadata.span_data.source_file_inclusions {
             source_file_inclusion.file.0 += base_source_file;
-            source_file_inclusion
-                .from
-                .as_mut()
-                .map(|span| patcher.patch_span(span));
+            if let Some(span) = source_file_inclusion.from.as_mut() {
+                patcher.patch_span(span)
+            }
             self.metadata
                 .source_file_inclusions
                 .push(source_file_inclusion)
@@ -191,31 +231,45 @@ impl<P> ContentPatcher<P> {
     where
         P: PatchSpan<S>,
     {
-        section
-            .constraints
-            .addr
-            .as_mut()
-            .map(|expr| self.patch_expr(expr));
+        if let Some(expr) = section.constraints.addr.as_mut() {
+            self.patch_expr(expr)
+        }
         self.patch_var(&mut section.addr);
         self.patch_var(&mut section.size);
         for fragment in &mut section.fragments {
             match fragment {
                 Fragment::Immediate(expr, _)
+                | Fragment::Jump(_, expr)
                 | Fragment::Embedded(_, expr)
                 | Fragment::LdInlineAddr(_, expr)
-                | Fragment::Reserved(expr) => self.patch_expr(expr),
+                | Fragment::Relative(expr)
+                | Fragment::Reserved(expr)
+                | Fragment::Load(expr) => self.patch_expr(expr),
                 Fragment::Reloc(var) => self.patch_var(var),
-                Fragment::Byte(_) => (),
+                Fragment::Byte(_)
+                | Fragment::EndLoad
+                | Fragment::Union
+                | Fragment::NextUnion
+                | Fragment::EndUnion => (),
             }
         }
     }
 
+    fn patch_assertion<S>(&self, assertion: &mut Assertion<S>)
+    where
+        P: PatchSpan<S>,
+    {
+        self.patch_closure(&mut assertion.condition);
+        self.span_patcher.patch_span(&mut assertion.span)
+    }
+
     fn patch_symbol<I, S>(&self, symbol: &mut Symbol<I, S>)
     where
         P: PatchSpan<S>,
     {
         match symbol {
             Symbol::Exported { def, .. } => self.patch_symbol_def(def),
+            Symbol::Weak { def, .. } => self.patch_symbol_def(def),
             Symbol::Local { def } => self.patch_symbol_def(def),
             Symbol::Unknown { .. } => (),
         }
@@ -227,24 +281,26 @@ impl<P> ContentPatcher<P> {
     {
         self.span_patcher.patch_span(&mut symbol_def.def_ident_span);
         match &mut symbol_def.meaning {
-            SymbolMeaning::Closure(closure) => {
-                self.patch_expr(&mut closure.expr);
-                self.patch_var(&mut closure.location)
-            }
+            SymbolMeaning::Closure(closure) => self.patch_closure(closure),
             SymbolMeaning::Section(section) => section.0 += self.base_section,
         }
     }
 
+    fn patch_closure<S>(&self, closure: &mut Closure<S>)
+    where
+        P: PatchSpan<S>,
+    {
+        self.patch_expr(&mut closure.expr);
+        self.patch_var(&mut closure.location)
+    }
+
     fn patch_expr<S>(&self, expr: &mut Expr<S>)
     where
         P: PatchSpan<S>,
     {
         for node in &mut expr.0 {
-            match &mut node.item {
-                ExprOp::Atom(Atom::Name(Name::Symbol(SymbolId(id)))) => {
-                    *id = self.symbol_mapping[*id]
-                }
-                _ => (),
+            if let ExprOp::Atom(Atom::Name(Name::Symbol(SymbolId(id)))) = &mut node.item {
+                *id = self.symbol_mapping[*id]
             }
             self.span_patcher.patch_span(&mut node.span)
         }
@@ -292,7 +348,7 @@ mod tests {
     use super::*;
 
     use crate::codebase::fake::MockFileSystem;
-    use crate::diagnostics::IgnoreDiagnostics;
+    use crate::diagnostics::{IgnoreDiagnostics, TestDiagnosticsListener};
     use crate::expr::Atom;
 
     #[test]
@@ -302,8 +358,9 @@ mod tests {
         // DW   my_symbol
         let object1 = ObjectData {
             content: Content {
+                assertions: vec![],
                 sections: vec![Section {
-                    constraints: Constraints { addr: None },
+                    constraints: Constraints::default(),
                     addr: VarId(0),
                     size: VarId(1),
                     fragments: vec![Fragment::Immediate(
@@ -320,8 +377,9 @@ mod tests {
         // my_symbol
         let object2 = ObjectData {
             content: Content {
+                assertions: vec![],
                 sections: vec![Section {
-                    constraints: Constraints { addr: None },
+                    constraints: Constraints::default(),
                     addr: VarId(0),
                     size: VarId(1),
                     fragments: vec![Fragment::Reloc(VarId(2))],
@@ -333,6 +391,7 @@ mod tests {
                         meaning: SymbolMeaning::Closure(Closure {
                             expr: Expr::from_atom(Atom::Location, ()),
                             location: VarId(2),
+                            params: 0,
                         }),
                     },
                 }],
@@ -354,6 +413,7 @@ mod tests {
                     meaning: SymbolMeaning::Closure(Closure {
                         expr: Expr::from_atom(Atom::Location, ()),
                         location: VarId(4),
+                        params: 0,
                     }),
                 }
             }]
@@ -367,8 +427,9 @@ mod tests {
         // my_symbol
         let object1 = ObjectData {
             content: Content {
+                assertions: vec![],
                 sections: vec![Section {
-                    constraints: Constraints { addr: None },
+                    constraints: Constraints::default(),
                     addr: VarId(0),
                     size: VarId(1),
                     fragments: vec![Fragment::Reloc(VarId(2))],
@@ -380,6 +441,7 @@ mod tests {
                         meaning: SymbolMeaning::Closure(Closure {
                             expr: Expr::from_atom(Atom::Location, ()),
                             location: VarId(2),
+                            params: 0,
                         }),
                     },
                 }],
@@ -391,8 +453,9 @@ mod tests {
         // DW   my_symbol
         let object2 = ObjectData {
             content: Content {
+                assertions: vec![],
                 sections: vec![Section {
-                    constraints: Constraints { addr: None },
+                    constraints: Constraints::default(),
                     addr: VarId(0),
                     size: VarId(1),
                     fragments: vec![Fragment::Immediate(
@@ -419,9 +482,95 @@ mod tests {
                     meaning: SymbolMeaning::Closure(Closure {
                         expr: Expr::from_atom(Atom::Location, ()),
                         location: VarId(2),
+                        params: 0,
                     }),
                 }
             }]
         )
     }
+
+    #[test]
+    fn strong_symbol_overrides_weak_symbol() {
+        let ident = "my_symbol";
+        let fs = &mut MockFileSystem::new();
+        let mut session = Session::<_, FakeSpanSystem<_>, _>::new(fs, IgnoreDiagnostics);
+        session.import_object(object_defining(|def| Symbol::Weak { ident, def }));
+        session.import_object(object_defining(|def| Symbol::Exported { ident, def }));
+        assert_eq!(
+            session.content.symbols,
+            vec![Symbol::Exported {
+                ident,
+                def: location_def(VarId(5)),
+            }]
+        )
+    }
+
+    #[test]
+    fn weak_symbol_does_not_override_strong_symbol() {
+        let ident = "my_symbol";
+        let fs = &mut MockFileSystem::new();
+        let mut session = Session::<_, FakeSpanSystem<_>, _>::new(fs, IgnoreDiagnostics);
+        session.import_object(object_defining(|def| Symbol::Exported { ident, def }));
+        session.import_object(object_defining(|def| Symbol::Weak { ident, def }));
+        assert_eq!(
+            session.content.symbols,
+            vec![Symbol::Exported {
+                ident,
+                def: location_def(VarId(2)),
+            }]
+        )
+    }
+
+    #[test]
+    fn diagnose_duplicate_strong_symbol() {
+        let ident = "my_symbol";
+        let listener = TestDiagnosticsListener::new();
+        let diagnostics = listener.diagnostics.clone();
+        let fs = &mut MockFileSystem::new();
+        let mut session = Session::<_, FakeSpanSystem<_>, _>::new(fs, listener);
+        session.import_object(object_defining(|def| Symbol::Exported { ident, def }));
+        session.import_object(object_defining(|def| Symbol::Exported { ident, def }));
+        assert_eq!(
+            session.content.symbols,
+            vec![Symbol::Exported {
+                ident,
+                def: location_def(VarId(2)),
+            }]
+        );
+        drop(session);
+        assert_eq!(
+            *diagnostics.into_inner(),
+            [Message::DuplicateSymbol { name: () }.at(()).into()]
+        )
+    }
+
+    fn object_defining(
+        symbol: impl FnOnce(SymbolDefRecord<()>) -> Symbol<&'static str, ()>,
+    ) -> ObjectData<FakeMetadata<()>, &'static str> {
+        ObjectData {
+            content: Content {
+                assertions: vec![],
+                sections: vec![Section {
+                    constraints: Constraints::default(),
+                    addr: VarId(0),
+                    size: VarId(1),
+                    fragments: vec![Fragment::Reloc(VarId(2))],
+                }],
+                symbols: vec![symbol(location_def(VarId(2)))],
+                vars: 3,
+            },
+            metadata: FakeMetadata::new(),
+        }
+    }
+
+    fn location_def(location: VarId) -> SymbolDefRecord<()> {
+        SymbolDefRecord {
+            def_ident_span: (),
+            meaning: SymbolMeaning::Closure(Closure {
+                expr: Expr::from_atom(Atom::Location, ()),
+                location,
+                params: 0,
+            }),
+        }
+    }
 }
@@ -1,30 +1,59 @@
 use crate::codebase::{Codebase, FileSystem, StdFileSystem};
 use crate::diagnostics::*;
+use crate::expr::{Atom, ExprOp};
 use crate::object::var::Var;
 use crate::object::*;
 use crate::program::Program;
-use crate::span::SpanSource;
+use crate::span::{Source, SpanSource};
 use crate::{Config, DiagnosticsConfig, InputConfig};
 
 use std::borrow::Borrow;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
+mod gc;
 mod import;
+mod place;
 mod translate;
 
-pub struct Linker<'a> {
-    config: &'a mut Config<'a>,
+pub struct Linker<'r, 'a> {
+    config: &'r mut Config<'a>,
+    gc_sections: bool,
 }
 
-impl<'a> Linker<'a> {
-    pub fn new(config: &'a mut Config<'a>) -> Self {
-        Self { config }
+impl<'r, 'a> Linker<'r, 'a> {
+    pub fn new(config: &'r mut Config<'a>) -> Self {
+        Self {
+            config,
+            gc_sections: false,
+        }
+    }
+
+    /// Discards the sections that are not reachable from a fixed-address section, a section
+    /// marked `KEEP`, or an unnamed section. Their names are listed in [`Program::discarded`].
+    pub fn gc_sections(mut self, enabled: bool) -> Self {
+        self.gc_sections = enabled;
+        self
     }
 
     pub fn link<I>(&mut self, objects: I) -> Option<Program>
     where
         I: IntoIterator<Item = Object>,
     {
+        let gc_sections = self.gc_sections;
+        self.run(|input, diagnostics| try_link(objects, gc_sections, input, diagnostics))
+    }
+
+    pub fn combine<I>(&mut self, objects: I) -> Object
+    where
+        I: IntoIterator<Item = Object>,
+    {
+        self.run(|input, diagnostics| combine(objects, input, diagnostics))
+    }
+
+    fn run<T>(
+        &mut self,
+        f: impl FnOnce(&mut dyn FileSystem, &mut dyn FnMut(Diagnostic)) -> T,
+    ) -> T {
         let mut input_holder = None;
         let mut diagnostics_holder = None;
         let input: &mut dyn FileSystem = match &mut self.config.input {
@@ -35,12 +64,13 @@ impl<'a> Linker<'a> {
             DiagnosticsConfig::Ignore => diagnostics_holder.get_or_insert(|_| {}),
             DiagnosticsConfig::Output(diagnostics) => *diagnostics,
         };
-        try_link(objects, input, diagnostics)
+        f(input, diagnostics)
     }
 }
 
 fn try_link<I>(
     objects: I,
+    gc_sections: bool,
     input: &mut dyn FileSystem,
     diagnostics: &mut dyn FnMut(Diagnostic),
 ) -> Option<Program>
@@ -56,7 +86,36 @@ where
     for Object(data) in objects {
         session.import_object(data)
     }
-    Some(session.link())
+    let discarded = if gc_sections {
+        session.content.discard_unreachable_sections()
+    } else {
+        Vec::new()
+    };
+    let program = session.link()?;
+    Some(Program {
+        discarded: discarded.into(),
+        ..program
+    })
+}
+
+fn combine<I>(
+    objects: I,
+    input: &mut dyn FileSystem,
+    diagnostics: &mut dyn FnMut(Diagnostic),
+) -> Object
+where
+    I: IntoIterator<Item = Object>,
+{
+    let mut session = Session::<_, SpanData, _>::new(
+        input,
+        OutputForwarder {
+            output: diagnostics,
+        },
+    );
+    for Object(data) in objects {
+        session.import_object(data)
+    }
+    session.into_object()
 }
 
 struct Session<'a, D, M: SpanSource, I> {
@@ -80,101 +139,426 @@ impl<'a, D, M: Default + SpanSource, I> Session<'a, D, M, I> {
         }
     }
 
-    fn link(mut self) -> Program
+    fn link(mut self) -> Option<Program>
     where
         for<'r> DiagnosticsContext<'r, 'a, M, D>: Diagnostics<M::Span>,
     {
-        let mut vars = VarTable(vec![Var::Unknown; self.content.vars]);
-        vars.resolve(&self.content);
-        let mut context = LinkageContext {
-            content: &self.content,
-            vars: &vars,
-            location: 0.into(),
-        };
         let mut diagnostics = DiagnosticsContext {
             codebase: &mut self.codebase,
             registry: &mut self.metadata,
             diagnostics: &mut self.diagnostics,
         };
-        Program {
+        let vars = self.content.resolve_layout(&mut diagnostics);
+        let mut context = LinkageContext {
+            content: &self.content,
+            vars: &vars,
+            location: 0.into(),
+        };
+        let mut is_resolved = true;
+        for section in self.content.sections() {
+            is_resolved &= section.check_sizes(&mut context, &mut diagnostics)
+        }
+        if !is_resolved {
+            return None;
+        }
+        for (index, section) in self.content.sections().enumerate() {
+            section.check_constraints(&context, &mut diagnostics);
+            if let Some(kind) = section.constraints.kind.filter(|kind| kind.is_ram()) {
+                let span = self.content.section_name_span(SectionId(index));
+                if let (true, Some(span)) = (section.fragments.iter().any(Fragment::is_data), span)
+                {
+                    diagnostics.emit_diag(Message::DataInRamSection { kind }.at(span.clone()))
+                }
+            }
+        }
+        self.content.check_overlaps(&vars, &mut diagnostics);
+        for assertion in &self.content.assertions {
+            assertion.check(&context, &mut diagnostics)
+        }
+        let mut reserved = Vec::new();
+        for section in self.content.sections() {
+            reserved.extend(section.reserved_rom(&mut context))
+        }
+        Some(Program {
             sections: self
                 .content
                 .sections()
                 .flat_map(|section| section.translate(&mut context, &mut diagnostics))
                 .collect(),
-        }
+            reserved: reserved.into(),
+            discarded: Box::new([]),
+        })
+    }
+}
+
+impl<'a, D> Session<'a, D, SpanData, Box<str>> {
+    fn into_object(self) -> Object {
+        Object(ObjectData {
+            content: self.content,
+            metadata: Metadata {
+                source_files: self.codebase.export_source_file_table(),
+                span_data: self.metadata,
+            },
+        })
     }
 }
 
 impl VarTable {
     fn resolve<I, S: Clone>(&mut self, content: &Content<I, S>) {
-        self.refine_all(content);
-        self.refine_all(content);
+        let sections = &content.sections;
+        let dependents = content.dependents();
+        let mut ends = vec![Var::Unknown; sections.len()];
+        let mut worklist: VecDeque<_> = (0..sections.len()).collect();
+        let mut is_queued = vec![true; sections.len()];
+        let mut refined = Vec::new();
+        self.memoize_closures(true);
+        while let Some(index) = worklist.pop_front() {
+            is_queued[index] = false;
+            let start = index
+                .checked_sub(1)
+                .map_or(Var::Unknown, |prev| ends[prev].clone());
+            let end = self.refine_section(&sections[index], start, content, &mut refined);
+            let mut affected = Vec::new();
+            if end != ends[index] {
+                ends[index] = end;
+                affected.extend(
+                    Some(index + 1)
+                        .filter(|&next| next < sections.len() && sections[next].follows_previous()),
+                )
+            }
+            for VarId(id) in refined.drain(..) {
+                affected.extend(&dependents[id])
+            }
+            for dependent in affected {
+                if !is_queued[dependent] {
+                    is_queued[dependent] = true;
+                    worklist.push_back(dependent)
+                }
+            }
+        }
+        self.memoize_closures(false)
     }
 
-    fn refine_all<I, S: Clone>(&mut self, content: &Content<I, S>) -> i32 {
-        let mut refinements = 0;
+    fn refine_section<I, S: Clone>(
+        &mut self,
+        section: &Section<S>,
+        start: Var,
+        content: &Content<I, S>,
+        refined: &mut Vec<VarId>,
+    ) -> Var {
         let context = &mut LinkageContext {
             content,
             vars: self,
-            location: Var::Unknown,
+            location: start,
         };
-        for section in content.sections() {
-            context.location = section.eval_addr(context);
-            context.vars[section.addr].refine(context.location.clone());
-            let size = section.traverse(context, |item, context| {
-                if let Fragment::Reloc(id) = item {
-                    refinements += context.vars[*id].refine(context.location.clone()) as i32
+        context.location = section.eval_addr(context);
+        if context.vars.refine(section.addr, context.location.clone()) {
+            refined.push(section.addr)
+        }
+        let size = section.traverse(context, |item, context, _| {
+            if let Fragment::Reloc(id) = item {
+                if context.vars.refine(*id, context.location.clone()) {
+                    refined.push(*id)
                 }
-            });
-            refinements += context.vars[section.size].refine(size) as i32
+            }
+        });
+        if context.vars.refine(section.size, size) {
+            refined.push(section.size)
+        }
+        context.location.clone()
+    }
+}
+
+impl<I, S: Clone> Content<I, S> {
+    fn section_name_span(&self, section: SectionId) -> Option<&S> {
+        self.symbols.iter().find_map(|symbol| match symbol {
+            Symbol::Exported { def, .. } | Symbol::Weak { def, .. } | Symbol::Local { def } => {
+                match def.meaning {
+                    SymbolMeaning::Section(id) if id == section => Some(&def.def_ident_span),
+                    _ => None,
+                }
+            }
+            Symbol::Unknown { .. } => None,
+        })
+    }
+
+    // For every var, the sections whose layout has to be recomputed when it is refined.
+    fn dependents(&self) -> Vec<Vec<usize>> {
+        let mut dependents = vec![Vec::new(); self.vars];
+        for (index, section) in self.sections.iter().enumerate() {
+            let mut vars = vec![false; self.vars];
+            let mut symbols = vec![false; self.symbols.len()];
+            let exprs = section
+                .constraints
+                .addr
+                .iter()
+                .chain(
+                    section
+                        .fragments
+                        .iter()
+                        .filter_map(|fragment| match fragment {
+                            Fragment::Jump(_, expr)
+                            | Fragment::LdInlineAddr(_, expr)
+                            | Fragment::Load(expr)
+                            | Fragment::Reserved(expr) => Some(expr),
+                            _ => None,
+                        }),
+                );
+            for expr in exprs {
+                self.collect_dependencies(expr, &mut vars, &mut symbols)
+            }
+            for (id, _) in vars.iter().enumerate().filter(|(_, &is_used)| is_used) {
+                dependents[id].push(index)
+            }
+        }
+        dependents
+    }
+
+    fn collect_dependencies(&self, expr: &Expr<S>, vars: &mut [bool], symbols: &mut [bool]) {
+        for item in &expr.0 {
+            let id = match item.item {
+                ExprOp::Atom(Atom::Name(Name::Symbol(SymbolId(id)))) => id,
+                _ => continue,
+            };
+            if std::mem::replace(&mut symbols[id], true) {
+                continue;
+            }
+            if let Symbol::Exported { def, .. } | Symbol::Weak { def, .. } | Symbol::Local { def } =
+                &self.symbols[id]
+            {
+                match &def.meaning {
+                    SymbolMeaning::Closure(closure) => {
+                        vars[closure.location.0] = true;
+                        self.collect_dependencies(&closure.expr, vars, symbols)
+                    }
+                    SymbolMeaning::Section(SectionId(section)) => {
+                        let section = &self.sections[*section];
+                        vars[section.addr.0] = true;
+                        vars[section.size.0] = true
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<S: Clone> Assertion<S> {
+    fn check<I>(
+        &self,
+        context: &LinkageContext<&Content<I, S>, &VarTable>,
+        diagnostics: &mut impl BackendDiagnostics<S>,
+    ) {
+        match self.condition.to_num(context, diagnostics).exact() {
+            Some(0) => {
+                let message = self.message.clone();
+                diagnostics.emit_diag(Message::AssertionFailed { message }.at(self.span.clone()))
+            }
+            Some(_) => (),
+            None => diagnostics.emit_diag(Message::AssertionNotConst.at(self.span.clone())),
         }
-        refinements
     }
 }
 
 impl<S: Clone> Section<S> {
+    fn check_constraints<I>(
+        &self,
+        context: &LinkageContext<&Content<I, S>, &VarTable>,
+        diagnostics: &mut impl BackendDiagnostics<S>,
+    ) {
+        let expr = match &self.constraints.addr {
+            Some(expr) => expr,
+            None => return,
+        };
+        let addr = match context.vars[self.addr].exact() {
+            Some(addr) => addr,
+            None => return,
+        };
+        if let Some(kind) = self.constraints.kind {
+            if !kind.addr_range().contains(&addr) {
+                diagnostics.emit_diag(Message::AddrOutsideRegion { addr, kind }.at(expr.span()))
+            }
+        }
+        if let Some(align) = self.constraints.align {
+            if addr & ((1 << align) - 1) != 0 {
+                diagnostics.emit_diag(Message::MisalignedAddr { addr, align }.at(expr.span()))
+            }
+        }
+    }
+
+    fn follows_previous(&self) -> bool {
+        self.constraints.addr.iter().any(|expr| {
+            expr.0
+                .iter()
+                .any(|item| item.item == ExprOp::Atom(Atom::Location))
+        })
+    }
+
+    fn check_sizes<I>(
+        &self,
+        context: &mut LinkageContext<&Content<I, S>, &VarTable>,
+        diagnostics: &mut impl BackendDiagnostics<S>,
+    ) -> bool {
+        let mut is_resolved = true;
+        context.location = context.vars[self.addr].clone();
+        let mut start = context.location.clone();
+        self.traverse(context, |fragment, context, _| {
+            let start = std::mem::replace(&mut start, context.location.clone());
+            if let Fragment::Jump(_, expr)
+            | Fragment::LdInlineAddr(_, expr)
+            | Fragment::Reserved(expr) = fragment
+            {
+                let context = &LinkageContext {
+                    content: context.content,
+                    vars: context.vars,
+                    location: start,
+                };
+                if fragment.size(context).exact().is_none() {
+                    diagnostics.emit_diag(Message::AmbiguousSize.at(expr.span()));
+                    is_resolved = false
+                }
+            }
+        });
+        is_resolved
+    }
+
+    fn ambiguous_jumps<I>(
+        &self,
+        context: &mut LinkageContext<&Content<I, S>, &VarTable>,
+    ) -> Vec<usize> {
+        let mut jumps = Vec::new();
+        let mut index = 0;
+        context.location = context.vars[self.addr].clone();
+        let mut start = context.location.clone();
+        self.traverse(context, |fragment, context, _| {
+            let start = std::mem::replace(&mut start, context.location.clone());
+            if let Fragment::Jump(..) = fragment {
+                let context = &LinkageContext {
+                    content: context.content,
+                    vars: context.vars,
+                    location: start,
+                };
+                if fragment.size(context).exact().is_none() {
+                    jumps.push(index)
+                }
+            }
+            index += 1
+        });
+        jumps
+    }
+
     fn traverse<V, F, I>(&self, context: &mut LinkageContext<&Content<I, S>, V>, mut f: F) -> Var
     where
         V: Borrow<VarTable>,
-        F: FnMut(&Fragment<Expr<S>>, &mut LinkageContext<&Content<I, S>, V>),
+        F: FnMut(&Fragment<Expr<S>>, &mut LinkageContext<&Content<I, S>, V>, &Var),
     {
         let addr = context.location.clone();
         let mut offset = Var::from(0);
+        let mut load = None;
+        let mut unions: Vec<UnionFrame> = Vec::new();
         for item in &self.fragments {
-            offset += &item.size(&context);
-            context.location = &addr + &offset;
-            f(item, context)
+            let size = item.size(context);
+            offset += &size;
+            if let Some(union) = unions.last_mut() {
+                union.alt += &size
+            }
+            match item {
+                Fragment::Load(expr) => {
+                    let load_addr = expr.to_num(context, &mut IgnoreDiagnostics);
+                    load = Some((load_addr, offset.clone()))
+                }
+                Fragment::EndLoad => load = None,
+                Fragment::Union => unions.push(UnionFrame {
+                    start: offset.clone(),
+                    size: 0.into(),
+                    alt: 0.into(),
+                }),
+                Fragment::NextUnion => {
+                    let union = unions.last_mut().unwrap();
+                    union.size = union.size.max(&union.alt);
+                    union.alt = 0.into();
+                    offset = union.start.clone()
+                }
+                Fragment::EndUnion => {
+                    let union = unions.pop().unwrap();
+                    let size = union.size.max(&union.alt);
+                    offset = &union.start + &size;
+                    if let Some(outer) = unions.last_mut() {
+                        outer.alt += &size
+                    }
+                }
+                _ => (),
+            }
+            let storage = &addr + &offset;
+            context.location = match &load {
+                Some((load_addr, start)) => load_addr + &(&offset - start),
+                None => storage.clone(),
+            };
+            f(item, context, &storage)
         }
         offset
     }
 
-    fn eval_addr<'a, V: Borrow<VarTable>, I>(
+    fn eval_addr<V: Borrow<VarTable>, I>(
         &self,
-        context: &LinkageContext<&'a Content<I, S>, V>,
+        context: &LinkageContext<&Content<I, S>, V>,
     ) -> Var {
         self.constraints
             .addr
             .as_ref()
             .map(|expr| expr.to_num(context, &mut IgnoreDiagnostics))
-            .unwrap_or_else(|| 0.into())
+            .unwrap_or_else(|| {
+                // A placed section has its address set before resolution.
+                let placed = context.vars.borrow()[self.addr].exact();
+                let kind = self.constraints.kind;
+                placed
+                    .unwrap_or_else(|| kind.map_or(0, |kind| *kind.addr_range().start()))
+                    .into()
+            })
     }
 }
 
+struct UnionFrame {
+    start: Var,
+    size: Var,
+    alt: Var,
+}
+
 impl<S: Clone> Fragment<Expr<S>> {
-    fn size<'a, V: Borrow<VarTable>, I>(
-        &self,
-        context: &LinkageContext<&'a Content<I, S>, V>,
-    ) -> Var {
+    fn is_data(&self) -> bool {
+        matches!(
+            self,
+            Fragment::Byte(_)
+                | Fragment::Immediate(..)
+                | Fragment::Jump(..)
+                | Fragment::LdInlineAddr(..)
+                | Fragment::Embedded(..)
+                | Fragment::Relative(_)
+        )
+    }
+
+    fn size<V: Borrow<VarTable>, I>(&self, context: &LinkageContext<&Content<I, S>, V>) -> Var {
         match self {
-            Fragment::Byte(_) | Fragment::Embedded(..) => 1.into(),
+            Fragment::Byte(_) | Fragment::Embedded(..) | Fragment::Relative(_) => 1.into(),
             Fragment::Immediate(_, width) => width.len().into(),
+            Fragment::Jump(_, target) => {
+                let end = &context.location + &2.into();
+                match &target.to_num(context, &mut IgnoreDiagnostics) - &end {
+                    Var::Range { min, max } if min >= -128 && max <= 127 => 2.into(),
+                    Var::Range { min, max } if max < -128 || min > 127 => 3.into(),
+                    _ => Var::Range { min: 2, max: 3 },
+                }
+            }
             Fragment::LdInlineAddr(_, expr) => match expr.to_num(context, &mut IgnoreDiagnostics) {
                 Var::Range { min, .. } if min >= 0xff00 => 2.into(),
                 Var::Range { max, .. } if max < 0xff00 => 3.into(),
                 _ => Var::Range { min: 2, max: 3 },
             },
-            Fragment::Reloc(_) => 0.into(),
+            Fragment::Reloc(_)
+            | Fragment::Load(_)
+            | Fragment::EndLoad
+            | Fragment::Union
+            | Fragment::NextUnion
+            | Fragment::EndUnion => 0.into(),
             Fragment::Reserved(bytes) => bytes.to_num(context, &mut IgnoreDiagnostics),
         }
     }
@@ -196,6 +580,7 @@ mod tests {
 
     use crate::codebase::fake::MockFileSystem;
     use crate::diagnostics::{IgnoreDiagnostics, Message, MockSpan, TestDiagnosticsListener};
+    use crate::eval::Const;
     use crate::expr::Expr;
     use crate::expr::*;
     use crate::span::fake::FakeSpanSystem;
@@ -205,8 +590,9 @@ mod tests {
     fn section_with_immediate_byte_fragment() {
         let object = ObjectData::<_, &str> {
             content: Content {
+                assertions: vec![],
                 sections: vec![Section {
-                    constraints: Constraints { addr: None },
+                    constraints: Constraints::default(),
                     addr: VarId(0),
                     size: VarId(1),
                     fragments: vec![Fragment::Immediate(
@@ -223,7 +609,7 @@ mod tests {
             let mut fs = MockFileSystem::new();
             let mut session = Session::<_, FakeSpanSystem<_>, _>::new(&mut fs, IgnoreDiagnostics);
             session.import_object(object);
-            session.link()
+            session.link().unwrap()
         };
         assert_eq!(*program.sections[0].data, [0xff])
     }
@@ -232,8 +618,9 @@ mod tests {
     fn section_with_two_immediate_byte_fragments() {
         let object = ObjectData::<_, &str> {
             content: Content {
+                assertions: vec![],
                 sections: vec![Section {
-                    constraints: Constraints { addr: None },
+                    constraints: Constraints::default(),
                     addr: VarId(0),
                     size: VarId(1),
                     fragments: vec![
@@ -250,31 +637,42 @@ mod tests {
             let mut fs = MockFileSystem::new();
             let mut session = Session::<_, FakeSpanSystem<_>, _>::new(&mut fs, IgnoreDiagnostics);
             session.import_object(object);
-            session.link()
+            session.link().unwrap()
         };
         assert_eq!(*program.sections[0].data, [0x12, 0x34])
     }
 
     #[test]
     fn diagnose_immediate_byte_fragment_less_than_negative_128() {
-        test_diagnostic_for_out_of_range_immediate_byte_fragment(-129)
+        test_diagnostic_for_out_of_range_immediate_fragment(-129, Width::Byte)
     }
 
     #[test]
     fn diagnose_immediate_byte_fragment_greater_than_255() {
-        test_diagnostic_for_out_of_range_immediate_byte_fragment(256)
+        test_diagnostic_for_out_of_range_immediate_fragment(256, Width::Byte)
     }
 
-    fn test_diagnostic_for_out_of_range_immediate_byte_fragment(value: i32) {
+    #[test]
+    fn diagnose_immediate_word_fragment_less_than_negative_32768() {
+        test_diagnostic_for_out_of_range_immediate_fragment(-32769, Width::Word)
+    }
+
+    #[test]
+    fn diagnose_immediate_word_fragment_greater_than_65535() {
+        test_diagnostic_for_out_of_range_immediate_fragment(65536, Width::Word)
+    }
+
+    fn test_diagnostic_for_out_of_range_immediate_fragment(value: i32, width: Width) {
         let object = ObjectData::<_, &str> {
             content: Content {
+                assertions: vec![],
                 sections: vec![Section {
-                    constraints: Constraints { addr: None },
+                    constraints: Constraints::default(),
                     addr: VarId(0),
                     size: VarId(1),
                     fragments: vec![Fragment::Immediate(
-                        Expr::from_atom(Atom::Const(value), MockSpan::from("byte")),
-                        Width::Byte,
+                        Expr::from_atom(Atom::Const(value), MockSpan::from("immediate")),
+                        width,
                     )],
                 }],
                 symbols: vec![],
@@ -290,12 +688,9 @@ mod tests {
         session.link();
         assert_eq!(
             *diagnostics.into_inner(),
-            [Message::ValueOutOfRange {
-                value,
-                width: Width::Byte,
-            }
-            .at(MockSpan::from("byte"))
-            .into()]
+            [Message::ValueOutOfRange { value, width }
+                .at(MockSpan::from("immediate"))
+                .into()]
         )
     }
 
@@ -304,8 +699,9 @@ mod tests {
         // DW   name
         let object = ObjectData {
             content: Content {
+                assertions: vec![],
                 sections: vec![Section {
-                    constraints: Constraints { addr: None },
+                    constraints: Constraints::default(),
                     addr: VarId(0),
                     size: VarId(1),
                     fragments: vec![Fragment::Immediate(
@@ -337,13 +733,98 @@ mod tests {
         )
     }
 
+    #[test]
+    fn diagnose_failed_assertion_after_resolution() {
+        //      NOP
+        //      ASSERT . == 0, "not at start"
+        let object = ObjectData {
+            content: Content {
+                assertions: vec![Assertion {
+                    condition: Closure {
+                        expr: Expr(vec![
+                            ExprOp::Atom(Atom::Location).with_span(MockSpan::from("dot")),
+                            ExprOp::Atom(Atom::Const(0)).with_span(MockSpan::from("zero")),
+                            ExprOp::Binary(BinOp::Equality).with_span(MockSpan::from("eq")),
+                        ]),
+                        location: VarId(2),
+                        params: 0,
+                    },
+                    message: Some("not at start".into()),
+                    span: MockSpan::from("assert"),
+                }],
+                sections: vec![Section {
+                    constraints: Constraints::default(),
+                    addr: VarId(0),
+                    size: VarId(1),
+                    fragments: vec![Fragment::Byte(0x00), Fragment::Reloc(VarId(2))],
+                }],
+                symbols: Vec::<Symbol<&str, _>>::new(),
+                vars: 3,
+            },
+            metadata: FakeMetadata::new(),
+        };
+        let listener = TestDiagnosticsListener::new();
+        let diagnostics = listener.diagnostics.clone();
+        let mut fs = MockFileSystem::new();
+        let mut session = Session::<_, FakeSpanSystem<_>, _>::new(&mut fs, listener);
+        session.import_object(object);
+        session.link();
+        assert_eq!(
+            *diagnostics.into_inner(),
+            [Message::AssertionFailed {
+                message: Some("not at start".into())
+            }
+            .at(MockSpan::from("assert"))
+            .into()]
+        )
+    }
+
+    #[test]
+    fn diagnose_assertion_without_location() {
+        //      ASSERT . == 0
+        let object = ObjectData {
+            content: Content {
+                assertions: vec![Assertion {
+                    condition: Closure {
+                        expr: Expr(vec![
+                            ExprOp::Atom(Atom::Location).with_span(MockSpan::from("dot")),
+                            ExprOp::Atom(Atom::Const(0)).with_span(MockSpan::from("zero")),
+                            ExprOp::Binary(BinOp::Equality).with_span(MockSpan::from("eq")),
+                        ]),
+                        location: VarId(0),
+                        params: 0,
+                    },
+                    message: None,
+                    span: MockSpan::from("assert"),
+                }],
+                sections: vec![],
+                symbols: Vec::<Symbol<&str, _>>::new(),
+                vars: 1,
+            },
+            metadata: FakeMetadata::new(),
+        };
+        let listener = TestDiagnosticsListener::new();
+        let diagnostics = listener.diagnostics.clone();
+        let mut fs = MockFileSystem::new();
+        let mut session = Session::<_, FakeSpanSystem<_>, _>::new(&mut fs, listener);
+        session.import_object(object);
+        session.link();
+        assert_eq!(
+            *diagnostics.into_inner(),
+            [Message::AssertionNotConst
+                .at(MockSpan::from("assert"))
+                .into()]
+        )
+    }
+
     #[test]
     fn diagnose_two_unresolved_symbols_in_one_expr() {
         // DW   name1 - name2
         let object = ObjectData {
             content: Content {
+                assertions: vec![],
                 sections: vec![Section {
-                    constraints: Constraints { addr: None },
+                    constraints: Constraints::default(),
                     addr: VarId(0),
                     size: VarId(1),
                     fragments: vec![Fragment::Immediate(
@@ -393,8 +874,9 @@ mod tests {
         // name DB  name
         let object = ObjectData {
             content: Content {
+                assertions: vec![],
                 sections: vec![Section {
-                    constraints: Constraints { addr: None },
+                    constraints: Constraints::default(),
                     addr: VarId(0),
                     size: VarId(1),
                     fragments: vec![
@@ -412,6 +894,7 @@ mod tests {
                         meaning: SymbolMeaning::Closure(Closure {
                             expr: Expr::from_atom(Atom::Location, ()),
                             location: VarId(2),
+                            params: 0,
                         }),
                     },
                 }],
@@ -423,7 +906,7 @@ mod tests {
             let mut fs = MockFileSystem::new();
             let mut session = Session::<_, FakeSpanSystem<_>, _>::new(&mut fs, IgnoreDiagnostics);
             session.import_object(object);
-            session.link()
+            session.link().unwrap()
         };
         assert_eq!(*program.sections[0].data, [0x00, 0x00])
     }
@@ -433,8 +916,9 @@ mod tests {
         // name DB  name
         let object = ObjectData {
             content: Content {
+                assertions: vec![],
                 sections: vec![Section {
-                    constraints: Constraints { addr: None },
+                    constraints: Constraints::default(),
                     addr: VarId(0),
                     size: VarId(1),
                     fragments: vec![
@@ -452,6 +936,7 @@ mod tests {
                         meaning: SymbolMeaning::Closure(Closure {
                             expr: Expr::from_atom(Atom::Location, ()),
                             location: VarId(2),
+                            params: 0,
                         }),
                     },
                 }],
@@ -463,7 +948,7 @@ mod tests {
             let mut fs = MockFileSystem::new();
             let mut session = Session::<_, FakeSpanSystem<_>, _>::new(&mut fs, IgnoreDiagnostics);
             session.import_object(object);
-            session.link()
+            session.link().unwrap()
         };
         assert_eq!(*program.sections[0].data, [0x02, 0x00])
     }
@@ -479,10 +964,12 @@ mod tests {
         // HALT
         let object = ObjectData::<_, &str> {
             content: Content {
+                assertions: vec![],
                 sections: vec![
                     Section {
                         constraints: Constraints {
                             addr: Some(Expr::from_atom(Atom::Const(0x0150), ())),
+                            ..Default::default()
                         },
                         addr: VarId(0),
                         size: VarId(1),
@@ -495,6 +982,7 @@ mod tests {
                                 ExprOp::Atom(Atom::Const(0x10)).with_span(()),
                                 ExprOp::Binary(BinOp::Plus).with_span(()),
                             ])),
+                            ..Default::default()
                         },
                         addr: VarId(2),
                         size: VarId(3),
@@ -511,7 +999,7 @@ mod tests {
             let mut fs = MockFileSystem::new();
             let mut session = Session::<_, FakeSpanSystem<_>, _>::new(&mut fs, IgnoreDiagnostics);
             session.import_object(object);
-            session.link()
+            session.link().unwrap()
         };
         assert_eq!(
             program.sections[1].addr,
@@ -526,9 +1014,11 @@ mod tests {
         // ORG $ffe1
         // LABEL
         let content = Content {
+            assertions: vec![],
             sections: vec![Section {
                 constraints: Constraints {
                     addr: Some(Expr::from_atom(Atom::Const(addr), ())),
+                    ..Default::default()
                 },
                 addr: VarId(0),
                 size: VarId(1),
@@ -541,24 +1031,122 @@ mod tests {
                     meaning: SymbolMeaning::Closure(Closure {
                         expr: Expr::from_atom(Atom::Location, ()),
                         location: VarId(2),
+                        params: 0,
                     }),
                 },
             }],
             vars: 3,
         };
-        let mut vars = VarTable(vec![addr.into(), 0.into(), addr.into()]);
+        let mut vars = VarTable::from(vec![addr.into(), 0.into(), addr.into()]);
 
         vars.resolve(&content);
         assert_eq!(vars[VarId(0)], addr.into());
     }
 
+    #[test]
+    fn label_in_load_block_defined_relative_to_load_addr() {
+        let load_addr = 0xc000;
+
+        // NOP
+        // LOAD WRAM0($c000)
+        // NOP
+        // LABEL
+        // ENDL
+        let content = Content {
+            assertions: vec![],
+            sections: vec![Section {
+                constraints: Constraints::default(),
+                addr: VarId(0),
+                size: VarId(1),
+                fragments: vec![
+                    Fragment::Byte(0x00),
+                    Fragment::Load(Expr::from_atom(Atom::Const(load_addr), ())),
+                    Fragment::Byte(0x00),
+                    Fragment::Reloc(VarId(2)),
+                    Fragment::EndLoad,
+                ],
+            }],
+            symbols: vec![Symbol::Exported {
+                ident: "LABEL",
+                def: SymbolDefRecord {
+                    def_ident_span: (),
+                    meaning: SymbolMeaning::Closure(Closure {
+                        expr: Expr::from_atom(Atom::Location, ()),
+                        location: VarId(2),
+                        params: 0,
+                    }),
+                },
+            }],
+            vars: 3,
+        };
+        let mut vars = VarTable::from(vec![Var::Unknown; 3]);
+        vars.resolve(&content);
+        assert_eq!(vars[VarId(1)], 2.into());
+        assert_eq!(vars[VarId(2)], (load_addr + 1).into())
+    }
+
+    #[test]
+    fn refine_section_depending_on_label_in_later_section() {
+        // SECTION
+        // DS   LABEL
+        // SECTION
+        // ORG  $0100
+        // LABEL
+        let content = Content::<&str, _> {
+            assertions: vec![],
+            sections: vec![
+                Section {
+                    constraints: Constraints::default(),
+                    addr: VarId(0),
+                    size: VarId(1),
+                    fragments: vec![Fragment::Reserved(Expr::from_atom(SymbolId(0).into(), ()))],
+                },
+                Section {
+                    constraints: Constraints {
+                        addr: Some(Expr::from_atom(Atom::Const(0x0100), ())),
+                        ..Default::default()
+                    },
+                    addr: VarId(2),
+                    size: VarId(3),
+                    fragments: vec![Fragment::Reloc(VarId(4))],
+                },
+            ],
+            symbols: vec![Symbol::Local {
+                def: SymbolDefRecord {
+                    def_ident_span: (),
+                    meaning: SymbolMeaning::Closure(Closure {
+                        expr: Expr::from_atom(Atom::Location, ()),
+                        location: VarId(4),
+                        params: 0,
+                    }),
+                },
+            }],
+            vars: 5,
+        };
+        assert_eq!(content.dependents()[4], [0]);
+        let mut vars = VarTable::from(vec![Var::Unknown; 5]);
+        vars.resolve(&content);
+        assert_eq!(vars[VarId(1)], 0x0100.into())
+    }
+
+    #[test]
+    fn refining_var_invalidates_memoized_closure_values() {
+        let mut vars = VarTable::from(vec![Var::Unknown]);
+        vars.memoize_closures(true);
+        vars.memoize_closure_value(VarId(0), &Const::Num(1.into()));
+        assert_eq!(vars.closure_value(VarId(0)), Some(Const::Num(1.into())));
+        vars.refine(VarId(0), 0.into());
+        assert_eq!(vars.closure_value(VarId(0)), None)
+    }
+
     #[test]
     fn empty_section_has_size_zero() {
         assert_section_size(
             0,
             Content {
+                assertions: vec![],
                 sections: vec![Section {
-                    constraints: Constraints { addr: None },
+                    constraints: Constraints::default(),
                     addr: VarId(0),
                     size: VarId(1),
                     fragments: vec![],
@@ -566,7 +1154,7 @@ mod tests {
                 symbols: vec![],
                 vars: 2,
             },
-            VarTable(vec![0x0000.into(), 0.into()]),
+            VarTable::from(vec![0x0000.into(), 0.into()]),
         )
     }
 
@@ -575,8 +1163,9 @@ mod tests {
         assert_section_size(
             1,
             Content {
+                assertions: vec![],
                 sections: vec![Section {
-                    constraints: Constraints { addr: None },
+                    constraints: Constraints::default(),
                     addr: VarId(0),
                     size: VarId(1),
                     fragments: vec![Fragment::Byte(0x00)],
@@ -584,10 +1173,71 @@ mod tests {
                 symbols: vec![],
                 vars: 2,
             },
-            VarTable(vec![0x0000.into(), 1.into()]),
+            VarTable::from(vec![0x0000.into(), 1.into()]),
         );
     }
 
+    #[test]
+    fn union_has_size_of_largest_alternative() {
+        assert_section_size(
+            5,
+            Content {
+                assertions: vec![],
+                sections: vec![Section {
+                    constraints: Constraints::default(),
+                    addr: VarId(0),
+                    size: VarId(1),
+                    fragments: vec![
+                        Fragment::Union,
+                        Fragment::Reserved(Expr::from_atom(Atom::Const(2), ())),
+                        Fragment::NextUnion,
+                        Fragment::Reserved(Expr::from_atom(Atom::Const(3), ())),
+                        Fragment::Byte(0x00),
+                        Fragment::EndUnion,
+                        Fragment::Byte(0x00),
+                    ],
+                }],
+                symbols: vec![],
+                vars: 2,
+            },
+            VarTable::from(vec![0x0000.into(), Var::Unknown]),
+        )
+    }
+
+    #[test]
+    fn union_alternatives_start_at_same_offset() {
+        let reloc = |id| Fragment::Reloc(VarId(id));
+        let content = Content::<&str, _> {
+            assertions: vec![],
+            sections: vec![Section {
+                constraints: Constraints {
+                    addr: Some(Expr::from_atom(Atom::Const(0xc000), ())),
+                    ..Default::default()
+                },
+                addr: VarId(0),
+                size: VarId(1),
+                fragments: vec![
+                    Fragment::Byte(0x00),
+                    Fragment::Union,
+                    reloc(2),
+                    Fragment::Reserved(Expr::from_atom(Atom::Const(2), ())),
+                    Fragment::NextUnion,
+                    reloc(3),
+                    Fragment::Byte(0x00),
+                    Fragment::EndUnion,
+                    reloc(4),
+                ],
+            }],
+            symbols: vec![],
+            vars: 5,
+        };
+        let mut vars = VarTable::from(vec![Var::Unknown; 5]);
+        vars.resolve(&content);
+        assert_eq!(vars[VarId(2)], 0xc001.into());
+        assert_eq!(vars[VarId(3)], 0xc001.into());
+        assert_eq!(vars[VarId(4)], 0xc003.into())
+    }
+
     #[test]
     fn section_with_const_inline_addr_ld_has_size_two() {
         test_section_size_with_literal_ld_inline_addr(0xff00, 2)
@@ -602,8 +1252,9 @@ mod tests {
         assert_section_size(
             expected,
             Content {
+                assertions: vec![],
                 sections: vec![Section {
-                    constraints: Constraints { addr: None },
+                    constraints: Constraints::default(),
                     addr: VarId(0),
                     size: VarId(1),
                     fragments: vec![Fragment::LdInlineAddr(0xf0, addr.into())],
@@ -611,7 +1262,7 @@ mod tests {
                 symbols: vec![],
                 vars: 2,
             },
-            VarTable(vec![0x0000.into(), Var::Unknown]),
+            VarTable::from(vec![0x0000.into(), Var::Unknown]),
         );
     }
 
@@ -620,8 +1271,9 @@ mod tests {
         assert_section_size(
             3,
             Content {
+                assertions: vec![],
                 sections: vec![Section {
-                    constraints: Constraints { addr: None },
+                    constraints: Constraints::default(),
                     addr: VarId(0),
                     size: VarId(1),
                     fragments: vec![
@@ -636,12 +1288,13 @@ mod tests {
                         meaning: SymbolMeaning::Closure(Closure {
                             expr: Expr::from_atom(Atom::Location, ()),
                             location: VarId(2),
+                            params: 0,
                         }),
                     },
                 }],
                 vars: 3,
             },
-            VarTable(vec![
+            VarTable::from(vec![
                 0x0000.into(),
                 Var::Range { min: 2, max: 3 },
                 Var::Range { min: 2, max: 3 },
@@ -656,9 +1309,11 @@ mod tests {
         //              DW      my_section
         let object = ObjectData {
             content: Content {
+                assertions: vec![],
                 sections: vec![Section {
                     constraints: Constraints {
                         addr: Some(Expr::from_atom(Atom::Const(0x1337), ())),
+                        ..Default::default()
                     },
                     addr: VarId(0),
                     size: VarId(1),
@@ -683,7 +1338,7 @@ mod tests {
             let mut fs = MockFileSystem::new();
             let mut session = Session::<_, FakeSpanSystem<_>, _>::new(&mut fs, IgnoreDiagnostics);
             session.import_object(object);
-            session.link()
+            session.link().unwrap()
         };
         assert_eq!(*program.sections[0].data, [0x37, 0x13])
     }
@@ -698,9 +1353,11 @@ mod tests {
         //          DS  10
         // label    DW  label
         let content = Content {
+            assertions: vec![],
             sections: vec![Section {
                 constraints: Constraints {
                     addr: Some(Expr::from_atom(Atom::Const(addr), ())),
+                    ..Default::default()
                 },
                 addr: VarId(0),
                 size: VarId(1),
@@ -720,12 +1377,13 @@ mod tests {
                     meaning: SymbolMeaning::Closure(Closure {
                         expr: Expr::from_atom(Atom::Location, ()),
                         location: VarId(2),
+                        params: 0,
                     }),
                 },
             }],
             vars: 3,
         };
-        let mut vars = VarTable(vec![addr.into(), (bytes + 2).into(), (addr + bytes).into()]);
+        let mut vars = VarTable::from(vec![addr.into(), (bytes + 2).into(), (addr + bytes).into()]);
 
         vars.resolve(&content);
         assert_eq!(vars[symbol], (addr + bytes).into())
@@ -0,0 +1,334 @@
+use super::{LinkageContext, VarTable};
+
+use crate::diagnostics::{BackendDiagnostics, Message};
+use crate::object::var::Var;
+use crate::object::*;
+use crate::span::Source;
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+type Space = (SectionKind, u16);
+
+const MAX_LAYOUT_PASSES: usize = 32;
+
+impl<I, S: Clone> Content<I, S> {
+    // Resolves the vars after giving every typed section without an address an address of its
+    // own. Placement can change the size of a section, so sections are placed again until no size
+    // grows beyond the room reserved for it, for at most MAX_LAYOUT_PASSES passes. Jumps whose
+    // size is still ambiguous at that point are turned into absolute jumps before resolving again.
+    pub(super) fn resolve_layout(
+        &mut self,
+        diagnostics: &mut impl BackendDiagnostics<S>,
+    ) -> VarTable {
+        let mut sizes = vec![0; self.sections.len()];
+        let mut placements: Option<Vec<_>> = None;
+        let mut passes = 0;
+        loop {
+            passes += 1;
+            let mut vars = VarTable::from(vec![Var::Unknown; self.vars]);
+            for (section, placement) in self.sections.iter().zip(placements.iter().flatten()) {
+                if let Some((addr, _)) = placement {
+                    vars[section.addr] = Var::from(*addr)
+                }
+            }
+            vars.resolve(self);
+            let mut has_grown = false;
+            for (section, size) in self.sections.iter().zip(&mut sizes) {
+                let new_size = max_size(&vars[section.size]);
+                if new_size > *size {
+                    *size = new_size;
+                    has_grown = true
+                }
+            }
+            let is_last_pass = passes == MAX_LAYOUT_PASSES;
+            if placements.is_some()
+                && !has_grown
+                && !is_last_pass
+                && self.widen_ambiguous_jumps(&vars)
+            {
+                continue;
+            }
+            match &placements {
+                Some(placements) if !has_grown || is_last_pass => {
+                    self.assign_banks(placements, diagnostics);
+                    return vars;
+                }
+                _ => placements = Some(self.place_sections(&vars, &sizes)),
+            }
+        }
+    }
+
+    fn widen_ambiguous_jumps(&mut self, vars: &VarTable) -> bool {
+        let mut context = LinkageContext {
+            content: &*self,
+            vars,
+            location: 0.into(),
+        };
+        let jumps: Vec<_> = self
+            .sections
+            .iter()
+            .map(|section| section.ambiguous_jumps(&mut context))
+            .collect();
+        let mut has_widened = false;
+        for (section, jumps) in self.sections.iter_mut().zip(jumps) {
+            for index in jumps.into_iter().rev() {
+                if let Fragment::Jump(condition, target) = section.fragments[index].clone() {
+                    let opcode = condition.map_or(0xc3, |condition| 0xc2 | condition);
+                    section.fragments.splice(
+                        index..=index,
+                        vec![
+                            Fragment::Byte(opcode),
+                            Fragment::Immediate(target, Width::Word),
+                        ],
+                    );
+                    has_widened = true
+                }
+            }
+        }
+        has_widened
+    }
+
+    // Places typed sections in order at the lowest free address that satisfies their constraints.
+    // Sections without a type or an address stay at address 0.
+    fn place_sections(&self, vars: &VarTable, sizes: &[i32]) -> Vec<Option<(i32, u16)>> {
+        let mut occupied: HashMap<Space, Vec<Range<i32>>> = HashMap::new();
+        for (section, size) in self.sections.iter().zip(sizes) {
+            if section.constraints.addr.is_none() {
+                continue;
+            }
+            if let Some(addr) = vars[section.addr].exact() {
+                if let Some(space) = section.space(addr) {
+                    occupied.entry(space).or_default().push(addr..addr + size)
+                }
+            }
+        }
+        self.sections
+            .iter()
+            .zip(sizes)
+            .map(|(section, &size)| {
+                let kind = match section.constraints {
+                    Constraints {
+                        addr: None,
+                        kind: Some(kind),
+                        ..
+                    } => kind,
+                    _ => return None,
+                };
+                let align = 1 << section.constraints.align.unwrap_or(0);
+                let region = kind.addr_range();
+                let banks = match (section.constraints.bank, kind.banks()) {
+                    (Some(bank), _) => bank..=bank,
+                    (None, Some(banks)) => *banks.start() as u16..=*banks.end() as u16,
+                    (None, None) => 0..=0,
+                };
+                for bank in banks {
+                    let ranges = occupied.entry((kind, bank)).or_default();
+                    let mut addr = align_up(*region.start(), align);
+                    while addr + size <= region.end() + 1 {
+                        let end = ranges
+                            .iter()
+                            .filter(|range| range.start < addr + size && addr < range.end)
+                            .map(|range| range.end)
+                            .max();
+                        match end {
+                            Some(end) => addr = align_up(end, align),
+                            None => {
+                                ranges.push(addr..addr + size);
+                                return Some((addr, bank));
+                            }
+                        }
+                    }
+                }
+                None
+            })
+            .collect()
+    }
+
+    fn assign_banks(
+        &mut self,
+        placements: &[Option<(i32, u16)>],
+        diagnostics: &mut impl BackendDiagnostics<S>,
+    ) {
+        for (index, placement) in placements.iter().enumerate() {
+            let constraints = &self.sections[index].constraints;
+            let kind = match constraints {
+                Constraints {
+                    addr: None,
+                    kind: Some(kind),
+                    ..
+                } => *kind,
+                _ => continue,
+            };
+            match *placement {
+                Some((_, bank)) => {
+                    if kind.banks().is_some() {
+                        self.sections[index].constraints.bank = Some(bank)
+                    }
+                }
+                None => {
+                    if let Some(span) = self.section_name_span(SectionId(index)) {
+                        diagnostics.emit_diag(Message::SectionDoesNotFit { kind }.at(span.clone()))
+                    }
+                }
+            }
+        }
+    }
+
+    // Reports sections that share addresses in the same bank.
+    pub(super) fn check_overlaps(
+        &self,
+        vars: &VarTable,
+        diagnostics: &mut impl BackendDiagnostics<S>,
+    ) {
+        let mut occupied: HashMap<Space, Vec<Range<i32>>> = HashMap::new();
+        for (index, section) in self.sections.iter().enumerate() {
+            let constraints = &section.constraints;
+            if constraints.addr.is_none() && constraints.kind.is_none() {
+                continue;
+            }
+            let addr = match vars[section.addr].exact() {
+                Some(addr) => addr,
+                None => continue,
+            };
+            let size = max_size(&vars[section.size]);
+            let space = match section.space(addr) {
+                Some(space) if size > 0 => space,
+                _ => continue,
+            };
+            let ranges = occupied.entry(space).or_default();
+            let overlap = ranges
+                .iter()
+                .filter(|range| range.start < addr + size && addr < range.end)
+                .map(|range| range.start.max(addr))
+                .min();
+            ranges.push(addr..addr + size);
+            let span = match &section.constraints.addr {
+                Some(expr) => Some(expr.span()),
+                None => self.section_name_span(SectionId(index)).cloned(),
+            };
+            if let (Some(addr), Some(span)) = (overlap, span) {
+                diagnostics.emit_diag(Message::OverlappingSections { addr }.at(span))
+            }
+        }
+    }
+}
+
+impl<S> Section<S> {
+    fn space(&self, addr: i32) -> Option<Space> {
+        let kind = self.constraints.kind.or_else(|| {
+            [
+                SectionKind::Rom0,
+                SectionKind::Romx,
+                SectionKind::Vram,
+                SectionKind::Sram,
+                SectionKind::Wram0,
+                SectionKind::Wramx,
+                SectionKind::Oam,
+                SectionKind::Hram,
+            ]
+            .iter()
+            .copied()
+            .find(|kind| kind.addr_range().contains(&addr))
+        })?;
+        let bank = self
+            .constraints
+            .bank
+            .unwrap_or_else(|| kind.banks().map_or(0, |banks| *banks.start() as u16));
+        Some((kind, bank))
+    }
+}
+
+fn max_size(size: &Var) -> i32 {
+    match size {
+        Var::Range { max, .. } => *max,
+        Var::Unknown => 0,
+    }
+}
+
+fn align_up(addr: i32, align: i32) -> i32 {
+    (addr + align - 1) & !(align - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::diagnostics::IgnoreDiagnostics;
+    use crate::expr::Atom;
+
+    #[test]
+    fn place_floating_sections_after_fixed_section() {
+        let mut content = Content::<&str, ()> {
+            assertions: vec![],
+            sections: vec![
+                mk_section(0, Some(0x0000), None, 0x10),
+                mk_section(2, None, Some(3), 1),
+                mk_section(4, None, None, 4),
+            ],
+            symbols: vec![],
+            vars: 6,
+        };
+        for section in &mut content.sections {
+            section.constraints.kind = Some(SectionKind::Rom0)
+        }
+        let vars = content.resolve_layout(&mut IgnoreDiagnostics);
+        assert_eq!(
+            content
+                .sections()
+                .map(|section| vars[section.addr].exact())
+                .collect::<Vec<_>>(),
+            [Some(0x0000), Some(0x0010), Some(0x0011)]
+        )
+    }
+
+    #[test]
+    fn keep_untyped_sections_at_zero() {
+        let mut content = Content::<&str, ()> {
+            assertions: vec![],
+            sections: vec![
+                mk_section(0, None, None, 0x5000),
+                mk_section(2, None, None, 1),
+            ],
+            symbols: vec![],
+            vars: 4,
+        };
+        let vars = content.resolve_layout(&mut IgnoreDiagnostics);
+        assert_eq!(vars[content.sections[0].addr].exact(), Some(0));
+        assert_eq!(vars[content.sections[1].addr].exact(), Some(0));
+        assert_eq!(content.sections[0].constraints.bank, None)
+    }
+
+    #[test]
+    fn place_section_in_next_bank_when_bank_is_full() {
+        let mut content = Content::<&str, ()> {
+            assertions: vec![],
+            sections: vec![
+                mk_section(0, None, None, 0x4000),
+                mk_section(2, None, None, 1),
+            ],
+            symbols: vec![],
+            vars: 4,
+        };
+        for section in &mut content.sections {
+            section.constraints.kind = Some(SectionKind::Romx)
+        }
+        let vars = content.resolve_layout(&mut IgnoreDiagnostics);
+        assert_eq!(vars[content.sections[1].addr].exact(), Some(0x4000));
+        assert_eq!(content.sections[0].constraints.bank, Some(1));
+        assert_eq!(content.sections[1].constraints.bank, Some(2))
+    }
+
+    fn mk_section(first_var: usize, addr: Option<i32>, align: Option<u8>, len: i32) -> Section<()> {
+        Section {
+            constraints: Constraints {
+                addr: addr.map(|addr| Expr::from_atom(Atom::Const(addr), ())),
+                align,
+                ..Default::default()
+            },
+            addr: VarId(first_var),
+            size: VarId(first_var + 1),
+            fragments: vec![Fragment::Reserved(Expr::from_atom(Atom::Const(len), ()))],
+        }
+    }
+}
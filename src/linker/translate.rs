@@ -1,11 +1,12 @@
 use super::{LinkageContext, VarTable};
 
 use crate::diagnostics::{BackendDiagnostics, Message};
-use crate::object::{Content, Expr, Fragment, Section, Width};
+use crate::object::var::Var;
+use crate::object::{Content, Expr, Fragment, Section, SectionKind, Width};
 use crate::program::Chunk;
 use crate::span::Source;
 
-use std::mem::replace;
+use std::ops::Range;
 use std::vec::IntoIter;
 
 impl<'a, S: Clone + 'a> Section<S> {
@@ -14,52 +15,116 @@ impl<'a, S: Clone + 'a> Section<S> {
         context: &mut LinkageContext<&'a Content<I, S>, &VarTable>,
         diagnostics: &mut impl BackendDiagnostics<S>,
     ) -> Vec<Chunk> {
+        if matches!(self.constraints.kind, Some(kind) if kind.is_ram()) {
+            return Vec::new();
+        }
+        let offset = self.rom_offset();
         let mut chunks = Vec::new();
         let mut data = Vec::new();
         let mut addr = context.vars[self.addr].clone();
         context.location = addr.clone();
-        self.traverse(context, |fragment, context| {
-            if let Fragment::Reserved(expr) = fragment {
-                let bytes = expr.to_num(context, diagnostics);
+        let mut start = addr.clone();
+        self.traverse(context, |fragment, context, storage| {
+            let start = std::mem::replace(&mut start, context.location.clone());
+            if let Fragment::Reserved(_)
+            | Fragment::Union
+            | Fragment::NextUnion
+            | Fragment::EndUnion = fragment
+            {
                 if !data.is_empty() {
                     chunks.push(Chunk {
-                        addr: addr.exact().unwrap() as usize,
-                        data: replace(&mut data, Vec::new()).into_boxed_slice(),
+                        addr: addr.exact().unwrap() as usize + offset,
+                        data: std::mem::take(&mut data).into_boxed_slice(),
                     });
                 }
-                context.location += &bytes;
-                addr = context.location.clone();
+                addr = storage.clone();
             } else {
-                data.extend(fragment.translate(context, diagnostics))
+                data.extend(fragment.translate(&start, context, diagnostics))
             }
         });
         if !data.is_empty() {
             chunks.push(Chunk {
-                addr: addr.exact().unwrap() as usize,
+                addr: addr.exact().unwrap() as usize + offset,
                 data: data.into_boxed_slice(),
             });
         }
         chunks
     }
+
+    pub(super) fn reserved_rom<I>(
+        &'a self,
+        context: &mut LinkageContext<&'a Content<I, S>, &VarTable>,
+    ) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        if matches!(self.constraints.kind, Some(kind) if kind.is_ram()) {
+            return ranges;
+        }
+        let offset = self.rom_offset();
+        context.location = context.vars[self.addr].clone();
+        let mut start = context.location.clone();
+        self.traverse(context, |fragment, context, _| {
+            let start = std::mem::replace(&mut start, context.location.clone());
+            if let Fragment::Reserved(_) = fragment {
+                if let (Some(start), Some(end)) = (start.exact(), context.location.exact()) {
+                    if start < end {
+                        ranges.push(start as usize + offset..end as usize + offset)
+                    }
+                }
+            }
+        });
+        ranges
+    }
+
+    fn rom_offset(&self) -> usize {
+        match (self.constraints.kind, self.constraints.bank) {
+            (Some(SectionKind::Romx), Some(bank)) => (usize::from(bank) - 1) * 0x4000,
+            _ => 0,
+        }
+    }
 }
 
 impl<S: Clone> Fragment<Expr<S>> {
     fn translate<I>(
         &self,
+        start: &Var,
         context: &LinkageContext<&Content<I, S>, &VarTable>,
         diagnostics: &mut impl BackendDiagnostics<S>,
     ) -> IntoIter<u8> {
         match self {
             Fragment::Byte(value) => vec![*value],
             Fragment::Embedded(opcode, expr) => {
-                let n = expr.to_num(context, diagnostics).exact().unwrap();
+                let n = expr.to_num(context, diagnostics).exact().unwrap_or(0);
                 vec![opcode | ((n as u8) << 3)]
             }
             Fragment::Immediate(expr, width) => {
-                resolve_expr_item(&expr, *width, context, diagnostics).into_bytes()
+                resolve_expr_item(expr, *width, context, diagnostics).into_bytes()
+            }
+            Fragment::Jump(condition, target) => {
+                let context = &LinkageContext {
+                    content: context.content,
+                    vars: context.vars,
+                    location: start.clone(),
+                };
+                if self.size(context).exact() == Some(2) {
+                    let offset = &target.to_num(context, diagnostics) - &(start + &2.into());
+                    let opcode = condition.map_or(0x18, |condition| 0x20 | condition);
+                    vec![opcode, offset.exact().unwrap_or(0) as u8]
+                } else {
+                    let opcode = condition.map_or(0xc3, |condition| 0xc2 | condition);
+                    let mut bytes = vec![opcode];
+                    bytes.extend(
+                        resolve_expr_item(target, Width::Word, context, diagnostics).into_bytes(),
+                    );
+                    bytes
+                }
             }
+            Fragment::Relative(expr) => resolve_relative_offset(expr, context, diagnostics),
             Fragment::LdInlineAddr(opcode, expr) => {
-                let addr = expr.to_num(context, diagnostics).exact().unwrap();
+                // The size check leaves only exact addresses unless resolution failed.
+                let addr = match expr.to_num(context, diagnostics) {
+                    Var::Range { min, .. } => min,
+                    Var::Unknown => 0,
+                };
                 let kind = if addr < 0xff00 {
                     AddrKind::Low
                 } else {
@@ -78,7 +143,12 @@ impl<S: Clone> Fragment<Expr<S>> {
                 bytes.extend(addr_repr.into_bytes());
                 bytes
             }
-            Fragment::Reloc(_) => vec![],
+            Fragment::Reloc(_)
+            | Fragment::Load(_)
+            | Fragment::EndLoad
+            | Fragment::Union
+            | Fragment::NextUnion
+            | Fragment::EndUnion => vec![],
             Fragment::Reserved(_) => unimplemented!(),
         }
         .into_iter()
@@ -121,6 +191,23 @@ fn resolve_expr_item<I, S: Clone>(
     fit_to_width((value, span), width, diagnostics)
 }
 
+fn resolve_relative_offset<I, S: Clone>(
+    expr: &Expr<S>,
+    context: &LinkageContext<&Content<I, S>, &VarTable>,
+    diagnostics: &mut impl BackendDiagnostics<S>,
+) -> Vec<u8> {
+    let offset = expr.to_num(context, diagnostics).exact().unwrap_or(0);
+    if !is_in_i8_range(offset) {
+        let excess = if offset < 0 {
+            i32::from(i8::MIN) - offset
+        } else {
+            offset - i32::from(i8::MAX)
+        };
+        diagnostics.emit_diag(Message::JumpOutOfRange { excess }.at(expr.span()))
+    }
+    vec![offset as u8]
+}
+
 fn fit_to_width<S: Clone>(
     (value, value_ref): (i32, S),
     width: Width,
@@ -138,7 +225,7 @@ fn fit_to_width<S: Clone>(
 fn is_in_range(n: i32, width: Width) -> bool {
     match width {
         Width::Byte => is_in_byte_range(n),
-        Width::Word => true,
+        Width::Word => is_in_word_range(n),
     }
 }
 
@@ -147,11 +234,23 @@ fn is_in_byte_range(n: i32) -> bool {
 }
 
 fn is_in_i8_range(n: i32) -> bool {
-    n >= i32::from(i8::min_value()) && n <= i32::from(i8::max_value())
+    n >= i32::from(i8::MIN) && n <= i32::from(i8::MAX)
 }
 
 fn is_in_u8_range(n: i32) -> bool {
-    n >= i32::from(u8::min_value()) && n <= i32::from(u8::max_value())
+    n >= i32::from(u8::MIN) && n <= i32::from(u8::MAX)
+}
+
+fn is_in_word_range(n: i32) -> bool {
+    is_in_i16_range(n) || is_in_u16_range(n)
+}
+
+fn is_in_i16_range(n: i32) -> bool {
+    n >= i32::from(i16::MIN) && n <= i32::from(i16::MAX)
+}
+
+fn is_in_u16_range(n: i32) -> bool {
+    n >= i32::from(u16::MIN) && n <= i32::from(u16::MAX)
 }
 
 #[cfg(test)]
@@ -208,9 +307,28 @@ mod tests {
         assert_eq!(actual, [0x01])
     }
 
+    #[test]
+    fn translate_backward_relative_offset() {
+        let actual = translate_section_item(Fragment::Relative((-128).into()));
+        assert_eq!(actual, [0x80])
+    }
+
+    #[test]
+    fn translate_near_jump_as_jr() {
+        let actual = translate_section_item(Fragment::Jump(Some(0x08), 0x10.into()));
+        assert_eq!(actual, [0x28, 0x0e])
+    }
+
+    #[test]
+    fn translate_far_jump_as_jp() {
+        let actual = translate_section_item(Fragment::Jump(None, 0x1234.into()));
+        assert_eq!(actual, [0xc3, 0x34, 0x12])
+    }
+
     fn translate_section_item<S: Clone + PartialEq>(fragment: Fragment<Expr<Name, S>>) -> Vec<u8> {
         fragment
             .translate(
+                &0.into(),
                 &LinkageContext {
                     content: &Content::<&str, _>::new(),
                     vars: &VarTable::new(),
@@ -228,9 +346,11 @@ mod tests {
         // ORG $7ff0
         // NOP
         let content = Content::<&str, _> {
+            assertions: vec![],
             sections: vec![Section {
                 constraints: Constraints {
                     addr: Some(Expr::from_atom(Atom::Const(addr), ())),
+                    ..Default::default()
                 },
                 addr: VarId(0),
                 size: VarId(1),
@@ -239,7 +359,7 @@ mod tests {
             symbols: vec![],
             vars: 2,
         };
-        let mut vars = VarTable(vec![addr.into(), 1.into()]);
+        let mut vars = VarTable::from(vec![addr.into(), 1.into()]);
 
         vars.resolve(&content);
         let context = &mut LinkageContext {
@@ -260,8 +380,9 @@ mod tests {
         // NOP
         // DB   .
         let content = Content::<&str, _> {
+            assertions: vec![],
             sections: vec![Section {
-                constraints: Constraints { addr: None },
+                constraints: Constraints::default(),
                 addr: VarId(0),
                 size: VarId(1),
                 fragments: vec![
@@ -272,7 +393,7 @@ mod tests {
             symbols: vec![],
             vars: 2,
         };
-        let mut vars = VarTable(vec![0.into(), 2.into()]);
+        let mut vars = VarTable::from(vec![0.into(), 2.into()]);
 
         vars.resolve(&content);
         let context = &mut LinkageContext {
@@ -295,9 +416,11 @@ mod tests {
         // ORG  $ffe1
         // DW   .
         let content = Content::<&str, _> {
+            assertions: vec![],
             sections: vec![Section {
                 constraints: Constraints {
                     addr: Some(Expr::from_atom(Atom::Const(addr), ())),
+                    ..Default::default()
                 },
                 addr: VarId(0),
                 size: VarId(1),
@@ -309,7 +432,7 @@ mod tests {
             symbols: vec![],
             vars: 2,
         };
-        let mut vars = VarTable(vec![addr.into(), 2.into()]);
+        let mut vars = VarTable::from(vec![addr.into(), 2.into()]);
 
         vars.resolve(&content);
         let context = &mut LinkageContext {
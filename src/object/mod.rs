@@ -1,8 +1,11 @@
 use self::var::Var;
 
+use crate::eval::Const;
 use crate::expr::{Atom, ExprOp};
 use crate::span::SpanSource;
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ops::{Index, IndexMut, Range, RangeInclusive};
 
 pub mod var;
@@ -15,6 +18,7 @@ pub(crate) struct ObjectData<M: SpanSource, I> {
 }
 
 pub struct Content<I, S> {
+    pub assertions: Vec<Assertion<S>>,
     pub sections: Vec<Section<S>>,
     pub symbols: Vec<Symbol<I, S>>,
     pub vars: usize,
@@ -27,8 +31,44 @@ pub struct Section<S> {
     pub fragments: Vec<Fragment<Expr<S>>>,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assertion<S> {
+    pub condition: Closure<S>,
+    pub message: Option<Box<str>>,
+    pub span: S,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Constraints<S> {
     pub addr: Option<Expr<S>>,
+    pub align: Option<u8>,
+    pub bank: Option<u16>,
+    pub kind: Option<SectionKind>,
+    pub keep: bool,
+}
+
+impl<S> Default for Constraints<S> {
+    fn default() -> Self {
+        Constraints {
+            addr: None,
+            align: None,
+            bank: None,
+            kind: None,
+            keep: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SectionKind {
+    Hram,
+    Oam,
+    Rom0,
+    Romx,
+    Sram,
+    Vram,
+    Wram0,
+    Wramx,
 }
 
 pub type Expr<S> = crate::expr::Expr<Name, S>;
@@ -41,23 +81,53 @@ pub enum Name {
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BuiltinId {
+    Abs,
+    Atan2,
+    Ceil,
+    Cos,
+    Def,
+    Div,
+    Endof,
+    Floor,
+    High,
+    Low,
+    Max,
+    Min,
+    Mul,
+    Round,
+    Sin,
     Sizeof,
+    Startof,
+    Strcat,
+    Strcmp,
+    Strin,
+    Strlen,
+    Strlwr,
+    Strsub,
+    Strupr,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct SymbolId(pub usize);
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct VarId(pub usize);
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Fragment<E> {
     Byte(u8),
     Immediate(E, Width),
+    Jump(Option<u8>, E),
     LdInlineAddr(u8, E),
     Embedded(u8, E),
+    Relative(E),
     Reloc(VarId),
     Reserved(E),
+    Load(E),
+    EndLoad,
+    Union,
+    NextUnion,
+    EndUnion,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -69,6 +139,7 @@ pub enum Width {
 #[derive(Debug, PartialEq)]
 pub enum Symbol<I, S> {
     Exported { ident: I, def: SymbolDefRecord<S> },
+    Weak { ident: I, def: SymbolDefRecord<S> },
     Local { def: SymbolDefRecord<S> },
     Unknown { ident: I },
 }
@@ -89,12 +160,17 @@ pub enum SymbolMeaning<S> {
 pub struct Closure<S> {
     pub expr: Expr<S>,
     pub location: VarId,
+    pub params: usize,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct SectionId(pub usize);
 
-pub struct VarTable(pub Vec<Var>);
+#[derive(Default)]
+pub struct VarTable {
+    vars: Vec<Var>,
+    closure_values: Option<RefCell<HashMap<VarId, Const>>>,
+}
 
 #[derive(Default)]
 pub struct Metadata {
@@ -104,6 +180,7 @@ pub struct Metadata {
 
 pub type SourceFileTable = Box<[Box<str>]>;
 
+#[derive(Default)]
 pub struct SpanData {
     pub(crate) source_file_inclusions: Vec<FileInclusionMetadata<Span>>,
     pub(crate) macro_defs: Vec<MacroDefMetadata<Span>>,
@@ -175,6 +252,7 @@ impl SpanSource for Metadata {
 impl<I, S> Content<I, S> {
     pub fn new() -> Content<I, S> {
         Content {
+            assertions: Vec::new(),
             sections: Vec::new(),
             symbols: Vec::new(),
             vars: 0,
@@ -192,29 +270,99 @@ pub(super) struct LinkageContext<C, V> {
     pub location: Var,
 }
 
+impl SectionKind {
+    pub fn is_ram(self) -> bool {
+        !matches!(self, SectionKind::Rom0 | SectionKind::Romx)
+    }
+
+    pub fn addr_range(self) -> RangeInclusive<i32> {
+        match self {
+            SectionKind::Hram => 0xff80..=0xfffe,
+            SectionKind::Oam => 0xfe00..=0xfe9f,
+            SectionKind::Rom0 => 0x0000..=0x3fff,
+            SectionKind::Romx => 0x4000..=0x7fff,
+            SectionKind::Sram => 0xa000..=0xbfff,
+            SectionKind::Vram => 0x8000..=0x9fff,
+            SectionKind::Wram0 => 0xc000..=0xcfff,
+            SectionKind::Wramx => 0xd000..=0xdfff,
+        }
+    }
+
+    pub fn banks(self) -> Option<RangeInclusive<i32>> {
+        match self {
+            SectionKind::Romx => Some(1..=511),
+            SectionKind::Sram => Some(0..=15),
+            SectionKind::Vram => Some(0..=1),
+            SectionKind::Wramx => Some(1..=7),
+            SectionKind::Hram | SectionKind::Oam | SectionKind::Rom0 | SectionKind::Wram0 => None,
+        }
+    }
+}
+
 impl VarTable {
     pub fn new() -> Self {
-        Self(Vec::new())
+        Self::default()
     }
 
     pub fn alloc(&mut self) -> VarId {
-        let id = VarId(self.0.len());
-        self.0.push(Default::default());
+        let id = VarId(self.vars.len());
+        self.vars.push(Default::default());
         id
     }
+
+    pub fn len(&self) -> usize {
+        self.vars.len()
+    }
+
+    pub fn refine(&mut self, id: VarId, value: Var) -> bool {
+        self[id] != value && self[id].refine(value)
+    }
+
+    // Closure values only depend on the table, so they stay valid until a var is modified.
+    pub(crate) fn memoize_closures(&mut self, enabled: bool) {
+        self.closure_values = if enabled {
+            Some(RefCell::new(HashMap::new()))
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn closure_value(&self, location: VarId) -> Option<Const> {
+        let values = self.closure_values.as_ref()?;
+        let value = values.borrow().get(&location).cloned();
+        value
+    }
+
+    pub(crate) fn memoize_closure_value(&self, location: VarId, value: &Const) {
+        if let Some(values) = &self.closure_values {
+            values.borrow_mut().insert(location, value.clone());
+        }
+    }
+}
+
+impl From<Vec<Var>> for VarTable {
+    fn from(vars: Vec<Var>) -> Self {
+        Self {
+            vars,
+            closure_values: None,
+        }
+    }
 }
 
 impl Index<VarId> for VarTable {
     type Output = Var;
 
     fn index(&self, VarId(id): VarId) -> &Self::Output {
-        &self.0[id as usize]
+        &self.vars[id]
     }
 }
 
 impl IndexMut<VarId> for VarTable {
     fn index_mut(&mut self, VarId(id): VarId) -> &mut Self::Output {
-        &mut self.0[id as usize]
+        if let Some(values) = &mut self.closure_values {
+            values.get_mut().clear()
+        }
+        &mut self.vars[id]
     }
 }
 
@@ -1,8 +1,15 @@
-use std::ops::{Add, AddAssign, BitOr, Div, Mul, RangeInclusive, Sub};
+use std::convert::TryFrom;
+use std::ops::{
+    Add, AddAssign, BitAnd, BitOr, BitXor, Div, Mul, Neg, Not, RangeInclusive, Rem, Shl, Shr, Sub,
+};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Default)]
 pub enum Var {
-    Range { min: i32, max: i32 },
+    Range {
+        min: i32,
+        max: i32,
+    },
+    #[default]
     Unknown,
 }
 
@@ -14,6 +21,111 @@ impl Var {
         }
     }
 
+    pub fn max(&self, other: &Var) -> Var {
+        match (self, other) {
+            (
+                Var::Range { min, max },
+                Var::Range {
+                    min: other_min,
+                    max: other_max,
+                },
+            ) => Var::Range {
+                min: *min.max(other_min),
+                max: *max.max(other_max),
+            },
+            _ => Var::Unknown,
+        }
+    }
+
+    pub fn min(&self, other: &Var) -> Var {
+        match (self, other) {
+            (
+                Var::Range { min, max },
+                Var::Range {
+                    min: other_min,
+                    max: other_max,
+                },
+            ) => Var::Range {
+                min: *min.min(other_min),
+                max: *max.min(other_max),
+            },
+            _ => Var::Unknown,
+        }
+    }
+
+    pub fn abs(&self) -> Var {
+        match *self {
+            Var::Range { min, .. } if min >= 0 => self.clone(),
+            Var::Range { max, .. } if max <= 0 => -self,
+            Var::Range { min, max } => match min.checked_neg() {
+                Some(neg_min) => (0..=neg_min.max(max)).into(),
+                None => Var::Unknown,
+            },
+            Var::Unknown => Var::Unknown,
+        }
+    }
+
+    pub fn is_equal_to(&self, rhs: &Var) -> Var {
+        match (self, rhs) {
+            (
+                Var::Range { min, max },
+                Var::Range {
+                    min: rhs_min,
+                    max: rhs_max,
+                },
+            ) => {
+                if min == max && rhs_min == rhs_max {
+                    ((min == rhs_min) as i32).into()
+                } else if max < rhs_min || rhs_max < min {
+                    0.into()
+                } else {
+                    (0..=1).into()
+                }
+            }
+            _ => Var::Unknown,
+        }
+    }
+
+    pub fn is_less_than(&self, rhs: &Var) -> Var {
+        match (self, rhs) {
+            (
+                Var::Range { min, max },
+                Var::Range {
+                    min: rhs_min,
+                    max: rhs_max,
+                },
+            ) => {
+                if max < rhs_min {
+                    1.into()
+                } else if min >= rhs_max {
+                    0.into()
+                } else {
+                    (0..=1).into()
+                }
+            }
+            _ => Var::Unknown,
+        }
+    }
+
+    pub fn logical_not(&self) -> Var {
+        match *self {
+            Var::Range { min: 0, max: 0 } => 1.into(),
+            Var::Range { min, max } if min > 0 || max < 0 => 0.into(),
+            Var::Range { .. } => (0..=1).into(),
+            Var::Unknown => Var::Unknown,
+        }
+    }
+
+    pub fn logical_and(&self, rhs: &Var) -> Var {
+        let (lhs, rhs) = (self.logical_not(), rhs.logical_not());
+        match (lhs.exact(), rhs.exact()) {
+            (Some(1), _) | (_, Some(1)) => 0.into(),
+            (Some(0), Some(0)) => 1.into(),
+            _ if lhs != Var::Unknown && rhs != Var::Unknown => (0..=1).into(),
+            _ => Var::Unknown,
+        }
+    }
+
     pub fn refine(&mut self, value: Var) -> bool {
         let old_value = self.clone();
         let was_refined = match (old_value, &value) {
@@ -41,12 +153,6 @@ impl Var {
     }
 }
 
-impl Default for Var {
-    fn default() -> Self {
-        Var::Unknown
-    }
-}
-
 impl From<i32> for Var {
     fn from(n: i32) -> Self {
         Var::Range { min: n, max: n }
@@ -64,19 +170,7 @@ impl From<RangeInclusive<i32>> for Var {
 
 impl AddAssign<&Var> for Var {
     fn add_assign(&mut self, rhs: &Var) {
-        match (self, rhs) {
-            (
-                Var::Range { min, max },
-                Var::Range {
-                    min: rhs_min,
-                    max: rhs_max,
-                },
-            ) => {
-                *min += rhs_min;
-                *max += rhs_max;
-            }
-            (this, _) => *this = Var::Unknown,
-        }
+        *self = combine_endpoints(self, rhs, i32::checked_add)
     }
 }
 
@@ -92,6 +186,22 @@ impl Add for &Var {
 impl Sub for &Var {
     type Output = Var;
     fn sub(self, rhs: &Var) -> Self::Output {
+        combine_endpoints(self, rhs, i32::checked_sub)
+    }
+}
+
+impl Mul for &Var {
+    type Output = Var;
+
+    fn mul(self, rhs: &Var) -> Self::Output {
+        combine_endpoints(self, rhs, i32::checked_mul)
+    }
+}
+
+impl BitOr for &Var {
+    type Output = Var;
+
+    fn bitor(self, rhs: &Var) -> Self::Output {
         match (self, rhs) {
             (
                 Var::Range { min, max },
@@ -99,50 +209,49 @@ impl Sub for &Var {
                     min: rhs_min,
                     max: rhs_max,
                 },
-            ) => Var::Range {
-                min: min - rhs_max,
-                max: max - rhs_min,
-            },
+            ) if min == max && rhs_min == rhs_max => (min | rhs_min).into(),
+            (
+                Var::Range { min, max },
+                Var::Range {
+                    min: rhs_min,
+                    max: rhs_max,
+                },
+            ) if *min >= 0 && *rhs_min >= 0 => {
+                (*min.max(rhs_min)..=bit_mask(*max.max(rhs_max))).into()
+            }
             _ => Var::Unknown,
         }
     }
 }
 
-impl Mul for &Var {
+impl BitAnd for &Var {
     type Output = Var;
 
-    fn mul(self, rhs: &Var) -> Self::Output {
+    fn bitand(self, rhs: &Var) -> Self::Output {
         match (self, rhs) {
             (
+                Var::Range { min, max },
                 Var::Range {
-                    min: lhs_min,
-                    max: lhs_max,
+                    min: rhs_min,
+                    max: rhs_max,
                 },
+            ) if min == max && rhs_min == rhs_max => (min & rhs_min).into(),
+            (
+                Var::Range { min, max },
                 Var::Range {
                     min: rhs_min,
                     max: rhs_max,
                 },
-            ) => {
-                let lhs_endpoints = [lhs_min, lhs_max];
-                let rhs_endpoints = [rhs_min, rhs_max];
-                let products = lhs_endpoints
-                    .iter()
-                    .cloned()
-                    .flat_map(|n| rhs_endpoints.iter().map(move |&m| m * n));
-                Var::Range {
-                    min: products.clone().min().unwrap(),
-                    max: products.max().unwrap(),
-                }
-            }
+            ) if *min >= 0 && *rhs_min >= 0 => (0..=*max.min(rhs_max)).into(),
             _ => Var::Unknown,
         }
     }
 }
 
-impl BitOr for &Var {
+impl BitXor for &Var {
     type Output = Var;
 
-    fn bitor(self, rhs: &Var) -> Self::Output {
+    fn bitxor(self, rhs: &Var) -> Self::Output {
         match (self, rhs) {
             (
                 Var::Range { min, max },
@@ -150,19 +259,143 @@ impl BitOr for &Var {
                     min: rhs_min,
                     max: rhs_max,
                 },
-            ) if min == max && rhs_min == rhs_max => (min | rhs_min).into(),
+            ) if min == max && rhs_min == rhs_max => (min ^ rhs_min).into(),
+            (
+                Var::Range { min, max },
+                Var::Range {
+                    min: rhs_min,
+                    max: rhs_max,
+                },
+            ) if *min >= 0 && *rhs_min >= 0 => (0..=bit_mask(*max.max(rhs_max))).into(),
+            _ => Var::Unknown,
+        }
+    }
+}
+
+fn bit_mask(n: i32) -> i32 {
+    (n as u32 + 1).next_power_of_two().wrapping_sub(1) as i32
+}
+
+impl Rem for &Var {
+    type Output = Var;
+
+    fn rem(self, rhs: &Var) -> Self::Output {
+        match (self, rhs.exact()) {
+            (Var::Range { min, max }, Some(rhs)) if min == max => {
+                min.checked_rem(rhs).map_or(Var::Unknown, Into::into)
+            }
+            (Var::Range { min, max }, Some(rhs)) if *min >= 0 && rhs > 0 => {
+                (0..=*max.min(&(rhs - 1))).into()
+            }
+            _ => Var::Unknown,
+        }
+    }
+}
+
+impl Shl for &Var {
+    type Output = Var;
+
+    fn shl(self, rhs: &Var) -> Self::Output {
+        match (self, rhs.exact()) {
+            (Var::Range { min, max }, Some(rhs)) if (0..32).contains(&rhs) => {
+                let shift = |n: i32| i32::try_from(i64::from(n) << rhs).ok();
+                match (shift(*min), shift(*max)) {
+                    (Some(min), Some(max)) => Var::Range { min, max },
+                    _ => Var::Unknown,
+                }
+            }
+            _ => Var::Unknown,
+        }
+    }
+}
+
+impl Shr for &Var {
+    type Output = Var;
+
+    fn shr(self, rhs: &Var) -> Self::Output {
+        match (self, rhs.exact()) {
+            (Var::Range { min, max }, Some(rhs)) if (0..32).contains(&rhs) => Var::Range {
+                min: min >> rhs,
+                max: max >> rhs,
+            },
             _ => Var::Unknown,
         }
     }
 }
 
+impl Neg for &Var {
+    type Output = Var;
+
+    fn neg(self) -> Self::Output {
+        match self {
+            Var::Range { min, max } => match (max.checked_neg(), min.checked_neg()) {
+                (Some(min), Some(max)) => Var::Range { min, max },
+                _ => Var::Unknown,
+            },
+            Var::Unknown => Var::Unknown,
+        }
+    }
+}
+
+impl Not for &Var {
+    type Output = Var;
+
+    fn not(self) -> Self::Output {
+        match self {
+            Var::Range { min, max } => Var::Range {
+                min: !max,
+                max: !min,
+            },
+            Var::Unknown => Var::Unknown,
+        }
+    }
+}
+
 impl Div for &Var {
     type Output = Var;
 
     fn div(self, rhs: &Var) -> Self::Output {
-        self.exact()
-            .and_then(|lhs| rhs.exact().map(|rhs| lhs / rhs))
-            .map_or(Var::Unknown, Into::into)
+        match rhs {
+            Var::Range { min, max } if *min > 0 || *max < 0 => {
+                combine_endpoints(self, rhs, i32::checked_div)
+            }
+            _ => Var::Unknown,
+        }
+    }
+}
+
+// Only valid for operations that are monotonic in each operand over the given ranges.
+fn combine_endpoints(lhs: &Var, rhs: &Var, f: fn(i32, i32) -> Option<i32>) -> Var {
+    match (lhs, rhs) {
+        (
+            Var::Range {
+                min: lhs_min,
+                max: lhs_max,
+            },
+            Var::Range {
+                min: rhs_min,
+                max: rhs_max,
+            },
+        ) => {
+            let pairs = [
+                (lhs_min, rhs_min),
+                (lhs_min, rhs_max),
+                (lhs_max, rhs_min),
+                (lhs_max, rhs_max),
+            ];
+            let mut results = [0; 4];
+            for (result, &(&n, &m)) in results.iter_mut().zip(&pairs) {
+                match f(n, m) {
+                    Some(value) => *result = value,
+                    None => return Var::Unknown,
+                }
+            }
+            Var::Range {
+                min: *results.iter().min().unwrap(),
+                max: *results.iter().max().unwrap(),
+            }
+        }
+        _ => Var::Unknown,
     }
 }
 
@@ -194,8 +427,132 @@ mod tests {
         assert_eq!(&Var::from(0x12) | &Var::from(0x34), Var::from(0x36))
     }
 
+    #[test]
+    fn max_of_ranges() {
+        let cases: &[(Var, Var, Var)] =
+            &triples![(2, 3, 3), (1..=4, 2..=3, 2..=4), (0..=1, 5..=6, 5..=6)];
+        for (lhs, rhs, max) in cases {
+            assert_eq!(lhs.max(rhs), *max)
+        }
+    }
+
+    #[test]
+    fn min_of_ranges() {
+        let cases: &[(Var, Var, Var)] = &triples![
+            (2, 3, 2),
+            (1..=4, 2..=3, 1..=3),
+            (0..=1, 5..=6, 0..=1),
+            (i32::MIN, 5, i32::MIN)
+        ];
+        for (lhs, rhs, min) in cases {
+            assert_eq!(lhs.min(rhs), *min)
+        }
+    }
+
+    #[test]
+    fn abs_of_ranges() {
+        assert_eq!(Var::from(-5).abs(), Var::from(5));
+        assert_eq!(Var::from(-3..=-1).abs(), Var::from(1..=3));
+        assert_eq!(Var::from(-2..=7).abs(), Var::from(0..=7));
+        assert_eq!(Var::from(i32::MIN).abs(), Var::Unknown)
+    }
+
+    #[test]
+    fn bitwise_and_non_negative_ranges() {
+        assert_eq!(&Var::from(0..=0x7f) & &Var::from(0x0f), Var::from(0..=0x0f))
+    }
+
+    #[test]
+    fn bitwise_xor_non_negative_ranges() {
+        assert_eq!(&Var::from(0..=5) ^ &Var::from(1..=2), Var::from(0..=7))
+    }
+
+    #[test]
+    fn shift_range_by_exact_amount() {
+        assert_eq!(&Var::from(1..=3) << &Var::from(4), Var::from(0x10..=0x30));
+        assert_eq!(&Var::from(0x10..=0x30) >> &Var::from(4), Var::from(1..=3))
+    }
+
+    #[test]
+    fn shift_out_of_range_is_unknown() {
+        assert_eq!(&Var::from(1) << &Var::from(32), Var::Unknown);
+        assert_eq!(&Var::from(i32::MAX) << &Var::from(1), Var::Unknown)
+    }
+
+    #[test]
+    fn remainder_of_non_negative_range() {
+        assert_eq!(&Var::from(0..=100) % &Var::from(8), Var::from(0..=7))
+    }
+
+    #[test]
+    fn remainder_by_zero_is_unknown() {
+        assert_eq!(&Var::from(1) % &Var::from(0), Var::Unknown)
+    }
+
+    #[test]
+    fn negate_range() {
+        assert_eq!(-&Var::from(-1..=3), Var::from(-3..=1))
+    }
+
+    #[test]
+    fn complement_range() {
+        assert_eq!(!&Var::from(0..=3), Var::from(-4..=-1))
+    }
+
+    #[test]
+    fn compare_disjoint_ranges() {
+        assert_eq!(
+            Var::from(0..=3).is_less_than(&Var::from(4..=5)),
+            Var::from(1)
+        );
+        assert_eq!(
+            Var::from(0..=3).is_equal_to(&Var::from(4..=5)),
+            Var::from(0)
+        )
+    }
+
+    #[test]
+    fn compare_overlapping_ranges() {
+        assert_eq!(
+            Var::from(0..=4).is_less_than(&Var::from(4..=5)),
+            Var::from(0..=1)
+        )
+    }
+
+    #[test]
+    fn logical_and_short_circuits_on_known_false() {
+        assert_eq!(Var::from(0).logical_and(&Var::Unknown), Var::from(0))
+    }
+
     #[test]
     fn div_exact_nums() {
         assert_eq!(&Var::from(72) / &Var::from(5), Var::from(14))
     }
+
+    #[test]
+    fn divide_ranges() {
+        let cases: &[(Var, Var, Var)] = &triples![
+            (10..=20, 2..=5, 2..=10),
+            (-20..=10, 5, -4..=2),
+            (10..=20, -5..=-2, -10..=-2)
+        ];
+        for (lhs, rhs, quotient) in cases {
+            assert_eq!(lhs / rhs, *quotient)
+        }
+    }
+
+    #[test]
+    fn divisor_range_including_zero_is_unknown() {
+        assert_eq!(&Var::from(1) / &Var::from(0), Var::Unknown);
+        assert_eq!(&Var::from(10) / &Var::from(-1..=1), Var::Unknown)
+    }
+
+    #[test]
+    fn overflow_is_unknown() {
+        assert_eq!(&Var::from(i32::MAX) + &Var::from(1), Var::Unknown);
+        assert_eq!(&Var::from(0..=i32::MAX) + &Var::from(1), Var::Unknown);
+        assert_eq!(&Var::from(i32::MIN) - &Var::from(1), Var::Unknown);
+        assert_eq!(&Var::from(0x10000) * &Var::from(0x10000), Var::Unknown);
+        assert_eq!(&Var::from(i32::MIN) / &Var::from(-1), Var::Unknown)
+    }
 }
@@ -1,5 +1,12 @@
+use crate::diagnostics::{Clause, Diagnostic, Tag};
+use crate::{Config, DiagnosticsConfig};
+
+use std::ops::Range;
+
 pub struct Program {
     pub sections: Box<[Chunk]>,
+    pub reserved: Box<[Range<usize>]>,
+    pub discarded: Box<[Box<str>]>,
 }
 
 pub struct Chunk {
@@ -7,27 +14,278 @@ pub struct Chunk {
     pub data: Box<[u8]>,
 }
 
+/// A range of a ROM bank that holds neither data nor reserved space.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gap {
+    pub bank: usize,
+    pub addr: u16,
+    pub len: usize,
+}
+
+/// Options for turning a [`Program`] into a ROM image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RomOptions {
+    pub fill: u8,
+    pub size: RomSize,
+    pub header: Option<Header>,
+}
+
+impl Default for RomOptions {
+    fn default() -> Self {
+        Self {
+            fill: 0xff,
+            size: RomSize::default(),
+            header: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RomSize {
+    /// Pads the image to at least 32 KiB.
+    #[default]
+    Min,
+    /// Pads the image to the next power of two of at least 32 KiB, as expected by MBC cartridges.
+    PowerOfTwo,
+    /// Pads the image to exactly the given number of bytes.
+    Fixed(usize),
+}
+
+/// Cartridge header fields written when [`RomOptions::header`] is set.
+///
+/// The title is truncated to 16 bytes, or to 15 bytes when `cgb_flag` is set.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Header {
+    pub title: Box<str>,
+    pub cgb_flag: u8,
+    pub sgb_flag: u8,
+    pub cartridge_type: u8,
+    pub rom_size: u8,
+    pub ram_size: u8,
+}
+
 impl Program {
     pub fn into_rom(self) -> Box<[u8]> {
-        let default = 0xffu8;
+        // Building with the default options cannot fail.
+        self.build_rom(&RomOptions::default(), &mut Config::default())
+            .unwrap()
+    }
+
+    /// Generates a ROM image as described by `options`, filling in the cartridge header and both
+    /// checksums if a header is given. Fails when a fixed size is not a whole number of banks of at
+    /// least 32 KiB or is exceeded by the content, and warns when the ROM size declared in the
+    /// header doesn't match the image.
+    pub fn build_rom(self, options: &RomOptions, config: &mut Config) -> Option<Box<[u8]>> {
+        if let RomSize::Fixed(size) = options.size {
+            if size < MIN_ROM_LEN || size % BANK_LEN != 0 {
+                let message = format!(
+                    "target size of {} bytes must be a multiple of {} bytes and at least {} bytes",
+                    size, BANK_LEN, MIN_ROM_LEN
+                );
+                emit_rom_diag(config, Tag::Error, message);
+                return None;
+            }
+        }
+        let mut rom = self.into_image(options.fill);
+        let len = match options.size {
+            RomSize::Min => rom.len().max(MIN_ROM_LEN),
+            RomSize::PowerOfTwo => rom.len().max(MIN_ROM_LEN).next_power_of_two(),
+            RomSize::Fixed(size) if rom.len() <= size => size,
+            RomSize::Fixed(size) => {
+                let message = format!(
+                    "ROM content of {} bytes exceeds target size of {} bytes",
+                    rom.len(),
+                    size
+                );
+                emit_rom_diag(config, Tag::Error, message);
+                return None;
+            }
+        };
+        rom.resize(len, options.fill);
+        if let Some(header) = &options.header {
+            header.write(&mut rom);
+            let declared = match header.rom_size {
+                code @ 0..=8 => Some(MIN_ROM_LEN << code),
+                _ => None,
+            };
+            if declared != Some(rom.len()) {
+                let message = format!(
+                    "ROM size code ${:02X} does not match image size of {} bytes",
+                    header.rom_size,
+                    rom.len()
+                );
+                emit_rom_diag(config, Tag::Warning, message)
+            }
+        }
+        Some(rom.into_boxed_slice())
+    }
+
+    /// Builds the ROM image like [`Program::build_rom`] and splits it into one binary per bank.
+    pub fn build_banks(
+        self,
+        options: &RomOptions,
+        config: &mut Config,
+    ) -> Option<Box<[Box<[u8]>]>> {
+        let rom = self.build_rom(options, config)?;
+        Some(rom.chunks(BANK_LEN).map(Box::from).collect())
+    }
+
+    /// Builds the ROM image like [`Program::build_rom`] and encodes it as Intel HEX. Every bank
+    /// starts with an extended segment address record, so data records address it from zero.
+    pub fn build_hex(self, options: &RomOptions, config: &mut Config) -> Option<String> {
+        let rom = self.build_rom(options, config)?;
+        if rom.len() > MAX_HEX_LEN {
+            let message = format!(
+                "ROM of {} bytes is too large for Intel HEX segment addressing",
+                rom.len()
+            );
+            emit_rom_diag(config, Tag::Error, message);
+            return None;
+        }
+        let mut hex = String::new();
+        for (bank, data) in rom.chunks(BANK_LEN).enumerate() {
+            let segment = ((bank * BANK_LEN) >> 4) as u16;
+            push_hex_record(
+                &mut hex,
+                0,
+                HEX_EXTENDED_SEGMENT_ADDR,
+                &segment.to_be_bytes(),
+            );
+            for (index, record) in data.chunks(HEX_RECORD_LEN).enumerate() {
+                let addr = (index * HEX_RECORD_LEN) as u16;
+                push_hex_record(&mut hex, addr, HEX_DATA, record)
+            }
+        }
+        push_hex_record(&mut hex, 0, HEX_END_OF_FILE, &[]);
+        Some(hex)
+    }
+
+    /// Lists the ranges of every ROM bank that are covered by neither a chunk nor reserved space.
+    pub fn gaps(&self) -> Vec<Gap> {
+        let mut used: Vec<_> = self
+            .sections
+            .iter()
+            .map(|chunk| chunk.addr..chunk.addr + chunk.data.len())
+            .chain(self.reserved.iter().cloned())
+            .filter(|range| !range.is_empty())
+            .collect();
+        used.sort_by_key(|range| range.start);
+        let end = used
+            .iter()
+            .map(|range| range.end)
+            .fold(MIN_ROM_LEN, usize::max);
+        let end = end.div_ceil(BANK_LEN) * BANK_LEN;
+        let mut gaps = Vec::new();
+        let mut offset = 0;
+        for range in used.into_iter().chain(Some(end..end)) {
+            while offset < range.start {
+                let bank = offset / BANK_LEN;
+                let gap_end = range.start.min((bank + 1) * BANK_LEN);
+                gaps.push(Gap {
+                    bank,
+                    addr: (offset % BANK_LEN + if bank == 0 { 0 } else { BANK_LEN }) as u16,
+                    len: gap_end - offset,
+                });
+                offset = gap_end
+            }
+            offset = offset.max(range.end)
+        }
+        gaps
+    }
+
+    fn into_image(self, fill: u8) -> Vec<u8> {
         let mut rom: Vec<u8> = Vec::new();
         for section in Vec::from(self.sections) {
             if !section.data.is_empty() {
                 let end = section.addr + section.data.len();
                 if rom.len() < end {
-                    rom.resize(end, default)
+                    rom.resize(end, fill)
                 }
                 rom[section.addr..end].copy_from_slice(&section.data)
             }
         }
-        if rom.len() < MIN_ROM_LEN {
-            rom.resize(MIN_ROM_LEN, default)
-        }
-        rom.into_boxed_slice()
+        rom
+    }
+}
+
+fn push_hex_record(hex: &mut String, addr: u16, kind: u8, data: &[u8]) {
+    let [addr_high, addr_low] = addr.to_be_bytes();
+    let mut bytes = vec![data.len() as u8, addr_high, addr_low, kind];
+    bytes.extend_from_slice(data);
+    let checksum = bytes.iter().fold(0u8, |sum, byte| sum.wrapping_sub(*byte));
+    hex.push(':');
+    for byte in bytes.iter().chain(Some(&checksum)) {
+        hex.push_str(&format!("{:02X}", byte))
+    }
+    hex.push('\n')
+}
+
+fn emit_rom_diag(config: &mut Config, tag: Tag, message: String) {
+    if let DiagnosticsConfig::Output(output) = &mut config.diagnostics {
+        output(Diagnostic {
+            clauses: vec![Clause {
+                file: "<rom>".into(),
+                tag,
+                message,
+                excerpt: None,
+            }],
+        })
     }
 }
 
 const MIN_ROM_LEN: usize = 0x8000;
+const BANK_LEN: usize = 0x4000;
+
+const HEX_RECORD_LEN: usize = 16;
+const HEX_DATA: u8 = 0x00;
+const HEX_END_OF_FILE: u8 = 0x01;
+const HEX_EXTENDED_SEGMENT_ADDR: u8 = 0x02;
+const MAX_HEX_LEN: usize = 0x10_0000;
+
+const LOGO_ADDR: usize = 0x104;
+const TITLE_ADDR: usize = 0x134;
+const CGB_FLAG_ADDR: usize = 0x143;
+const SGB_FLAG_ADDR: usize = 0x146;
+const CARTRIDGE_TYPE_ADDR: usize = 0x147;
+const ROM_SIZE_ADDR: usize = 0x148;
+const RAM_SIZE_ADDR: usize = 0x149;
+const HEADER_CHECKSUM_ADDR: usize = 0x14d;
+const GLOBAL_CHECKSUM_ADDR: usize = 0x14e;
+
+const LOGO: [u8; 48] = [
+    0xce, 0xed, 0x66, 0x66, 0xcc, 0x0d, 0x00, 0x0b, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0c, 0x00, 0x0d,
+    0x00, 0x08, 0x11, 0x1f, 0x88, 0x89, 0x00, 0x0e, 0xdc, 0xcc, 0x6e, 0xe6, 0xdd, 0xdd, 0xd9, 0x99,
+    0xbb, 0xbb, 0x67, 0x63, 0x6e, 0x0e, 0xec, 0xcc, 0xdd, 0xdc, 0x99, 0x9f, 0xbb, 0xb9, 0x33, 0x3e,
+];
+
+impl Header {
+    fn write(&self, rom: &mut [u8]) {
+        rom[LOGO_ADDR..LOGO_ADDR + LOGO.len()].copy_from_slice(&LOGO);
+        let title_len = if self.cgb_flag == 0 { 16 } else { 15 };
+        let title = &mut rom[TITLE_ADDR..TITLE_ADDR + title_len];
+        title.fill(0);
+        for (dest, byte) in title.iter_mut().zip(self.title.bytes()) {
+            *dest = byte
+        }
+        if self.cgb_flag != 0 {
+            rom[CGB_FLAG_ADDR] = self.cgb_flag
+        }
+        rom[SGB_FLAG_ADDR] = self.sgb_flag;
+        rom[CARTRIDGE_TYPE_ADDR] = self.cartridge_type;
+        rom[ROM_SIZE_ADDR] = self.rom_size;
+        rom[RAM_SIZE_ADDR] = self.ram_size;
+        rom[HEADER_CHECKSUM_ADDR] = rom[TITLE_ADDR..HEADER_CHECKSUM_ADDR]
+            .iter()
+            .fold(0u8, |sum, byte| sum.wrapping_sub(*byte).wrapping_sub(1));
+        let global_checksum = rom
+            .iter()
+            .enumerate()
+            .filter(|(addr, _)| !(GLOBAL_CHECKSUM_ADDR..GLOBAL_CHECKSUM_ADDR + 2).contains(addr))
+            .fold(0u16, |sum, (_, byte)| sum.wrapping_add((*byte).into()));
+        rom[GLOBAL_CHECKSUM_ADDR..GLOBAL_CHECKSUM_ADDR + 2]
+            .copy_from_slice(&global_checksum.to_be_bytes())
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -37,6 +295,8 @@ mod tests {
     fn empty_object_converted_to_all_0xff_rom() {
         let object = Program {
             sections: Box::new([]),
+            reserved: Box::new([]),
+            discarded: Box::new([]),
         };
         let rom = object.into_rom();
         assert_eq!(*rom, [0xffu8; MIN_ROM_LEN][..])
@@ -51,6 +311,8 @@ mod tests {
                 addr,
                 data: Box::new([byte]),
             }]),
+            reserved: Box::new([]),
+            discarded: Box::new([]),
         };
         let rom = object.into_rom();
         let mut expected = [0xffu8; MIN_ROM_LEN];
@@ -66,8 +328,253 @@ mod tests {
                 addr,
                 data: Box::new([]),
             }]),
+            reserved: Box::new([]),
+            discarded: Box::new([]),
         };
         let rom = object.into_rom();
         assert_eq!(rom.len(), MIN_ROM_LEN)
     }
+
+    #[test]
+    fn write_header_fields_and_checksums() {
+        let object = Program {
+            sections: Box::new([]),
+            reserved: Box::new([]),
+            discarded: Box::new([]),
+        };
+        let header = Header {
+            title: "TEST".into(),
+            ..Header::default()
+        };
+        let options = RomOptions {
+            header: Some(header),
+            ..RomOptions::default()
+        };
+        let rom = object.build_rom(&options, &mut Config::default()).unwrap();
+        assert_eq!(rom[LOGO_ADDR..LOGO_ADDR + LOGO.len()], LOGO);
+        assert_eq!(rom[TITLE_ADDR..TITLE_ADDR + 5], *b"TEST\0");
+        assert_eq!(rom[HEADER_CHECKSUM_ADDR], 0xac);
+        let sum = rom
+            .iter()
+            .fold(0u16, |sum, byte| sum.wrapping_add((*byte).into()))
+            .wrapping_sub(rom[GLOBAL_CHECKSUM_ADDR].into())
+            .wrapping_sub(rom[GLOBAL_CHECKSUM_ADDR + 1].into());
+        assert_eq!(
+            rom[GLOBAL_CHECKSUM_ADDR..GLOBAL_CHECKSUM_ADDR + 2],
+            sum.to_be_bytes()
+        )
+    }
+
+    #[test]
+    fn cgb_flag_shortens_title() {
+        let object = Program {
+            sections: Box::new([]),
+            reserved: Box::new([]),
+            discarded: Box::new([]),
+        };
+        let header = Header {
+            title: "ABCDEFGHIJKLMNOPQ".into(),
+            cgb_flag: 0x80,
+            ..Header::default()
+        };
+        let options = RomOptions {
+            header: Some(header),
+            ..RomOptions::default()
+        };
+        let rom = object.build_rom(&options, &mut Config::default()).unwrap();
+        assert_eq!(rom[TITLE_ADDR..=CGB_FLAG_ADDR], *b"ABCDEFGHIJKLMNO\x80")
+    }
+
+    #[test]
+    fn warn_about_rom_size_mismatch() {
+        let object = Program {
+            sections: Box::new([]),
+            reserved: Box::new([]),
+            discarded: Box::new([]),
+        };
+        let header = Header {
+            rom_size: 0x01,
+            ..Header::default()
+        };
+        let mut diagnostics = Vec::new();
+        let mut output = |diagnostic: Diagnostic| diagnostics.push(diagnostic.to_string());
+        let mut config = Config {
+            diagnostics: DiagnosticsConfig::Output(&mut output),
+            ..Config::default()
+        };
+        let options = RomOptions {
+            header: Some(header),
+            ..RomOptions::default()
+        };
+        object.build_rom(&options, &mut config);
+        assert_eq!(
+            diagnostics,
+            ["<rom>: warning: ROM size code $01 does not match image size of 32768 bytes\n"]
+        )
+    }
+
+    #[test]
+    fn pad_with_fill_byte_to_power_of_two() {
+        let object = Program {
+            sections: Box::new([Chunk {
+                addr: MIN_ROM_LEN,
+                data: Box::new([0x42]),
+            }]),
+            reserved: Box::new([]),
+            discarded: Box::new([]),
+        };
+        let options = RomOptions {
+            fill: 0x00,
+            size: RomSize::PowerOfTwo,
+            header: None,
+        };
+        let rom = object.build_rom(&options, &mut Config::default()).unwrap();
+        let mut expected = vec![0x00; 2 * MIN_ROM_LEN];
+        expected[MIN_ROM_LEN] = 0x42;
+        assert_eq!(*rom, *expected)
+    }
+
+    #[test]
+    fn pad_to_fixed_size() {
+        let object = Program {
+            sections: Box::new([]),
+            reserved: Box::new([]),
+            discarded: Box::new([]),
+        };
+        let options = RomOptions {
+            size: RomSize::Fixed(0x20000),
+            ..RomOptions::default()
+        };
+        let rom = object.build_rom(&options, &mut Config::default()).unwrap();
+        assert_eq!(*rom, [0xff; 0x20000][..])
+    }
+
+    #[test]
+    fn diagnose_content_exceeding_fixed_size() {
+        let object = Program {
+            sections: Box::new([Chunk {
+                addr: MIN_ROM_LEN,
+                data: Box::new([0x42]),
+            }]),
+            reserved: Box::new([]),
+            discarded: Box::new([]),
+        };
+        let options = RomOptions {
+            size: RomSize::Fixed(MIN_ROM_LEN),
+            ..RomOptions::default()
+        };
+        let mut diagnostics = Vec::new();
+        let mut output = |diagnostic: Diagnostic| diagnostics.push(diagnostic.to_string());
+        let mut config = Config {
+            diagnostics: DiagnosticsConfig::Output(&mut output),
+            ..Config::default()
+        };
+        assert!(object.build_rom(&options, &mut config).is_none());
+        assert_eq!(
+            diagnostics,
+            ["<rom>: error: ROM content of 32769 bytes exceeds target size of 32768 bytes\n"]
+        )
+    }
+
+    #[test]
+    fn diagnose_invalid_fixed_size() {
+        for &size in &[0x100, MIN_ROM_LEN + 1] {
+            let object = Program {
+                sections: Box::new([]),
+                reserved: Box::new([]),
+                discarded: Box::new([]),
+            };
+            let options = RomOptions {
+                size: RomSize::Fixed(size),
+                header: Some(Header::default()),
+                ..RomOptions::default()
+            };
+            let mut diagnostics = Vec::new();
+            let mut output = |diagnostic: Diagnostic| diagnostics.push(diagnostic.to_string());
+            let mut config = Config {
+                diagnostics: DiagnosticsConfig::Output(&mut output),
+                ..Config::default()
+            };
+            assert!(object.build_rom(&options, &mut config).is_none());
+            assert_eq!(
+                diagnostics,
+                [format!(
+                    "<rom>: error: target size of {} bytes must be a multiple of 16384 bytes and at least 32768 bytes\n",
+                    size
+                )]
+            )
+        }
+    }
+
+    #[test]
+    fn split_rom_into_banks() {
+        let object = Program {
+            sections: Box::new([Chunk {
+                addr: BANK_LEN,
+                data: Box::new([0x42]),
+            }]),
+            reserved: Box::new([]),
+            discarded: Box::new([]),
+        };
+        let banks = object
+            .build_banks(&RomOptions::default(), &mut Config::default())
+            .unwrap();
+        assert_eq!(banks.len(), 2);
+        assert_eq!(*banks[0], [0xff; BANK_LEN][..]);
+        assert_eq!(banks[1][..2], [0x42, 0xff])
+    }
+
+    #[test]
+    fn encode_intel_hex_with_segment_per_bank() {
+        let object = Program {
+            sections: Box::new([Chunk {
+                addr: 0,
+                data: Box::new([0x42]),
+            }]),
+            reserved: Box::new([]),
+            discarded: Box::new([]),
+        };
+        let hex = object
+            .build_hex(&RomOptions::default(), &mut Config::default())
+            .unwrap();
+        let lines: Vec<_> = hex.lines().collect();
+        assert_eq!(lines.len(), 2 + 2 * BANK_LEN / HEX_RECORD_LEN + 1);
+        assert_eq!(lines[0], ":020000020000FC");
+        assert_eq!(lines[1], ":1000000042FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFBD");
+        assert_eq!(lines[2], ":10001000FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF0");
+        assert_eq!(lines[1 + BANK_LEN / HEX_RECORD_LEN], ":020000020400F8");
+        assert_eq!(lines[lines.len() - 1], ":00000001FF")
+    }
+
+    #[test]
+    fn report_gaps_per_bank() {
+        let object = Program {
+            sections: Box::new([Chunk {
+                addr: 0x100,
+                data: Box::new([0x00, 0xc3, 0x50, 0x01]),
+            }]),
+            reserved: Box::new([0x4000..0x4010, 0x7ff0..0x8000]),
+            discarded: Box::new([]),
+        };
+        assert_eq!(
+            object.gaps(),
+            [
+                Gap {
+                    bank: 0,
+                    addr: 0x0000,
+                    len: 0x100
+                },
+                Gap {
+                    bank: 0,
+                    addr: 0x0104,
+                    len: 0x3efc
+                },
+                Gap {
+                    bank: 1,
+                    addr: 0x4010,
+                    len: 0x3fe0
+                },
+            ]
+        )
+    }
 }
@@ -94,16 +94,6 @@ fn merge_macro_expansion_ranges(
     left.start().clone()..=right.end().clone()
 }
 
-impl Default for SpanData {
-    fn default() -> Self {
-        Self {
-            source_file_inclusions: Vec::default(),
-            macro_defs: Vec::default(),
-            macro_expansions: Vec::default(),
-        }
-    }
-}
-
 impl SpanSource for SpanData {
     type Span = Span;
 }
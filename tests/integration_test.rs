@@ -79,6 +79,711 @@ MY_MAC(OP)
     )
 }
 
+#[test]
+fn branch_to_anonymous_and_numeric_labels() {
+    let src = r"
+:       DEC     B
+        JR      NZ, :-
+        JR      Z, 1f
+        NOP
+1:      JR      :+
+:       RET";
+    let binary = [
+        0x05, // DEC B
+        0x20, 0xfd, // JR NZ, :-
+        0x28, 0x01, // JR Z, 1f
+        0x00, // NOP
+        0x18, 0x00, // JR :+
+        0xc9, // RET
+    ];
+    let (assembled, diagnostics) = assemble_snippet(src);
+    assert_eq!(diagnostics, []);
+    assert_eq!(*assembled.unwrap(), binary)
+}
+
+#[test]
+fn relax_jumps_to_near_targets() {
+    let src = r"
+START   NOP
+        JMP     NZ, NEXT
+        JMP     START
+NEXT    RET";
+    let binary = [
+        0x00, // NOP
+        0x20, 0x02, // JR NZ, NEXT
+        0x18, 0xfb, // JR START
+        0xc9, // RET
+    ];
+    let (assembled, diagnostics) = assemble_snippet(src);
+    assert_eq!(diagnostics, []);
+    assert_eq!(*assembled.unwrap(), binary)
+}
+
+#[test]
+fn relax_jump_to_far_target() {
+    let src = r"
+FAR     RET
+        DS      200
+        JMP     FAR";
+    let (assembled, diagnostics) = assemble_snippet(src);
+    assert_eq!(diagnostics, []);
+    assert_eq!(*assembled.unwrap(), [0xc3, 0x00, 0x00])
+}
+
+#[test]
+fn resolve_chain_of_relaxed_jumps() {
+    let src = r"
+        JMP     NEXT
+        JMP     NEXT
+        JMP     NEXT
+        JMP     NEXT
+        JMP     NEXT
+        DS      118
+NEXT    JMP     END
+END     RET";
+    let (assembled, diagnostics) = assemble_snippet(src);
+    assert_eq!(diagnostics, []);
+    assert_eq!(*assembled.unwrap(), [0x18, 0x00, 0xc9])
+}
+
+#[test]
+fn widen_mutually_dependent_jumps() {
+    let src = r"
+L1      JMP     L2
+        DS      124
+        JMP     L1
+L2      DW      L2";
+    let binary = [
+        0xc3, 0x00, 0x00, // JP L1
+        0x82, 0x00, // DW L2
+    ];
+    let (assembled, diagnostics) = assemble_snippet(src);
+    assert_eq!(diagnostics, []);
+    assert_eq!(*assembled.unwrap(), binary)
+}
+
+#[test]
+fn diagnose_ambiguous_size() {
+    let (assembled, diagnostics) = assemble_snippet("X       DS      Y - X\nY       NOP\n");
+    assert_eq!(assembled, None);
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:1: error: size of item is ambiguous and cannot be resolved\nX       DS      Y - X\n                  ^\n"]
+    )
+}
+
+#[test]
+fn labels_in_load_block_relative_to_load_addr() {
+    let src = r"
+        LOAD    WRAM0($C000)
+        NOP
+target  NOP
+        ENDL
+        JP      target";
+    let binary = [
+        0x00, // NOP
+        0x00, // NOP
+        0xc3, 0x01, 0xc0, // JP target
+    ];
+    let (assembled, diagnostics) = assemble_snippet(src);
+    assert_eq!(diagnostics, []);
+    assert_eq!(*assembled.unwrap(), binary)
+}
+
+#[test]
+fn load_block_reserves_ram() {
+    let src = r"
+        LOAD    WRAM0
+        NOP
+        NOP
+        ENDL
+vars    SECTION WRAM0
+flag    DS      1
+code    SECTION ROM0
+        DW      flag";
+    let (assembled, diagnostics) = assemble_snippet(src);
+    assert_eq!(diagnostics, []);
+    assert_eq!(*assembled.unwrap(), [0x02, 0xc0])
+}
+
+#[test]
+fn overlay_union_alternatives() {
+    let src = r"
+vars    SECTION WRAM0($C000)
+        UNION
+alpha   DS      2
+        NEXTU
+beta    DS      1
+gamma   DS      4
+        ENDU
+delta   DS      1
+code    SECTION ROM0($0000)
+        DW      gamma
+        DW      delta";
+    let (assembled, diagnostics) = assemble_snippet(src);
+    assert_eq!(diagnostics, []);
+    assert_eq!(*assembled.unwrap(), [0x01, 0xc0, 0x05, 0xc0])
+}
+
+#[test]
+fn eval_c_like_operators() {
+    let src = r"
+        DB      (1 << 4) | ($F0 & $3C) ^ 1, -1 & $FF, ~$0F & $FF, 17 % 5
+        DB      1 < 2 && 3 >= 3, !(2 != 2) || 0, 8 >> 2 > 1";
+    let (assembled, diagnostics) = assemble_snippet(src);
+    assert_eq!(diagnostics, []);
+    assert_eq!(
+        *assembled.unwrap(),
+        [0x31, 0xff, 0xf0, 0x02, 0x01, 0x01, 0x01]
+    )
+}
+
+#[test]
+fn eval_builtin_functions() {
+    let src = r"
+code    SECTION ROM0($150)
+        DB      HIGH($1234), low($1234), MIN(3, 5), max(3, 5), ABS(-2)
+        DB      DEF(code), def(nothing), LOW(STARTOF(code)), LOW(ENDOF(code))";
+    let (assembled, diagnostics) = assemble_snippet(src);
+    assert_eq!(diagnostics, []);
+    assert_eq!(
+        *assembled.unwrap(),
+        [0x12, 0x34, 0x03, 0x05, 0x02, 0x01, 0x00, 0x50, 0x59]
+    )
+}
+
+#[test]
+fn generate_sine_table_with_fixed_point_builtins() {
+    let src = r"
+AMPLITUDE EQU 100.0
+        DB      ROUND(MUL(SIN(0.0), AMPLITUDE)) >> 16
+        DB      ROUND(MUL(SIN(0.125), AMPLITUDE)) >> 16
+        DB      ROUND(MUL(SIN(0.25), AMPLITUDE)) >> 16
+        DB      ROUND(MUL(COS(0.5), AMPLITUDE)) >> 16 & $FF";
+    let (assembled, diagnostics) = assemble_snippet(src);
+    assert_eq!(diagnostics, []);
+    assert_eq!(*assembled.unwrap(), [0, 71, 100, 0x9c])
+}
+
+#[test]
+fn assemble_literals_in_every_radix() {
+    let src = r"
+        DB      %1010_0101, 0b11, &17, $FF, 0x10, 'A', 1_000 % 256";
+    let (assembled, diagnostics) = assemble_snippet(src);
+    assert_eq!(diagnostics, []);
+    assert_eq!(
+        *assembled.unwrap(),
+        [0xa5, 0x03, 0x0f, 0xff, 0x10, 0x41, 0xe8]
+    )
+}
+
+#[test]
+fn assemble_unspaced_and_and_modulo() {
+    let src = r"
+X       EQU     6
+        DB      7&3, X&3, 7 &3, X%1, (X)%4, X % %11";
+    let (assembled, diagnostics) = assemble_snippet(src);
+    assert_eq!(diagnostics, []);
+    assert_eq!(*assembled.unwrap(), [3, 2, 3, 0, 2, 0])
+}
+
+#[test]
+fn call_user_defined_function() {
+    let src = r"
+TILE_ADDR(n) EQU $8000 + n * 16
+        DW      TILE_ADDR(2), TILE_ADDR(TILE_ADDR(0) - $7FFF)";
+    let (assembled, diagnostics) = assemble_snippet(src);
+    assert_eq!(diagnostics, []);
+    assert_eq!(*assembled.unwrap(), [0x20, 0x80, 0x10, 0x80])
+}
+
+#[test]
+fn call_function_defined_in_another_module() {
+    let mut fs = Buffers(&[
+        ("defs.s", "TILE_ADDR(n) EQU $8000 + n * 16\n"),
+        ("main.s", "        DW      TILE_ADDR(3)\n"),
+    ]);
+    let mut config = Config {
+        input: InputConfig::Custom(&mut fs),
+        diagnostics: DiagnosticsConfig::Ignore,
+        print: PrintConfig::default(),
+    };
+    let objects: Vec<_> = ["defs.s", "main.s"]
+        .iter()
+        .map(|name| Assembler::new(&mut config).assemble(name).unwrap())
+        .collect();
+    let program = Linker::new(&mut config).link(objects).unwrap();
+    assert_eq!(
+        *Vec::from(program.sections).pop().unwrap().data,
+        [0x30, 0x80]
+    )
+}
+
+#[test]
+fn link_combined_object() {
+    let mut fs = Buffers(&[
+        ("defs.s", "TILE_ADDR(n) EQU $8000 + n * 16\n"),
+        ("main.s", "        DW      TILE_ADDR(3)\n"),
+    ]);
+    let mut config = Config {
+        input: InputConfig::Custom(&mut fs),
+        diagnostics: DiagnosticsConfig::Ignore,
+        print: PrintConfig::default(),
+    };
+    let objects: Vec<_> = ["defs.s", "main.s"]
+        .iter()
+        .map(|name| Assembler::new(&mut config).assemble(name).unwrap())
+        .collect();
+    let combined = Linker::new(&mut config).combine(objects);
+    let program = Linker::new(&mut config).link(vec![combined]).unwrap();
+    assert_eq!(
+        *Vec::from(program.sections).pop().unwrap().data,
+        [0x30, 0x80]
+    )
+}
+
+#[test]
+fn strong_definition_overrides_weak_definition() {
+    let mut fs = Buffers(&[
+        (
+            "engine.s",
+            "        WEAK    on_vblank, on_timer
+on_vblank EQU $1000
+on_timer EQU $2000
+        DW      on_vblank, on_timer
+",
+        ),
+        ("game.s", "on_vblank EQU $1234\n"),
+    ]);
+    let mut config = Config {
+        input: InputConfig::Custom(&mut fs),
+        diagnostics: DiagnosticsConfig::Ignore,
+        print: PrintConfig::default(),
+    };
+    for names in &[["engine.s", "game.s"], ["game.s", "engine.s"]] {
+        let objects: Vec<_> = names
+            .iter()
+            .map(|name| Assembler::new(&mut config).assemble(name).unwrap())
+            .collect();
+        let program = Linker::new(&mut config).link(objects).unwrap();
+        assert_eq!(
+            *Vec::from(program.sections).pop().unwrap().data,
+            [0x34, 0x12, 0x00, 0x20]
+        )
+    }
+}
+
+#[test]
+fn discard_unreachable_sections() {
+    let src = r"
+main    SECTION ROM0($150)
+        CALL    helper
+        DB      LEN
+helpers SECTION ROM0
+helper
+        RET
+unused  SECTION ROM0
+LEN     EQU     4
+        NOP
+kept    SECTION ROMX, KEEP
+        NOP
+";
+    let mut fs = Buffers(&[("game.s", src)]);
+    let mut config = Config {
+        input: InputConfig::Custom(&mut fs),
+        diagnostics: DiagnosticsConfig::Ignore,
+        print: PrintConfig::default(),
+    };
+    let object = Assembler::new(&mut config).assemble("game.s").unwrap();
+    let program = Linker::new(&mut config)
+        .gc_sections(true)
+        .link(vec![object])
+        .unwrap();
+    assert_eq!(*program.discarded, ["unused".into()]);
+    assert_eq!(
+        Vec::from(program.sections)
+            .into_iter()
+            .map(|chunk| (chunk.addr, chunk.data.into_vec()))
+            .collect::<Vec<_>>(),
+        [
+            (0x150, vec![0xcd, 0x00, 0x00, 0x04]),
+            (0x0000, vec![0xc9]),
+            (0x4000, vec![0x00]),
+        ]
+    )
+}
+
+#[test]
+fn report_gaps_around_data_and_reserved_space() {
+    let src = "code    SECTION ROM0($100)\n        NOP\n        DS      3\n        NOP\n";
+    let mut fs = Buffers(&[("game.s", src)]);
+    let mut config = Config {
+        input: InputConfig::Custom(&mut fs),
+        diagnostics: DiagnosticsConfig::Ignore,
+        print: PrintConfig::default(),
+    };
+    let object = Assembler::new(&mut config).assemble("game.s").unwrap();
+    let program = Linker::new(&mut config).link(vec![object]).unwrap();
+    assert_eq!(program.reserved.len(), 1);
+    assert_eq!(program.reserved[0], 0x101..0x104);
+    assert_eq!(
+        program.gaps(),
+        [
+            Gap {
+                bank: 0,
+                addr: 0x0000,
+                len: 0x100
+            },
+            Gap {
+                bank: 0,
+                addr: 0x0105,
+                len: 0x3efb
+            },
+            Gap {
+                bank: 1,
+                addr: 0x4000,
+                len: 0x4000
+            },
+        ]
+    )
+}
+
+#[test]
+fn diagnose_error_in_second_file_of_combined_object() {
+    let mut fs = Buffers(&[
+        ("first.s", "        NOP\n"),
+        ("second.s", "        NOP\n        DB      $100\n"),
+    ]);
+    let mut diagnostics = vec![];
+    let mut output = |diagnostic: Diagnostic| diagnostics.push(diagnostic.to_string());
+    let mut config = Config {
+        input: InputConfig::Custom(&mut fs),
+        diagnostics: DiagnosticsConfig::Output(&mut output),
+        print: PrintConfig::default(),
+    };
+    let objects: Vec<_> = ["first.s", "second.s"]
+        .iter()
+        .map(|name| Assembler::new(&mut config).assemble(name).unwrap())
+        .collect();
+    let combined = Linker::new(&mut config).combine(objects);
+    Linker::new(&mut config).link(vec![combined]);
+    assert_eq!(
+        diagnostics,
+        ["second.s:2: error: value 256 cannot be represented in a byte\n        DB      $100\n                ^^^^\n"]
+    )
+}
+
+#[test]
+fn diagnose_anonymous_label_ref_without_label() {
+    let (_, diagnostics) = assemble_snippet("        NOP\n        JR      :-\n");
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:2: error: no label matches reference `:-`\n        JR      :-\n                ^^\n"]
+    )
+}
+
+#[test]
+fn diagnose_forward_anonymous_label_ref_once() {
+    let (_, diagnostics) = assemble_snippet("        JR      :+\n        NOP\n");
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:1: error: no label matches reference `:+`\n        JR      :+\n                ^^\n"]
+    )
+}
+
+#[test]
+fn diagnose_failed_assertion_at_link_time() {
+    let (_, diagnostics) = assemble_snippet("        NOP\n        ASSERT  . == 0, \"moved\"\n");
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:2: error: assertion failed: moved\n        ASSERT  . == 0, \"moved\"\n        ^^^^^^\n"]
+    )
+}
+
+#[test]
+fn diagnose_assertion_on_location_before_first_section() {
+    let src = "        ASSERT  . == 0\ncode    SECTION ROM0\n        NOP\n";
+    let (_, diagnostics) = assemble_snippet(src);
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:1: error: assertion condition is not a constant\n        ASSERT  . == 0\n        ^^^^^^\n"]
+    )
+}
+
+#[test]
+fn diagnose_misaligned_section_addr() {
+    let (_, diagnostics) = assemble_snippet("code    SECTION ROM0($151), ALIGN(4)\n        NOP\n");
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:1: error: address $0151 is not aligned to 16 bytes\ncode    SECTION ROM0($151), ALIGN(4)\n                     ^^^^\n"]
+    )
+}
+
+#[test]
+fn diagnose_data_in_ram_section() {
+    let src = "code    SECTION ROM0\n        NOP\nvars    SECTION WRAM0\n        DB      1, 2\n";
+    let mut fs = SingleBuffer::new("game.s", src);
+    let mut diagnostics = vec![];
+    let mut output = |diagnostic: Diagnostic| diagnostics.push(diagnostic.to_string());
+    let mut config = Config {
+        input: InputConfig::Custom(&mut fs),
+        diagnostics: DiagnosticsConfig::Output(&mut output),
+        print: PrintConfig::default(),
+    };
+    let object = Assembler::new(&mut config).assemble("game.s").unwrap();
+    let program = Linker::new(&mut config).link(vec![object]).unwrap();
+    assert_eq!(program.sections.len(), 1);
+    assert_eq!(program.sections[0].addr, 0x0000);
+    assert_eq!(program.into_rom().len(), 0x8000);
+    assert_eq!(
+        diagnostics,
+        ["game.s:3: error: `WRAM0` sections cannot contain data\nvars    SECTION WRAM0\n^^^^\n"]
+    )
+}
+
+#[test]
+fn place_floating_sections_in_same_bank() {
+    let src = "one     SECTION ROMX, BANK(2)\n        NOP\n        NOP\ntwo     SECTION ROMX, BANK(2), ALIGN(4)\n        RET\nthree   SECTION ROM0\n        DW      two\n";
+    let mut fs = SingleBuffer::new("game.s", src);
+    let mut config = Config {
+        input: InputConfig::Custom(&mut fs),
+        diagnostics: DiagnosticsConfig::Ignore,
+        print: PrintConfig::default(),
+    };
+    let object = Assembler::new(&mut config).assemble("game.s").unwrap();
+    let program = Linker::new(&mut config).link(vec![object]).unwrap();
+    assert_eq!(
+        program
+            .sections
+            .iter()
+            .map(|chunk| (chunk.addr, &*chunk.data))
+            .collect::<Vec<_>>(),
+        [
+            (0x8000, &[NOP, NOP][..]),
+            (0x8010, &[0xc9][..]),
+            (0x0000, &[0x10, 0x40][..])
+        ]
+    )
+}
+
+#[test]
+fn diagnose_overlapping_sections() {
+    let (_, diagnostics) = assemble_snippet(
+        "one     SECTION ROM0($100)\n        NOP\n        NOP\ntwo     SECTION ROM0($101)\n        NOP\n",
+    );
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:4: error: section overlaps another section at $0101\ntwo     SECTION ROM0($101)\n                     ^^^^\n"]
+    )
+}
+
+#[test]
+fn diagnose_literal_overflow() {
+    let (_, diagnostics) = assemble_snippet("        NOP\n        DW      4294967296\n");
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:2: error: literal does not fit in 32 bits\n        DW      4294967296\n                ^^^^^^^^^^\n"]
+    )
+}
+
+#[test]
+fn diagnose_division_by_zero() {
+    let (_, diagnostics) = assemble_snippet("        NOP\n        DW      1 / 0\n");
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:2: error: division by zero\n        DW      1 / 0\n                  ^\n"]
+    )
+}
+
+#[test]
+fn diagnose_fixed_point_division_by_zero() {
+    let (_, diagnostics) = assemble_snippet("        NOP\n        DW      DIV(1.0, 0)\n");
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:2: error: division by zero\n        DW      DIV(1.0, 0)\n                   ^^^^^^^^\n"]
+    )
+}
+
+#[test]
+fn diagnose_unresolved_rst_target() {
+    let (_, diagnostics) = assemble_snippet("        RST     UNDEF\n");
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:1: error: symbol `UNDEF` could not be resolved\n        RST     UNDEF\n                ^^^^^\n"]
+    )
+}
+
+#[test]
+fn diagnose_word_out_of_range() {
+    let (_, diagnostics) = assemble_snippet("        NOP\n        DW      $10000\n");
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:2: error: value 65536 cannot be represented in a word\n        DW      $10000\n                ^^^^^^\n"]
+    )
+}
+
+#[test]
+fn diagnose_relative_jump_out_of_range() {
+    let (_, diagnostics) =
+        assemble_snippet("        JR      far\n        DS      200\nfar     NOP\n");
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:1: error: jump target out of range by 73 bytes, consider using `JP`\n        JR      far\n                ^^^\n"]
+    )
+}
+
+#[test]
+fn diagnose_function_arg_count() {
+    let (_, diagnostics) = assemble_snippet("F(x, y) EQU x + y\n        DB      F(1)\n");
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:2: error: `F` expects 2 arguments, found 1\n        DB      F(1)\n                ^\n"]
+    )
+}
+
+#[test]
+fn diagnose_unmatched_pushs() {
+    let (_, diagnostics) = assemble_snippet("        NOP\n        PUSHS\n");
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:2: error: `PUSHS` without matching `POPS`\n        PUSHS\n        ^^^^^\n"]
+    )
+}
+
+#[test]
+fn emit_warning_from_warn_directive() {
+    let (assembled, diagnostics) = assemble_snippet("        WARN    \"careful\"\n        NOP\n");
+    assert_eq!(*assembled.unwrap(), [NOP]);
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>(),
+        ["__buffer:1: warning: careful\n        WARN    \"careful\"\n        ^^^^\n"]
+    )
+}
+
+#[test]
+fn print_to_custom_sink() {
+    let name = "__buffer";
+    let mut fs = SingleBuffer::new(
+        name,
+        "N EQU 3\n        PRINT \"N\"\n        PRINTLN \" = \", N * 2\n",
+    );
+    let mut printed = String::new();
+    let mut print = |text: &str| printed.push_str(text);
+    let mut config = Config {
+        input: InputConfig::Custom(&mut fs),
+        diagnostics: DiagnosticsConfig::Ignore,
+        print: PrintConfig::Custom(&mut print),
+    };
+    Assembler::new(&mut config).assemble(name);
+    assert_eq!(printed, "N = 6\n")
+}
+
+#[test]
+fn expand_string_symbol_into_instruction() {
+    assert_eq!(
+        assemble_snippet(
+            r#"
+BYTES   EQUS    "DB 1, 2"
+        BYTES
+"#
+        ),
+        (Some(vec![0x01, 0x02].into()), vec![])
+    )
+}
+
+#[test]
+fn interpolate_symbols_into_printed_string() {
+    let name = "__buffer";
+    let mut fs = SingleBuffer::new(
+        name,
+        r#"
+BUILD   EQU     42
+VERSION EQUS    "1.{d:BUILD}"
+        PRINTLN "v{VERSION} (${04X:BUILD})"
+"#,
+    );
+    let mut printed = String::new();
+    let mut print = |text: &str| printed.push_str(text);
+    let mut config = Config {
+        input: InputConfig::Custom(&mut fs),
+        diagnostics: DiagnosticsConfig::Ignore,
+        print: PrintConfig::Custom(&mut print),
+    };
+    Assembler::new(&mut config).assemble(name);
+    assert_eq!(printed, "v1.42 ($002A)\n")
+}
+
+#[test]
+fn build_strings_with_string_builtins() {
+    let name = "__buffer";
+    let mut fs = SingleBuffer::new(
+        name,
+        r#"
+NAME    EQUS    "hero"
+        PRINTLN strupr("{NAME}"), " ", strlen("{NAME}")
+TITLE   EQUS    strcat("SUPER ", strsub("{NAME}", 1, 2))
+        PRINTLN "{TITLE}", strin("{TITLE}", "he"), strcmp("a", "b")
+"#,
+    );
+    let mut printed = String::new();
+    let mut print = |text: &str| printed.push_str(text);
+    let mut config = Config {
+        input: InputConfig::Custom(&mut fs),
+        diagnostics: DiagnosticsConfig::Ignore,
+        print: PrintConfig::Custom(&mut print),
+    };
+    Assembler::new(&mut config).assemble(name);
+    assert_eq!(printed, "HERO 4\nSUPER he7-1\n")
+}
+
 const NOP: u8 = 0x00;
 
 fn assemble_snippet(src: &str) -> (Option<Box<[u8]>>, Vec<Diagnostic>) {
@@ -89,6 +794,7 @@ fn assemble_snippet(src: &str) -> (Option<Box<[u8]>>, Vec<Diagnostic>) {
     let mut config = Config {
         input: InputConfig::Custom(&mut fs),
         diagnostics: DiagnosticsConfig::Output(&mut output),
+        print: PrintConfig::default(),
     };
     let object = {
         let mut assembler = Assembler::new(&mut config);
@@ -99,7 +805,7 @@ fn assemble_snippet(src: &str) -> (Option<Box<[u8]>>, Vec<Diagnostic>) {
         linker.link(vec![object])
     });
     (
-        program.map(|program| Vec::from(program.sections).pop().unwrap().data.into()),
+        program.map(|program| Vec::from(program.sections).pop().unwrap().data),
         diagnostics,
     )
 }
@@ -124,3 +830,12 @@ impl<'a> FileSystem for SingleBuffer<'a> {
         }
     }
 }
+
+struct Buffers<'a>(&'a [(&'a str, &'a str)]);
+
+impl<'a> FileSystem for Buffers<'a> {
+    fn read_file(&self, name: &str) -> Result<Vec<u8>, io::Error> {
+        let (_, src) = self.0.iter().find(|(buffer, _)| *buffer == name).unwrap();
+        Ok(src.bytes().collect())
+    }
+}